@@ -191,7 +191,9 @@ use bevy::{
     ecs::{intern::Interned, schedule::ScheduleLabel},
 };
 use bevy_animation_graph_builtin_nodes::BuiltinNodesPlugin;
-use bevy_animation_graph_core::plugin::AnimationGraphCorePlugin;
+use bevy_animation_graph_core::{
+    deterministic::DeterministicEvaluation, plugin::AnimationGraphCorePlugin,
+};
 
 pub mod core {
     pub use bevy_animation_graph_core::*;
@@ -205,6 +207,7 @@ pub mod builtin_nodes {
 pub struct AnimationGraphPlugin {
     physics_schedule: Interned<dyn ScheduleLabel>,
     final_schedule: Interned<dyn ScheduleLabel>,
+    deterministic: Option<DeterministicEvaluation>,
 }
 
 impl Default for AnimationGraphPlugin {
@@ -212,6 +215,7 @@ impl Default for AnimationGraphPlugin {
         Self {
             physics_schedule: FixedPostUpdate.intern(),
             final_schedule: PostUpdate.intern(),
+            deterministic: None,
         }
     }
 }
@@ -223,14 +227,22 @@ impl AnimationGraphPlugin {
             ..Default::default()
         }
     }
+
+    /// Enables the deterministic evaluation mode, see [`DeterministicEvaluation`]. The physics
+    /// schedule should be a fixed timestep schedule, like the default one.
+    pub fn with_deterministic_evaluation(mut self, settings: DeterministicEvaluation) -> Self {
+        self.deterministic = Some(settings);
+        self
+    }
 }
 
 impl Plugin for AnimationGraphPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(AnimationGraphCorePlugin {
-            physics_schedule: self.physics_schedule,
-            final_schedule: self.final_schedule,
-        });
+        let mut core = AnimationGraphCorePlugin::new(self.physics_schedule, self.final_schedule);
+        if let Some(deterministic) = &self.deterministic {
+            core = core.with_deterministic_evaluation(deterministic.clone());
+        }
+        app.add_plugins(core);
 
         app.add_plugins(BuiltinNodesPlugin);
     }
@@ -191,6 +191,7 @@ impl NodeLike for BlendNode {
                 interpolator.interpolate_pose(&mut base, &overlay);
             }
         };
+        ctx.recycle_pose(overlay);
 
         ctx.set_time(base.timestamp);
         ctx.set_data_fwd(Self::OUT_POSE, base);
@@ -0,0 +1,221 @@
+use bevy::{
+    color::LinearRgba,
+    math::Quat,
+    reflect::{Reflect, std_traits::ReflectDefault},
+    transform::components::Transform,
+};
+use bevy_animation_graph_core::{
+    animation_clip::EntityPath,
+    animation_node::{NodeLike, ReflectNodeLike},
+    context::{new_context::NodeContext, spec_context::SpecContext},
+    edge_data::DataSpec,
+    errors::GraphError,
+    id::BoneId,
+    ik::{
+        chain::{ChainIKSettings, IKChain, IKJoint},
+        joint_limit::JointLimit,
+    },
+    pose::Pose,
+    skeleton::Skeleton,
+    space_conversion::SpaceConversionContext,
+};
+use serde::{Deserialize, Serialize};
+
+/// Limit of a bone of the chain
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default)]
+#[reflect(Default)]
+pub struct BoneJointLimit {
+    pub bone: EntityPath,
+    pub limit: JointLimit,
+}
+
+#[derive(Reflect, Clone, Debug)]
+#[reflect(Default, NodeLike)]
+#[type_path = "bevy_animation_graph::builtin_nodes"]
+pub struct ChainIKNode {
+    /// Number of bones above the target bone that are rotated. Not used if the root bone input
+    /// is connected.
+    pub chain_length: usize,
+    pub settings: ChainIKSettings,
+    pub joint_limits: Vec<BoneJointLimit>,
+}
+
+impl Default for ChainIKNode {
+    fn default() -> Self {
+        Self {
+            chain_length: 2,
+            settings: ChainIKSettings::default(),
+            joint_limits: Vec::new(),
+        }
+    }
+}
+
+impl ChainIKNode {
+    pub const IN_TIME: &'static str = "time";
+    pub const IN_POSE: &'static str = "pose";
+    pub const OUT_POSE: &'static str = "pose";
+    pub const TARGETBONE: &'static str = "target_path";
+    /// Optional, first bone of the chain
+    pub const ROOTBONE: &'static str = "root_path";
+    pub const TARGETPOS: &'static str = "target_position";
+    /// Optional, rotation of the target bone in character space
+    pub const TARGETROT: &'static str = "target_rotation";
+
+    pub fn new(
+        chain_length: usize,
+        settings: ChainIKSettings,
+        joint_limits: Vec<BoneJointLimit>,
+    ) -> Self {
+        Self {
+            chain_length,
+            settings,
+            joint_limits,
+        }
+    }
+
+    /// Bones of the chain, from the first rotated bone to the target bone. The skeleton root
+    /// cannot be part of the chain, as character space is the space of the root bone.
+    fn chain_bones(
+        &self,
+        skeleton: &Skeleton,
+        target_bone: BoneId,
+        root_bone: Option<BoneId>,
+    ) -> Option<Vec<BoneId>> {
+        let mut bones = vec![target_bone];
+        let mut current = target_bone;
+        while root_bone.map_or(bones.len() <= self.chain_length, |root| current != root) {
+            let parent = skeleton.parent(&current)?;
+            if parent == skeleton.root() {
+                if root_bone.is_some() {
+                    return None;
+                }
+                break;
+            }
+            bones.push(parent);
+            current = parent;
+        }
+        bones.reverse();
+
+        (bones.len() >= 2 && skeleton.parent(&bones[0]).is_some()).then_some(bones)
+    }
+}
+
+impl NodeLike for ChainIKNode {
+    fn duration(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        let duration = ctx.duration_back(Self::IN_TIME)?;
+        ctx.set_duration_fwd(duration);
+        Ok(())
+    }
+
+    fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        if let Ok(input) = ctx.time_update_fwd() {
+            ctx.set_time_update_back(Self::IN_TIME, input);
+        }
+
+        let target = ctx.data_back(Self::TARGETBONE)?.into_entity_path()?.id();
+        let root = ctx
+            .data_back(Self::ROOTBONE)
+            .ok()
+            .map(|root| root.into_entity_path())
+            .transpose()?
+            .map(|root| root.id());
+        let target_position = ctx.data_back(Self::TARGETPOS)?.into_vec3()?;
+        let target_rotation = ctx
+            .data_back(Self::TARGETROT)
+            .ok()
+            .map(|rotation| rotation.as_quat())
+            .transpose()?;
+        let mut pose = ctx.data_back(Self::IN_POSE)?.into_pose()?;
+        let Some(skeleton) = ctx
+            .graph_context
+            .resources
+            .skeleton_assets
+            .get(&pose.skeleton)
+        else {
+            return Err(GraphError::SkeletonMissing(ctx.node_id));
+        };
+
+        if let Some(bones) = self.chain_bones(skeleton, target, root) {
+            let space_conversion = ctx.graph_context.space_conversion();
+            let mut chain = IKChain {
+                base: space_conversion.character_transform_of_bone(
+                    &pose,
+                    skeleton,
+                    skeleton.parent(&bones[0]).unwrap(),
+                ),
+                joints: bones
+                    .iter()
+                    .map(|bone| IKJoint {
+                        local: local_transform(&pose, skeleton, space_conversion, *bone),
+                        rest_rotation: skeleton
+                            .default_transforms(*bone)
+                            .map_or(Quat::IDENTITY, |rest| rest.local.rotation),
+                        limit: self
+                            .joint_limits
+                            .iter()
+                            .find(|limit| limit.bone.id() == *bone)
+                            .map(|limit| limit.limit),
+                    })
+                    .collect(),
+            };
+
+            chain.solve(target_position, &self.settings);
+            if let Some(rotation) = target_rotation {
+                chain.set_end_rotation(rotation);
+            }
+
+            for (bone, joint) in bones.iter().zip(&chain.joints) {
+                let index = pose.insert_bone(*bone);
+                pose.set_rotation(index, joint.local.rotation);
+            }
+
+            // Debug render (if enabled)
+            for bone in bones {
+                ctx.graph_context.use_debug_gizmos(|mut gizmos| {
+                    gizmos.bone_gizmo(bone, LinearRgba::BLUE, false, skeleton, Some(&pose))
+                });
+            }
+        }
+
+        ctx.set_time(pose.timestamp);
+        ctx.set_data_fwd(Self::OUT_POSE, pose);
+        Ok(())
+    }
+
+    fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+        ctx //
+            .add_input_data(Self::TARGETBONE, DataSpec::EntityPath)
+            .add_input_data(Self::ROOTBONE, DataSpec::EntityPath)
+            .add_input_data(Self::TARGETPOS, DataSpec::Vec3)
+            .add_input_data(Self::TARGETROT, DataSpec::Quat)
+            .add_input_data(Self::IN_POSE, DataSpec::Pose)
+            .add_input_time(Self::IN_TIME);
+        ctx //
+            .add_output_data(Self::OUT_POSE, DataSpec::Pose)
+            .add_output_time();
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        "Chain IK".into()
+    }
+}
+
+/// Local transform of a bone in the pose, with the values it does not have taken from the scene
+fn local_transform(
+    pose: &Pose,
+    skeleton: &Skeleton,
+    space_conversion: SpaceConversionContext,
+    bone: BoneId,
+) -> Transform {
+    let fallback = space_conversion
+        .pose_fallback
+        .local_transform(bone)
+        .or_else(|| skeleton.default_transforms(bone).map(|rest| rest.local))
+        .unwrap_or_default();
+    match pose.bone_index(&bone) {
+        Some(index) => pose.transform_with_base(index, fallback),
+        None => fallback,
+    }
+}
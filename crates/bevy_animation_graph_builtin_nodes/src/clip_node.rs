@@ -10,7 +10,7 @@ use bevy::{
     reflect::prelude::*,
 };
 use bevy_animation_graph_core::{
-    animation_clip::{EntityPath, GraphClip, Interpolation},
+    animation_clip::{EntityPath, GraphClip, Interpolation, sampling::BoneSample},
     animation_graph::TimeUpdate,
     animation_node::{NodeLike, ReflectNodeLike},
     context::{new_context::NodeContext, spec_context::SpecContext},
@@ -21,7 +21,7 @@ use bevy_animation_graph_core::{
     errors::GraphError,
     event_track::sample_tracks,
     id::BoneId,
-    pose::{BonePose, Pose, RootMotionDelta, RootMotionMode},
+    pose::{Pose, RootMotionDelta, RootMotionMode},
 };
 
 #[derive(Reflect, Clone, Debug, Default)]
@@ -30,6 +30,9 @@ use bevy_animation_graph_core::{
 pub struct ClipNode {
     pub(crate) clip: Handle<GraphClip>,
     pub(crate) override_duration: Option<f32>,
+    /// Interpolation to sample the clip with instead of the one of its keyframes. Only applies to
+    /// clips built from keyframes, such as keyframe, BVH and baked clips. glTF clips are sampled
+    /// with the interpolation of the file.
     pub(crate) override_interpolation: Option<Interpolation>,
     /// Controls whether and how root motion is extracted from this clip.
     pub(crate) root_motion_mode: RootMotionMode,
@@ -69,6 +72,17 @@ impl ClipNode {
         }
     }
 
+    /// Bone root motion is extracted from: the configured one, or the skeleton's root bone
+    fn root_motion_bone_id(&self, ctx: &NodeContext, clip: &GraphClip) -> Option<BoneId> {
+        self.root_motion_bone.as_ref().map(|p| p.id()).or_else(|| {
+            ctx.graph_context
+                .resources
+                .skeleton_assets
+                .get(&clip.skeleton)
+                .map(|s| s.root())
+        })
+    }
+
     pub fn update_time(&self, ctx: &NodeContext, input: &TimeUpdate) -> Result<f32, GraphError> {
         let prev_time = ctx.prev_time();
 
@@ -87,7 +101,19 @@ impl ClipNode {
 
 impl NodeLike for ClipNode {
     fn duration(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
-        ctx.set_duration_fwd(Some(self.clip_duration(&ctx)?));
+        // Clips with a loop range never end
+        let loops = ctx
+            .graph_context
+            .resources
+            .graph_clip_assets
+            .get(&self.clip)
+            .is_some_and(|clip| clip.loop_range.is_some());
+        let duration = if loops {
+            None
+        } else {
+            Some(self.clip_duration(&ctx)?)
+        };
+        ctx.set_duration_fwd(duration);
         Ok(())
     }
 
@@ -109,71 +135,114 @@ impl NodeLike for ClipNode {
         let time = self.update_time(&ctx, &time_update)?;
         ctx.set_time(time);
 
+        // Once its end is reached, the loop range of the clip repeats
+        let sample_time = clip.loop_range.map_or(time, |range| range.wrap(time));
+
         // Sample events
         let mut event_queue =
-            EventQueue::with_events(sample_tracks(clip.event_tracks.values(), time));
+            EventQueue::with_events(sample_tracks(clip.event_tracks.values(), sample_time));
 
-        let mut out_pose = Pose {
-            timestamp: time,
-            skeleton: clip.skeleton.clone(),
-            ..Pose::default()
-        };
+        let mut out_pose = ctx.new_pose(&clip.skeleton);
+        out_pose.timestamp = time;
 
-        if time > clip_duration {
+        if sample_time > clip_duration {
             event_queue.add_event(SampledEvent::instant(AnimationEvent::AnimationClipFinished));
         }
 
-        let clamped_time = time.clamp(0., clip_duration);
-
-        for (bone_id, curves) in &clip.curves {
-            let mut bone_pose = BonePose::default();
-            for curve in curves {
-                let value = sample_animation_curve(curve, clamped_time);
-                match value {
-                    CurveValue::Translation(t) => bone_pose.translation = Some(t),
-                    CurveValue::Rotation(r) => bone_pose.rotation = Some(r),
-                    CurveValue::Scale(s) => bone_pose.scale = Some(s),
-                    CurveValue::BoneWeights(w) => bone_pose.weights = Some(w),
+        let clamped_time = sample_time.clamp(0., clip_duration);
+
+        // The pose of a culled player is not shown, only root motion is still needed
+        let root_motion_bone_id = (self.root_motion_mode != RootMotionMode::Disabled)
+            .then(|| self.root_motion_bone_id(&ctx, clip))
+            .flatten();
+        let skip_bone =
+            |bone_id: BoneId| ctx.graph_context.culled && Some(bone_id) != root_motion_bone_id;
+
+        if clip.keyframes().is_some() {
+            // Keyframes are sampled instead of the curves built from them, as only keyframes can
+            // be sampled with another interpolation
+            let keyframe_time = keyframe_time(clip, clamped_time);
+            for (bone_id, bone) in clip.keyframe_bones() {
+                if skip_bone(bone_id) {
+                    continue;
+                }
+
+                let bone_index = out_pose.insert_bone(bone_id);
+                let sample = bone.sample(keyframe_time, self.override_interpolation);
+                for value in keyframe_values(sample) {
+                    set_curve_value(&mut out_pose, bone_index, value);
+                }
+            }
+        } else {
+            if self.override_interpolation.is_some() {
+                bevy::log::warn_once!(
+                    "The interpolation override of a clip node is ignored, as its clip has no \
+                     keyframes (e.g. it was loaded from glTF)"
+                );
+            }
+            for (bone_id, curves) in &clip.curves {
+                let bone_id = BoneId::from(*bone_id);
+                if skip_bone(bone_id) {
+                    continue;
+                }
+
+                let bone_index = out_pose.insert_bone(bone_id);
+                for value in curves
+                    .iter()
+                    .filter_map(|curve| sample_animation_curve(curve, clamped_time))
+                {
+                    set_curve_value(&mut out_pose, bone_index, value);
                 }
             }
-            out_pose.add_bone(bone_pose, BoneId::from(*bone_id));
         }
 
         // --- Root motion extraction ---
         if self.root_motion_mode != RootMotionMode::Disabled {
-            let root_bone_id = self.root_motion_bone.as_ref().map(|p| p.id()).or_else(|| {
-                ctx.graph_context
-                    .resources
-                    .skeleton_assets
-                    .get(&clip.skeleton)
-                    .map(|s| s.root())
-            });
+            let root_bone_id = self.root_motion_bone_id(&ctx, clip);
 
             if let Some(root_bone_id) = root_bone_id {
                 let target_id = root_bone_id.animation_target_id();
 
                 // Sample root bone at current time (already done above)
-                let current_bone = out_pose.get_bone(root_bone_id);
-                let current_translation = current_bone
-                    .and_then(|b| b.translation)
+                let root_bone_index = out_pose
+                    .bone_index(&root_bone_id)
+                    .filter(|index| out_pose.contains_bone(*index));
+                let current_translation = root_bone_index
+                    .and_then(|index| out_pose.translation(index))
                     .unwrap_or(Vec3::ZERO);
-                let current_rotation = current_bone
-                    .and_then(|b| b.rotation)
+                let current_rotation = root_bone_index
+                    .and_then(|index| out_pose.rotation(index))
                     .unwrap_or(Quat::IDENTITY);
 
                 // Helper: sample root bone translation/rotation at a given time
-                let has_root_curves = clip.curves.contains_key(&target_id);
+                let root_keyframes = clip
+                    .keyframe_bones()
+                    .find(|(bone_id, _)| *bone_id == root_bone_id)
+                    .map(|(_, bone)| bone);
+                let has_root_curves =
+                    root_keyframes.is_some() || clip.curves.contains_key(&target_id);
                 let sample_root_at = |t: f32| -> (Vec3, Quat) {
+                    let values: Vec<CurveValue> = match root_keyframes {
+                        Some(bone) => keyframe_values(
+                            bone.sample(keyframe_time(clip, t), self.override_interpolation),
+                        )
+                        .collect(),
+                        None => clip
+                            .curves
+                            .get(&target_id)
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|curve| sample_animation_curve(curve, t))
+                            .collect(),
+                    };
+
                     let mut tr = Vec3::ZERO;
                     let mut rot = Quat::IDENTITY;
-                    if let Some(curves) = clip.curves.get(&target_id) {
-                        for curve in curves {
-                            let value = sample_animation_curve(curve, t);
-                            match value {
-                                CurveValue::Translation(v) => tr = v,
-                                CurveValue::Rotation(v) => rot = v,
-                                _ => {}
-                            }
+                    for value in values {
+                        match value {
+                            CurveValue::Translation(v) => tr = v,
+                            CurveValue::Rotation(v) => rot = v,
+                            _ => {}
                         }
                     }
                     (tr, rot)
@@ -190,7 +259,13 @@ impl NodeLike for ClipNode {
                 }
 
                 let prev_time = ctx.prev_time();
+                let prev_time = clip
+                    .loop_range
+                    .map_or(prev_time, |range| range.wrap(prev_time));
                 let clamped_prev_time = prev_time.clamp(0., clip_duration);
+                let (wrap_start, wrap_end) = clip
+                    .loop_range
+                    .map_or((0., clip_duration), |range| (range.start, range.end));
 
                 // Determine whether time is flowing forward or backward.
                 // For absolute/event seeks we don't know the true direction,
@@ -206,9 +281,9 @@ impl NodeLike for ClipNode {
                 let (mut delta_translation, mut delta_rotation) = if flowing_forward {
                     if clamped_time < clamped_prev_time - f32::EPSILON {
                         // Forward wrap: prev -> end, then start -> current
-                        let (end_tr, end_rot) = sample_root_at(clip_duration);
+                        let (end_tr, end_rot) = sample_root_at(wrap_end);
                         let (prev_tr, prev_rot) = sample_root_at(clamped_prev_time);
-                        let (start_tr, start_rot) = sample_root_at(0.0);
+                        let (start_tr, start_rot) = sample_root_at(wrap_start);
 
                         let dt1 = end_tr - prev_tr;
                         let dr1 = prev_rot.inverse() * end_rot;
@@ -228,9 +303,9 @@ impl NodeLike for ClipNode {
                     // Backward playback
                     if clamped_time > clamped_prev_time + f32::EPSILON {
                         // Backward wrap: prev -> start, then end -> current
-                        let (start_tr, start_rot) = sample_root_at(0.0);
+                        let (start_tr, start_rot) = sample_root_at(wrap_start);
                         let (prev_tr, prev_rot) = sample_root_at(clamped_prev_time);
-                        let (end_tr, end_rot) = sample_root_at(clip_duration);
+                        let (end_tr, end_rot) = sample_root_at(wrap_end);
 
                         let dt1 = start_tr - prev_tr;
                         let dr1 = prev_rot.inverse() * start_rot;
@@ -263,15 +338,15 @@ impl NodeLike for ClipNode {
                 match self.root_motion_mode {
                     RootMotionMode::Full => {
                         // Use full delta, zero root bone completely
-                        if let Some(bone_idx) = out_pose.paths.get(&root_bone_id).copied() {
-                            out_pose.bones[bone_idx].translation = Some(rest_translation);
-                            out_pose.bones[bone_idx].rotation = Some(rest_rotation);
+                        if let Some(bone_idx) = root_bone_index {
+                            out_pose.set_translation(bone_idx, rest_translation);
+                            out_pose.set_rotation(bone_idx, rest_rotation);
                         } else {
                             bevy::log::warn!(
                                 "Root motion: could not find root bone in pose for zeroing. \
                                  root_bone_id={:?}, pose has {} bones",
                                 root_bone_id,
-                                out_pose.paths.len()
+                                out_pose.bone_count()
                             );
                         }
                     }
@@ -288,11 +363,12 @@ impl NodeLike for ClipNode {
                         // Zero only XZ translation in the visual pose.
                         // Keep Y (vertical bob) and full rotation (decomposing and
                         // removing only Y rotation cleanly is complex).
-                        if let Some(bone_idx) = out_pose.paths.get(&root_bone_id).copied()
-                            && let Some(ref mut t) = out_pose.bones[bone_idx].translation
+                        if let Some(bone_idx) = root_bone_index
+                            && let Some(mut t) = out_pose.translation(bone_idx)
                         {
                             t.x = rest_translation.x;
                             t.z = rest_translation.z;
+                            out_pose.set_translation(bone_idx, t);
                         }
                     }
                     RootMotionMode::Disabled => unreachable!(),
@@ -325,7 +401,6 @@ impl NodeLike for ClipNode {
     }
 }
 
-#[allow(dead_code)]
 enum CurveValue {
     Translation(Vec3),
     Rotation(Quat),
@@ -333,10 +408,36 @@ enum CurveValue {
     BoneWeights(Vec<f32>),
 }
 
-/// Sample the animation at a particular time
+fn set_curve_value(pose: &mut Pose, bone_index: usize, value: CurveValue) {
+    match value {
+        CurveValue::Translation(t) => pose.set_translation(bone_index, t),
+        CurveValue::Rotation(r) => pose.set_rotation(bone_index, r),
+        CurveValue::Scale(s) => pose.set_scale(bone_index, s),
+        CurveValue::BoneWeights(w) => pose.set_weights(bone_index, &w),
+    }
+}
+
+fn keyframe_values(sample: BoneSample) -> impl Iterator<Item = CurveValue> {
+    [
+        sample.translation.map(CurveValue::Translation),
+        sample.rotation.map(CurveValue::Rotation),
+        sample.scale.map(CurveValue::Scale),
+        sample.weights.map(CurveValue::BoneWeights),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Time of the keyframes of a clip, which are not trimmed to the range of the clip
+fn keyframe_time(clip: &GraphClip, time: f32) -> f32 {
+    clip.range.map_or(time, |range| time + range.start)
+}
+
+/// Sample the animation at a particular time. Only transform curves can be sampled, morph
+/// weights are only sampled from the keyframes of clips that have them.
 // HACK: We really need some API for sampling animation curves in Bevy outside of the builtin
 // animation flow.
-fn sample_animation_curve(curve: &VariableCurve, time: f32) -> CurveValue {
+fn sample_animation_curve(curve: &VariableCurve, time: f32) -> Option<CurveValue> {
     let evaluator_id = curve.0.evaluator_id();
     let mut evaluator = curve.0.create_evaluator();
 
@@ -366,7 +467,7 @@ fn sample_animation_curve(curve: &VariableCurve, time: f32) -> CurveValue {
                  )
                 };
                 let value = animatable_evaluator.evaluator.stack[0].value;
-                CurveValue::Translation(value)
+                Some(CurveValue::Translation(value))
             } else if id == &rotation_evaluator_id {
                 let animatable_evaluator: &AnimatableCurveEvaluator<Quat> = unsafe {
                     std::mem::transmute(
@@ -376,7 +477,7 @@ fn sample_animation_curve(curve: &VariableCurve, time: f32) -> CurveValue {
                  )
                 };
                 let value = animatable_evaluator.evaluator.stack[0].value;
-                CurveValue::Rotation(value)
+                Some(CurveValue::Rotation(value))
             } else if id == &scale_evaluator_id {
                 let animatable_evaluator: &AnimatableCurveEvaluator<Vec3> = unsafe {
                     std::mem::transmute(
@@ -386,12 +487,12 @@ fn sample_animation_curve(curve: &VariableCurve, time: f32) -> CurveValue {
                  )
                 };
                 let value = animatable_evaluator.evaluator.stack[0].value;
-                CurveValue::Scale(value)
+                Some(CurveValue::Scale(value))
             } else {
-                todo!()
+                None
             }
         }
-        EvaluatorId::Type(_id) => todo!(),
+        EvaluatorId::Type(_) => None,
     }
 }
 
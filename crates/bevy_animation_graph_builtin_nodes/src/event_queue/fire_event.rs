@@ -4,7 +4,7 @@ use bevy_animation_graph_core::{
     context::{new_context::NodeContext, spec_context::SpecContext},
     edge_data::{
         DataSpec,
-        events::{AnimationEvent, EventQueue, SampledEvent},
+        events::{AnimationEvent, EventPayload, EventQueue, SampledEvent},
     },
     errors::GraphError,
 };
@@ -14,6 +14,9 @@ use bevy_animation_graph_core::{
 #[type_path = "bevy_animation_graph::builtin_nodes"]
 pub struct FireEventNode {
     pub event: AnimationEvent,
+    /// Data attached to the fired event
+    #[reflect(default)]
+    pub payload: EventPayload,
 }
 
 impl FireEventNode {
@@ -21,7 +24,15 @@ impl FireEventNode {
     pub const CONDITION_IN: &'static str = "condition";
 
     pub fn new(event: AnimationEvent) -> Self {
-        Self { event }
+        Self {
+            event,
+            payload: EventPayload::None,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: EventPayload) -> Self {
+        self.payload = payload;
+        self
     }
 }
 
@@ -36,7 +47,9 @@ impl NodeLike for FireEventNode {
         if cond {
             ctx.set_data_fwd(
                 Self::EVENT_OUT,
-                EventQueue::with_events([SampledEvent::instant(self.event.clone())]),
+                EventQueue::with_events([
+                    SampledEvent::instant(self.event.clone()).with_payload(self.payload.clone())
+                ]),
             );
         } else {
             ctx.set_data_fwd(Self::EVENT_OUT, EventQueue::with_events([]));
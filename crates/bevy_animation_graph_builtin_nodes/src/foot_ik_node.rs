@@ -0,0 +1,268 @@
+use bevy::{
+    color::LinearRgba,
+    ecs::entity::Entity,
+    math::{Quat, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+use bevy_animation_graph_core::{
+    animation_clip::EntityPath,
+    animation_node::{NodeLike, ReflectNodeLike},
+    context::{
+        new_context::NodeContext, node_states::ReflectGraphStateType, spec_context::SpecContext,
+    },
+    edge_data::{DataSpec, DataValue},
+    errors::GraphError,
+    id::BoneId,
+    ik::{
+        aim::{smoothing_factor, turn_towards},
+        foot::{FootPlacement, FootRaySettings, pelvis_offset},
+        two_bone::two_bone_ik,
+    },
+    skeleton::Skeleton,
+};
+
+/// Smoothed placements of a [`FootIKNode`]
+#[derive(Reflect, Clone, Debug, Default)]
+#[reflect(Default, GraphStateType)]
+pub struct FootIKState {
+    pub pelvis_offset: f32,
+    pub feet: Vec<FootPlacement>,
+}
+
+/// Keeps the feet of a character on the ground. The animation is assumed to have the ground at
+/// the height of the skeleton root.
+///
+/// The ground is found with the [`GroundQuery`] of the system resources, see
+/// [`SystemResources::ground_query`]. Without one, the pose is left as it is. The colliders of
+/// the character's root entity and bone entities are never taken for ground.
+///
+/// [`GroundQuery`]: bevy_animation_graph_core::ik::foot::GroundQuery
+/// [`SystemResources::ground_query`]: bevy_animation_graph_core::context::system_resources::SystemResources::ground_query
+#[derive(Reflect, Clone, Debug)]
+#[reflect(Default, NodeLike)]
+#[type_path = "bevy_animation_graph::builtin_nodes"]
+pub struct FootIKNode {
+    /// Bone that is moved up or down so that the legs reach the ground, usually the hips. It
+    /// cannot be the skeleton root.
+    pub pelvis: EntityPath,
+    /// Foot bones. The parent and grandparent of each foot (the knee and the hip) are rotated
+    /// with two-bone IK.
+    pub feet: Vec<EntityPath>,
+    pub rays: FootRaySettings,
+    /// Largest distance the pelvis is moved up or down
+    pub max_pelvis_offset: f32,
+    /// Whether the feet are rotated to match the slope of the ground
+    pub align_feet: bool,
+    /// Softness of the leg IK, see [`two_bone_ik`]
+    pub softness: f32,
+    /// Time in seconds for the feet and pelvis to move halfway to a new placement. 0 disables
+    /// smoothing.
+    pub smoothing: f32,
+}
+
+impl Default for FootIKNode {
+    fn default() -> Self {
+        Self {
+            pelvis: EntityPath::default(),
+            feet: Vec::new(),
+            rays: FootRaySettings::default(),
+            max_pelvis_offset: 0.5,
+            align_feet: true,
+            softness: 0.,
+            smoothing: 0.05,
+        }
+    }
+}
+
+/// Bones of a leg that foot IK rotates
+struct Leg {
+    foot: BoneId,
+    knee: BoneId,
+    hip: BoneId,
+    /// Global position of the foot in the input pose
+    foot_position: Vec3,
+}
+
+impl FootIKNode {
+    pub const IN_TIME: &'static str = "time";
+    pub const IN_POSE: &'static str = "pose";
+    pub const OUT_POSE: &'static str = "pose";
+    /// Optional, blend factor between the input pose (0) and the placed pose (1)
+    pub const WEIGHT: &'static str = "weight";
+
+    fn legs(&self, skeleton: &Skeleton) -> Vec<(BoneId, BoneId, BoneId)> {
+        self.feet
+            .iter()
+            .filter_map(|foot| {
+                let foot = foot.id();
+                let knee = skeleton.parent(&foot)?;
+                let hip = skeleton
+                    .parent(&knee)
+                    .filter(|hip| *hip != skeleton.root())?;
+                Some((foot, knee, hip))
+            })
+            .collect()
+    }
+}
+
+impl NodeLike for FootIKNode {
+    fn duration(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        let duration = ctx.duration_back(Self::IN_TIME)?;
+        ctx.set_duration_fwd(duration);
+        Ok(())
+    }
+
+    fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        // Smoothing follows the time that passed this frame, jumps in time do not move the feet
+        let delta_time = ctx.time_delta_fwd(Self::IN_TIME)?;
+
+        let weight = ctx
+            .data_back(Self::WEIGHT)
+            .unwrap_or(DataValue::F32(1.))
+            .as_f32()?
+            .clamp(0., 1.);
+        let mut pose = ctx.data_back(Self::IN_POSE)?.into_pose()?;
+        ctx.set_time(pose.timestamp);
+        let Some(skeleton) = ctx
+            .graph_context
+            .resources
+            .skeleton_assets
+            .get(&pose.skeleton)
+        else {
+            return Err(GraphError::SkeletonMissing(ctx.node_id));
+        };
+
+        if let Some(ground) = ctx.graph_context.resources.ground_query() {
+            let space_conversion = ctx.graph_context.space_conversion();
+            let char_to_global = space_conversion.char_to_global_transform(skeleton);
+            let global_to_char = space_conversion.global_to_char_transform(skeleton);
+
+            let legs: Vec<Leg> = self
+                .legs(skeleton)
+                .into_iter()
+                .map(|(foot, knee, hip)| Leg {
+                    foot,
+                    knee,
+                    hip,
+                    foot_position: char_to_global.transform_point(
+                        space_conversion
+                            .character_transform_of_bone(&pose, skeleton, foot)
+                            .translation,
+                    ),
+                })
+                .collect();
+            let character: Vec<Entity> = ctx
+                .graph_context
+                .entity_map
+                .values()
+                .copied()
+                .chain([ctx.graph_context.root_entity])
+                .collect();
+            let placements: Vec<FootPlacement> = legs
+                .iter()
+                .map(|leg| {
+                    let height = leg.foot_position.y - char_to_global.translation.y;
+                    self.rays
+                        .place_foot(ground, leg.foot_position, height, &character)
+                        .unwrap_or_default()
+                })
+                .collect();
+            let target_pelvis_offset = pelvis_offset(&placements, self.max_pelvis_offset);
+
+            let smoothing = smoothing_factor(delta_time, self.smoothing);
+            let state = ctx.state_mut_or_else(|| FootIKState {
+                pelvis_offset: target_pelvis_offset,
+                feet: placements.clone(),
+            })?;
+            state
+                .feet
+                .resize(placements.len(), FootPlacement::default());
+            state.pelvis_offset += (target_pelvis_offset - state.pelvis_offset) * smoothing;
+            for (smoothed, placement) in state.feet.iter_mut().zip(&placements) {
+                smoothed.offset += (placement.offset - smoothed.offset) * smoothing;
+                smoothed.normal = turn_towards(smoothed.normal, placement.normal, smoothing);
+            }
+            let state = state.clone();
+
+            // Vertical offsets are given in global space
+            let global_up =
+                |offset: f32| global_to_char.rotation * (global_to_char.scale * Vec3::Y * offset);
+            let space_conversion = ctx.graph_context.space_conversion();
+
+            let pelvis = self.pelvis.id();
+            if let Some(parent) = skeleton.parent(&pelvis) {
+                let mut pelvis_transform =
+                    space_conversion.character_transform_of_bone(&pose, skeleton, pelvis);
+                pelvis_transform.translation += global_up(state.pelvis_offset * weight);
+                let local =
+                    space_conversion.root_to_bone_space(pelvis_transform, &pose, skeleton, parent);
+                let index = pose.insert_bone(pelvis);
+                pose.set_translation(index, local.translation);
+            }
+
+            let up = (global_to_char.rotation * Vec3::Y).normalize();
+            for (leg, placement) in legs.iter().zip(&state.feet) {
+                let target = global_to_char.transform_point(leg.foot_position)
+                    + global_up(placement.offset * weight);
+                let transform_of =
+                    |bone| space_conversion.character_transform_of_bone(&pose, skeleton, bone);
+                let (foot, knee, hip) = two_bone_ik(
+                    transform_of(leg.foot),
+                    transform_of(leg.knee),
+                    transform_of(leg.hip),
+                    target,
+                    None,
+                    self.softness,
+                );
+                let hip_parent_rotation = skeleton
+                    .parent(&leg.hip)
+                    .map_or(Quat::IDENTITY, |parent| transform_of(parent).rotation);
+
+                let foot_rotation = if self.align_feet {
+                    let normal = (global_to_char.rotation * placement.normal).normalize_or(up);
+                    Quat::IDENTITY.slerp(Quat::from_rotation_arc(up, normal), weight)
+                        * foot.rotation
+                } else {
+                    foot.rotation
+                };
+
+                for (bone, rotation) in [
+                    (leg.hip, hip_parent_rotation.inverse() * hip.rotation),
+                    (leg.knee, hip.rotation.inverse() * knee.rotation),
+                    (leg.foot, knee.rotation.inverse() * foot_rotation),
+                ] {
+                    let index = pose.insert_bone(bone);
+                    pose.set_rotation(index, rotation.normalize());
+                }
+            }
+
+            // Debug render (if enabled)
+            for leg in legs {
+                for bone in [leg.hip, leg.knee, leg.foot] {
+                    ctx.graph_context.use_debug_gizmos(|mut gizmos| {
+                        gizmos.bone_gizmo(bone, LinearRgba::BLUE, false, skeleton, Some(&pose))
+                    });
+                }
+            }
+        }
+
+        ctx.set_data_fwd(Self::OUT_POSE, pose);
+        Ok(())
+    }
+
+    fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+        ctx //
+            .add_input_data(Self::WEIGHT, DataSpec::F32)
+            .add_input_data(Self::IN_POSE, DataSpec::Pose)
+            .add_input_time(Self::IN_TIME);
+        ctx //
+            .add_output_data(Self::OUT_POSE, DataSpec::Pose)
+            .add_output_time();
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        "Foot IK".into()
+    }
+}
@@ -71,3 +71,187 @@ impl NodeLike for FsmNode {
         "⌘ FSM".into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+    use bevy_animation_graph_core::{
+        animation_clip::{
+            EntityPath, GraphClip, Interpolation,
+            keyframes::{BoneKeyframes, ClipKeyframes, KeyframeTrack},
+        },
+        animation_graph::{AnimationGraph, DEFAULT_OUTPUT_POSE, GraphInputPin, NodeId},
+        animation_graph_player::AnimationGraphPlayer,
+        animation_node::AnimationNode,
+        context::spec_context::NodeSpec,
+        edge_data::DataValue,
+        headless::{EvaluationStep, GraphAssets, HeadlessEvaluator},
+        skeleton::Skeleton,
+        state_machine::high_level::{
+            DirectTransition, DirectTransitionId, State, StateId, TransitionData, TransitionKind,
+            condition::{CompareOp, TransitionCondition},
+        },
+    };
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{blend_node::BlendNode, clip_node::ClipNode};
+
+    fn bone() -> EntityPath {
+        EntityPath::from_slashed_string("root/bone".into())
+    }
+
+    /// Clip that turns the bone around the given axis
+    fn clip(skeleton: Handle<Skeleton>, axis: Vec3) -> GraphClip {
+        let mut rotation = KeyframeTrack::new(Interpolation::Linear);
+        rotation.push(0., Quat::IDENTITY);
+        rotation.push(0.5, Quat::from_axis_angle(axis, 1.));
+        rotation.push(1., Quat::IDENTITY);
+        let keyframes = ClipKeyframes {
+            duration: 1.,
+            bones: vec![BoneKeyframes {
+                rotation: Some(rotation),
+                ..BoneKeyframes::new(bone())
+            }],
+        };
+        GraphClip::from_keyframes(keyframes, skeleton, HashMap::default(), None).unwrap()
+    }
+
+    /// Graph that outputs the pose of the given node
+    fn pose_graph(node: AnimationNode) -> (AnimationGraph, NodeId) {
+        let mut graph = AnimationGraph::new();
+        let id = node.id;
+        graph.add_node(node);
+        graph.add_output_data(DEFAULT_OUTPUT_POSE.into(), DataSpec::Pose);
+        graph.add_output_time();
+        graph.add_output_data_edge(id, DEFAULT_OUTPUT_POSE, DEFAULT_OUTPUT_POSE);
+        graph.add_output_pose_edge(id);
+        (graph, id)
+    }
+
+    /// State machine that goes from playing a clip to blending two clips by the `speed` input
+    fn assets() -> (GraphAssets, Handle<AnimationGraph>, Handle<Skeleton>) {
+        let mut assets = GraphAssets::default();
+        let mut skeleton = Skeleton::default();
+        let root = EntityPath::from_slashed_string("root".into());
+        skeleton.add_bone(root.clone(), Transform::IDENTITY, Transform::IDENTITY);
+        skeleton.add_bone(bone(), Transform::IDENTITY, Transform::IDENTITY);
+        skeleton.set_root(root.id());
+        let skeleton = assets.skeletons.add(skeleton);
+        let clip_a = assets.clips.add(clip(skeleton.clone(), Vec3::X));
+        let clip_b = assets.clips.add(clip(skeleton.clone(), Vec3::Y));
+
+        let (idle, _) = pose_graph(AnimationNode::new(
+            "clip",
+            ClipNode::new(clip_a.clone(), None, None),
+        ));
+
+        let (mut moving, blend) = pose_graph(AnimationNode::new("blend", BlendNode::default()));
+        let clip_a = AnimationNode::new("clip a", ClipNode::new(clip_a, None, None));
+        let clip_b = AnimationNode::new("clip b", ClipNode::new(clip_b, None, None));
+        for (clip, pin) in [(clip_a, "a"), (clip_b, "b")] {
+            let id = clip.id;
+            moving.add_node(clip);
+            moving.add_node_parameter_edge(id, ClipNode::OUT_POSE, blend, format!("pose_{pin}"));
+            moving.add_node_pose_edge(id, blend, format!("time_{pin}"));
+        }
+        let speed = GraphInputPin::Passthrough("speed".into());
+        moving.add_input_data(speed.clone(), DataSpec::F32);
+        moving.add_input_data_edge(speed.clone(), blend, BlendNode::FACTOR);
+
+        let state = |label: &str, id: u128, graph| State {
+            id: StateId::from(Uuid::from_u128(id)),
+            label: label.into(),
+            graph,
+            state_transition: None,
+        };
+        let idle = state("idle", 1, assets.graphs.add(idle));
+        let moving = state("moving", 2, assets.graphs.add(moving));
+        let mut fsm = StateMachine::default();
+        fsm.set_start_state(idle.id);
+        let mut node_spec = NodeSpec::default();
+        node_spec.add_input_data("speed".into(), DataSpec::F32);
+        fsm.set_input_spec(node_spec);
+        fsm.add_transition_unchecked(DirectTransition {
+            id: DirectTransitionId::from(Uuid::from_u128(3)),
+            source: idle.id,
+            target: moving.id,
+            data: TransitionData {
+                kind: TransitionKind::Crossfade {
+                    duration: 0.2,
+                    curve: Default::default(),
+                    sync: Default::default(),
+                },
+                conditions: vec![TransitionCondition::F32 {
+                    input: "speed".into(),
+                    op: CompareOp::More,
+                    value: 0.5,
+                }],
+                ..Default::default()
+            },
+        });
+        fsm.add_state(idle);
+        fsm.add_state(moving);
+        fsm.update_low_level_fsm();
+        let fsm = assets.state_machines.add(fsm);
+
+        let (mut graph, fsm_node) = pose_graph(AnimationNode::new("fsm", FsmNode::new(fsm)));
+        graph.add_input_data(speed.clone(), DataSpec::F32);
+        graph.add_input_data_edge(speed, fsm_node, "speed");
+        let events = GraphInputPin::Passthrough(AnimationGraphPlayer::USER_EVENTS.into());
+        graph.add_input_data(events.clone(), DataSpec::EventQueue);
+        graph.add_input_data_edge(events, fsm_node, LowLevelStateMachine::DRIVER_EVENT_QUEUE);
+        let graph = assets.graphs.add(graph);
+
+        (assets, graph, skeleton)
+    }
+
+    fn run(seed: u64) -> Vec<EvaluationStep> {
+        let (assets, graph, skeleton) = assets();
+        let mut evaluator = HeadlessEvaluator::new(assets, graph, skeleton);
+        evaluator.player_mut().set_seed(seed);
+        (0..40)
+            .map(|frame| {
+                let speed = (frame as f32 / 20.).min(1.);
+                evaluator
+                    .player_mut()
+                    .set_input_data("speed", DataValue::F32(speed));
+                evaluator.step(TimeUpdate::Delta(1. / 60.)).unwrap()
+            })
+            .collect()
+    }
+
+    /// Rotation of the animated bone in each step
+    fn rotations(steps: &[EvaluationStep]) -> Vec<Quat> {
+        steps
+            .iter()
+            .map(|step| {
+                let pose = step.pose().unwrap();
+                pose.rotation(pose.bone_index(&bone().id()).unwrap())
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_runs_of_a_state_machine_are_identical() {
+        let first = run(7);
+        let second = run(7);
+        let bits = |steps: &[EvaluationStep]| -> Vec<_> {
+            steps
+                .iter()
+                .zip(rotations(steps))
+                .map(|(step, rotation)| {
+                    (step.time.to_bits(), rotation.to_array().map(f32::to_bits))
+                })
+                .collect()
+        };
+        assert_eq!(bits(&first), bits(&second));
+
+        // The state machine goes from the first clip, which turns around X, to the blend
+        let axis = |rotation: Quat| rotation.to_axis_angle().0;
+        let rotations = rotations(&first);
+        assert!(axis(rotations[10]).y.abs() < 1e-5);
+        assert!(axis(rotations[39]).y.abs() > 0.1);
+    }
+}
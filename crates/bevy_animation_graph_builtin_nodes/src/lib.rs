@@ -5,6 +5,7 @@ use crate::{
     blend_space_1d_node::BlendSpace1DNode,
     blend_space_node::BlendSpaceNode,
     bool::{and_bool::AndBool, const_bool::ConstBool, not_bool::NotBool, or_bool::OrBool},
+    chain_ik_node::ChainIKNode,
     chain_node::ChainNode,
     clip_node::ClipNode,
     constants::Constants,
@@ -17,14 +18,17 @@ use crate::{
         div_f32::DivF32, mul_f32::MulF32, sub_f32::SubF32,
     },
     flip_lr_node::FlipLRNode,
+    foot_ik_node::{FootIKNode, FootIKState},
     fsm_node::FsmNode,
     graph_node::GraphNode,
+    look_at_node::{LookAtNode, LookAtState},
     loop_node::LoopNode,
     padding::PaddingNode,
     ragdoll::const_ragdoll_config::ConstRagdollConfig,
     replicate_time::ReplicateTimeNode,
     rotation_node::RotationNode,
     speed_node::SpeedNode,
+    spring_node::{SpringNode, SpringState},
     twoboneik_node::TwoBoneIKNode,
     vec3::rotation_arc::RotationArcNode,
 };
@@ -33,6 +37,7 @@ pub mod blend_node;
 pub mod blend_space_1d_node;
 pub mod blend_space_node;
 pub mod bool;
+pub mod chain_ik_node;
 pub mod chain_node;
 pub mod clip_node;
 pub mod const_entity_path;
@@ -42,9 +47,11 @@ pub mod event_markup_node;
 pub mod event_queue;
 pub mod f32;
 pub mod flip_lr_node;
+pub mod foot_ik_node;
 pub mod fsm_node;
 pub mod global_input;
 pub mod graph_node;
+pub mod look_at_node;
 pub mod loop_node;
 pub mod padding;
 pub mod quat;
@@ -52,6 +59,7 @@ pub mod ragdoll;
 pub mod replicate_time;
 pub mod rotation_node;
 pub mod speed_node;
+pub mod spring_node;
 pub mod twoboneik_node;
 pub mod vec3;
 
@@ -82,6 +90,13 @@ impl BuiltinNodesPlugin {
             .register_type::<SpeedNode>()
             .register_type::<FsmNode>()
             .register_type::<TwoBoneIKNode>()
+            .register_type::<ChainIKNode>()
+            .register_type::<LookAtNode>()
+            .register_type::<LookAtState>()
+            .register_type::<FootIKNode>()
+            .register_type::<FootIKState>()
+            .register_type::<SpringNode>()
+            .register_type::<SpringState>()
             .register_type::<Constants>()
             // bool
             .register_type::<AndBool>()
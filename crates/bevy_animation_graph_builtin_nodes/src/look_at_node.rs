@@ -0,0 +1,243 @@
+use bevy::{
+    color::LinearRgba,
+    math::{Quat, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+    transform::components::Transform,
+};
+use bevy_animation_graph_core::{
+    animation_node::{NodeLike, ReflectNodeLike},
+    context::{
+        new_context::NodeContext, node_states::ReflectGraphStateType, spec_context::SpecContext,
+    },
+    edge_data::{DataSpec, DataValue},
+    errors::GraphError,
+    id::BoneId,
+    ik::aim::{AimLimits, smoothing_factor, turn_towards},
+    pose::Pose,
+    skeleton::Skeleton,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::rotation_node::ChainDecay;
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[reflect(Default)]
+pub enum LookAtSpace {
+    #[default]
+    Character,
+    Global,
+}
+
+/// Smoothed aim direction of a [`LookAtNode`], in character space
+#[derive(Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Default, GraphStateType)]
+pub struct LookAtState {
+    pub direction: Vec3,
+}
+
+#[derive(Reflect, Clone, Debug)]
+#[reflect(Default, NodeLike)]
+#[type_path = "bevy_animation_graph::builtin_nodes"]
+pub struct LookAtNode {
+    /// Axis of the target bone that is aimed at the target position, in the space of the bone
+    pub axis: Vec3,
+    pub target_space: LookAtSpace,
+    /// Limits relative to where the axis points in the input pose, in character space
+    pub limits: AimLimits,
+    /// Number of bones the rotation is spread over: the target bone and its parents
+    pub chain_length: usize,
+    pub chain_decay: ChainDecay,
+    /// Time in seconds for the aim to turn halfway towards the target. 0 disables smoothing.
+    pub smoothing: f32,
+}
+
+impl Default for LookAtNode {
+    fn default() -> Self {
+        Self {
+            axis: Vec3::Z,
+            target_space: LookAtSpace::Character,
+            limits: AimLimits::default(),
+            chain_length: 1,
+            chain_decay: ChainDecay::Linear,
+            smoothing: 0.,
+        }
+    }
+}
+
+impl LookAtNode {
+    pub const IN_TIME: &'static str = "time";
+    pub const IN_POSE: &'static str = "pose";
+    pub const OUT_POSE: &'static str = "pose";
+    pub const TARGETBONE: &'static str = "target_path";
+    pub const TARGETPOS: &'static str = "target_position";
+    /// Optional, blend factor between the input pose (0) and the aimed pose (1)
+    pub const WEIGHT: &'static str = "weight";
+
+    pub fn new(
+        axis: Vec3,
+        target_space: LookAtSpace,
+        limits: AimLimits,
+        chain_length: usize,
+        chain_decay: ChainDecay,
+        smoothing: f32,
+    ) -> Self {
+        Self {
+            axis,
+            target_space,
+            limits,
+            chain_length,
+            chain_decay,
+            smoothing,
+        }
+    }
+
+    /// Bones that are rotated, from the top of the chain to the target bone. The skeleton root
+    /// is left out, as character space is the space of the root bone.
+    fn chain_bones(&self, skeleton: &Skeleton, target: BoneId) -> Vec<BoneId> {
+        if target == skeleton.root() {
+            return Vec::new();
+        }
+
+        let mut chain = vec![target];
+        let mut bone = target;
+        while chain.len() < self.chain_length {
+            match skeleton.parent(&bone) {
+                Some(parent) if parent != skeleton.root() => {
+                    chain.insert(0, parent);
+                    bone = parent;
+                }
+                _ => break,
+            }
+        }
+        chain
+    }
+
+    /// Share of the rotation taken by each bone of the chain, from the top of the chain
+    fn chain_weights(&self, len: usize) -> Vec<f32> {
+        match self.chain_decay {
+            ChainDecay::Linear => (1..=len).map(|i| i as f32).collect(),
+        }
+    }
+}
+
+impl NodeLike for LookAtNode {
+    fn duration(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        let duration = ctx.duration_back(Self::IN_TIME)?;
+        ctx.set_duration_fwd(duration);
+        Ok(())
+    }
+
+    fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        // Smoothing follows the time that passed this frame, jumps in time do not move the aim
+        let delta_time = ctx.time_delta_fwd(Self::IN_TIME)?;
+
+        let target = ctx.data_back(Self::TARGETBONE)?.into_entity_path()?.id();
+        let target_position = ctx.data_back(Self::TARGETPOS)?.into_vec3()?;
+        let weight = ctx
+            .data_back(Self::WEIGHT)
+            .unwrap_or(DataValue::F32(1.))
+            .as_f32()?
+            .clamp(0., 1.);
+        let mut pose = ctx.data_back(Self::IN_POSE)?.into_pose()?;
+        ctx.set_time(pose.timestamp);
+        let Some(skeleton) = ctx
+            .graph_context
+            .resources
+            .skeleton_assets
+            .get(&pose.skeleton)
+        else {
+            return Err(GraphError::SkeletonMissing(ctx.node_id));
+        };
+
+        let chain = self.chain_bones(skeleton, target);
+        let aim_direction = |ctx: &NodeContext, pose: &Pose| {
+            let transform = ctx
+                .graph_context
+                .space_conversion()
+                .character_transform_of_bone(pose, skeleton, target);
+            (transform.translation, transform.rotation * self.axis)
+        };
+
+        let (origin, input_direction) = aim_direction(&ctx, &pose);
+        let target_position = match self.target_space {
+            LookAtSpace::Character => target_position,
+            LookAtSpace::Global => {
+                ctx.graph_context
+                    .space_conversion()
+                    .transform_global_to_character(
+                        Transform::from_translation(target_position),
+                        skeleton,
+                    )
+                    .translation
+            }
+        };
+
+        if !chain.is_empty()
+            && let Some(input_direction) = input_direction.try_normalize()
+            && let Some(direction) = (target_position - origin).try_normalize()
+        {
+            let direction = self.limits.apply(input_direction, direction);
+
+            let smoothing = smoothing_factor(delta_time, self.smoothing);
+            let state = ctx.state_mut_or_else(|| LookAtState { direction })?;
+            state.direction = turn_towards(state.direction, direction, smoothing);
+            let direction = turn_towards(input_direction, state.direction, weight);
+
+            // Each bone takes its share of the rotation that is left, so that the target bone
+            // ends up aimed along the direction no matter how its parents rotated it
+            let weights = self.chain_weights(chain.len());
+            let mut weight_left: f32 = weights.iter().sum();
+            for (bone, bone_weight) in chain.iter().zip(weights) {
+                let (_, current_direction) = aim_direction(&ctx, &pose);
+                let rotation = Quat::IDENTITY.slerp(
+                    Quat::from_rotation_arc(current_direction.normalize(), direction),
+                    bone_weight / weight_left,
+                );
+                weight_left -= bone_weight;
+
+                let space_conversion = ctx.graph_context.space_conversion();
+                let bone_rotation = space_conversion
+                    .character_transform_of_bone(&pose, skeleton, *bone)
+                    .rotation;
+                let parent_rotation = skeleton.parent(bone).map_or(Quat::IDENTITY, |parent| {
+                    space_conversion
+                        .character_transform_of_bone(&pose, skeleton, parent)
+                        .rotation
+                });
+                let index = pose.insert_bone(*bone);
+                pose.set_rotation(
+                    index,
+                    (parent_rotation.inverse() * rotation * bone_rotation).normalize(),
+                );
+            }
+
+            // Debug render (if enabled)
+            for bone in chain {
+                ctx.graph_context.use_debug_gizmos(|mut gizmos| {
+                    gizmos.bone_gizmo(bone, LinearRgba::BLUE, false, skeleton, Some(&pose))
+                });
+            }
+        }
+
+        ctx.set_data_fwd(Self::OUT_POSE, pose);
+        Ok(())
+    }
+
+    fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+        ctx //
+            .add_input_data(Self::TARGETBONE, DataSpec::EntityPath)
+            .add_input_data(Self::TARGETPOS, DataSpec::Vec3)
+            .add_input_data(Self::WEIGHT, DataSpec::F32)
+            .add_input_data(Self::IN_POSE, DataSpec::Pose)
+            .add_input_time(Self::IN_TIME);
+        ctx //
+            .add_output_data(Self::OUT_POSE, DataSpec::Pose)
+            .add_output_time();
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        "Look At".into()
+    }
+}
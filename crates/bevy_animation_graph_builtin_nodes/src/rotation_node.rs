@@ -8,7 +8,6 @@ use bevy_animation_graph_core::{
     context::{new_context::NodeContext, spec_context::SpecContext},
     edge_data::DataSpec,
     errors::GraphError,
-    pose::BonePose,
 };
 use serde::{Deserialize, Serialize};
 
@@ -100,10 +99,6 @@ impl NodeLike for RotationNode {
             return Err(GraphError::SkeletonMissing(ctx.node_id));
         };
 
-        if !pose.paths.contains_key(&target) {
-            pose.add_bone(BonePose::default(), target);
-        }
-
         // build bone chain
         let mut chain = vec![target];
         while let Some(parent) = skeleton.parent(&target) {
@@ -157,25 +152,17 @@ impl NodeLike for RotationNode {
                 }
             };
 
-            let mut bone_pose = pose
-                .paths
-                .get(&target)
-                .and_then(|bone_id| pose.bones.get_mut(*bone_id).cloned())
-                .unwrap_or_default();
-
-            if let Some(rot) = bone_pose.rotation {
-                let rotation = match self.application_mode {
+            let bone_index = pose.insert_bone(target);
+            let rotation = match pose.rotation(bone_index) {
+                Some(rot) => match self.application_mode {
                     RotationMode::Blend => rot.slerp(rotation_bone_space, percent),
                     RotationMode::Compose => {
                         Quat::IDENTITY.slerp(rotation_bone_space, percent) * rot
                     }
-                };
-                bone_pose.rotation = Some(rotation);
-            } else {
-                bone_pose.rotation = Some(rotation_bone_space);
-            }
-
-            pose.add_bone(bone_pose, target);
+                },
+                None => rotation_bone_space,
+            };
+            pose.set_rotation(bone_index, rotation);
         }
 
         ctx.set_time(pose.timestamp);
@@ -0,0 +1,205 @@
+use bevy::{
+    color::LinearRgba,
+    math::{Quat, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+use bevy_animation_graph_core::{
+    animation_clip::EntityPath,
+    animation_node::{NodeLike, ReflectNodeLike},
+    context::{
+        new_context::NodeContext, node_states::ReflectGraphStateType, spec_context::SpecContext,
+    },
+    edge_data::DataSpec,
+    errors::GraphError,
+    id::BoneId,
+    skeleton::Skeleton,
+    spring::{PlacedSpringCollider, SpringChainState, SpringColliderShape, SpringSettings},
+};
+use serde::{Deserialize, Serialize};
+
+/// Chain of bones that is moved by springs
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default)]
+#[reflect(Default)]
+pub struct SpringChain {
+    /// First bone of the chain. It stays where the animation has it, but is rotated by the
+    /// simulation. It cannot be the skeleton root.
+    pub root: EntityPath,
+    /// Last bone of the chain. Its position is simulated, but its rotation is left to its parent,
+    /// so chains usually end in a leaf bone.
+    pub tip: EntityPath,
+    pub settings: SpringSettings,
+}
+
+/// Collider attached to a bone, which simulated bones are kept out of
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default)]
+#[reflect(Default)]
+pub struct SpringCollider {
+    pub bone: EntityPath,
+    pub shape: SpringColliderShape,
+}
+
+/// Simulation state of a [`SpringNode`], by chain
+#[derive(Reflect, Clone, Debug, Default)]
+#[reflect(Default, GraphStateType)]
+pub struct SpringState {
+    pub chains: Vec<SpringChainState>,
+}
+
+/// Secondary motion for hair, tails, straps and the like. Each chain of bones lags behind the
+/// animation and swings back towards it.
+#[derive(Reflect, Clone, Debug, Default)]
+#[reflect(Default, NodeLike)]
+#[type_path = "bevy_animation_graph::builtin_nodes"]
+pub struct SpringNode {
+    pub chains: Vec<SpringChain>,
+    pub colliders: Vec<SpringCollider>,
+}
+
+impl SpringNode {
+    pub const IN_TIME: &'static str = "time";
+    pub const IN_POSE: &'static str = "pose";
+    pub const OUT_POSE: &'static str = "pose";
+
+    pub fn new(chains: Vec<SpringChain>, colliders: Vec<SpringCollider>) -> Self {
+        Self { chains, colliders }
+    }
+
+    /// Bones of a chain from its root to its tip. Empty if the tip is not below the root.
+    fn chain_bones(skeleton: &Skeleton, chain: &SpringChain) -> Vec<BoneId> {
+        let root = chain.root.id();
+        let mut bones = vec![chain.tip.id()];
+        while bones[bones.len() - 1] != root {
+            match skeleton.parent(&bones[bones.len() - 1]) {
+                Some(parent) => bones.push(parent),
+                None => return Vec::new(),
+            }
+        }
+        bones.reverse();
+        bones
+    }
+}
+
+impl NodeLike for SpringNode {
+    fn duration(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        let duration = ctx.duration_back(Self::IN_TIME)?;
+        ctx.set_duration_fwd(duration);
+        Ok(())
+    }
+
+    fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        // The simulation advances by the time that passed this frame, and holds still when time
+        // jumps
+        let delta_time = ctx.time_delta_fwd(Self::IN_TIME)?;
+
+        let mut pose = ctx.data_back(Self::IN_POSE)?.into_pose()?;
+        ctx.set_time(pose.timestamp);
+        let Some(skeleton) = ctx
+            .graph_context
+            .resources
+            .skeleton_assets
+            .get(&pose.skeleton)
+        else {
+            return Err(GraphError::SkeletonMissing(ctx.node_id));
+        };
+
+        let space_conversion = ctx.graph_context.space_conversion();
+        let chains: Vec<Vec<BoneId>> = self
+            .chains
+            .iter()
+            .map(|chain| Self::chain_bones(skeleton, chain))
+            .collect();
+        let animated: Vec<Vec<Vec3>> = chains
+            .iter()
+            .map(|bones| {
+                bones
+                    .iter()
+                    .map(|bone| {
+                        space_conversion
+                            .global_transform_of_bone(&pose, skeleton, *bone)
+                            .translation
+                    })
+                    .collect()
+            })
+            .collect();
+        let colliders: Vec<PlacedSpringCollider> = self
+            .colliders
+            .iter()
+            .map(|collider| {
+                collider
+                    .shape
+                    .placed(space_conversion.global_transform_of_bone(
+                        &pose,
+                        skeleton,
+                        collider.bone.id(),
+                    ))
+            })
+            .collect();
+
+        let state = ctx.state_mut::<SpringState>()?;
+        state
+            .chains
+            .resize_with(self.chains.len(), SpringChainState::default);
+        for ((chain_state, chain), animated) in
+            state.chains.iter_mut().zip(&self.chains).zip(&animated)
+        {
+            chain_state.step(animated, &chain.settings, &colliders, delta_time);
+        }
+        let simulated = state.chains.clone();
+
+        // Each bone is rotated towards the simulated position of the next one, from the root of
+        // the chain down
+        let space_conversion = ctx.graph_context.space_conversion();
+        for (bones, simulated) in chains.iter().zip(&simulated) {
+            for (i, bone_pair) in bones.windows(2).enumerate() {
+                let (bone, child) = (bone_pair[0], bone_pair[1]);
+                let (Some(parent), Some(target)) =
+                    (skeleton.parent(&bone), simulated.positions.get(i + 1))
+                else {
+                    continue;
+                };
+                let transform = space_conversion.global_transform_of_bone(&pose, skeleton, bone);
+                let child = space_conversion
+                    .global_transform_of_bone(&pose, skeleton, child)
+                    .translation;
+                let (Some(from), Some(to)) = (
+                    (child - transform.translation).try_normalize(),
+                    (*target - transform.translation).try_normalize(),
+                ) else {
+                    continue;
+                };
+
+                let parent_rotation = space_conversion
+                    .global_transform_of_bone(&pose, skeleton, parent)
+                    .rotation;
+                let rotation = Quat::from_rotation_arc(from, to) * transform.rotation;
+                let index = pose.insert_bone(bone);
+                pose.set_rotation(index, (parent_rotation.inverse() * rotation).normalize());
+            }
+        }
+
+        // Debug render (if enabled)
+        for bone in chains.into_iter().flatten() {
+            ctx.graph_context.use_debug_gizmos(|mut gizmos| {
+                gizmos.bone_gizmo(bone, LinearRgba::BLUE, false, skeleton, Some(&pose))
+            });
+        }
+
+        ctx.set_data_fwd(Self::OUT_POSE, pose);
+        Ok(())
+    }
+
+    fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+        ctx //
+            .add_input_data(Self::IN_POSE, DataSpec::Pose)
+            .add_input_time(Self::IN_TIME);
+        ctx //
+            .add_output_data(Self::OUT_POSE, DataSpec::Pose)
+            .add_output_time();
+
+        Ok(())
+    }
+
+    fn display_name(&self) -> String {
+        "Spring".into()
+    }
+}
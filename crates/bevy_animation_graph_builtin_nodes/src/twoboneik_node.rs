@@ -1,20 +1,26 @@
 use bevy::{
     color::LinearRgba,
-    math::{Quat, Vec3},
+    math::Quat,
     reflect::{Reflect, std_traits::ReflectDefault},
     transform::components::Transform,
 };
 use bevy_animation_graph_core::{
     animation_node::{NodeLike, ReflectNodeLike},
     context::{new_context::NodeContext, spec_context::SpecContext},
-    edge_data::DataSpec,
+    edge_data::{DataSpec, DataValue},
     errors::GraphError,
+    ik::two_bone::two_bone_ik,
 };
 
 #[derive(Reflect, Clone, Debug, Default)]
 #[reflect(Default, NodeLike)]
 #[type_path = "bevy_animation_graph::builtin_nodes"]
-pub struct TwoBoneIKNode;
+pub struct TwoBoneIKNode {
+    /// Fraction of the length of the chain over which it straightens out more and more slowly
+    /// as the target moves out of reach, instead of snapping straight. 0 disables it.
+    #[reflect(default)]
+    pub softness: f32,
+}
 
 impl TwoBoneIKNode {
     pub const IN_TIME: &'static str = "time";
@@ -22,9 +28,15 @@ impl TwoBoneIKNode {
     pub const OUT_POSE: &'static str = "pose";
     pub const TARGETBONE: &'static str = "target_path";
     pub const TARGETPOS: &'static str = "target_position";
-
-    pub fn new() -> Self {
-        Self {}
+    /// Optional, position in character space that the middle joint bends towards
+    pub const POLETARGET: &'static str = "pole_target";
+    /// Optional, rotation of the target bone in character space
+    pub const TARGETROT: &'static str = "target_rotation";
+    /// Optional, blend factor between the input pose (0) and the solved pose (1)
+    pub const WEIGHT: &'static str = "weight";
+
+    pub fn new(softness: f32) -> Self {
+        Self { softness }
     }
 }
 
@@ -43,8 +55,22 @@ impl NodeLike for TwoBoneIKNode {
         let target = ctx.data_back(Self::TARGETBONE)?.into_entity_path()?;
         let target = target.id();
         let target_pos_char = ctx.data_back(Self::TARGETPOS)?.into_vec3()?;
+        let target_rot_char = ctx
+            .data_back(Self::TARGETROT)
+            .ok()
+            .map(|rotation| rotation.as_quat())
+            .transpose()?;
+        let pole_char = ctx
+            .data_back(Self::POLETARGET)
+            .ok()
+            .map(|pole| pole.into_vec3())
+            .transpose()?;
+        let weight = ctx
+            .data_back(Self::WEIGHT)
+            .unwrap_or(DataValue::F32(1.))
+            .as_f32()?
+            .clamp(0., 1.);
         let mut pose = ctx.data_back(Self::IN_POSE)?.into_pose()?;
-        //let targetrotation: Quat = ctx.parameter_back(Self::TARGETROT).unwrap();
         let Some(skeleton) = ctx
             .graph_context
             .resources
@@ -55,7 +81,8 @@ impl NodeLike for TwoBoneIKNode {
         };
 
         if let (Some(bone_id), Some(parent_path), Some(grandparent_path)) = (
-            pose.paths.get(&target),
+            pose.bone_index(&target)
+                .filter(|index| pose.contains_bone(*index)),
             skeleton.parent(&target),
             skeleton.parent(&target).and_then(|p| skeleton.parent(&p)),
         ) {
@@ -67,27 +94,37 @@ impl NodeLike for TwoBoneIKNode {
                 gizmos.bone_gizmo(parent_path, LinearRgba::RED, false, skeleton, Some(&pose))
             });
 
-            let bone = pose.bones[*bone_id].clone();
-            let target_gp = ctx.graph_context.space_conversion().root_to_bone_space(
-                Transform::from_translation(target_pos_char),
+            let space_conversion = ctx.graph_context.space_conversion();
+            let grandparent_parent = skeleton.parent(&grandparent_path).unwrap();
+            let target_gp = space_conversion.root_to_bone_space(
+                Transform {
+                    translation: target_pos_char,
+                    rotation: target_rot_char.unwrap_or(Quat::IDENTITY),
+                    ..Transform::default()
+                },
                 &pose,
                 skeleton,
-                skeleton.parent(&grandparent_path).unwrap(),
+                grandparent_parent,
             );
+            let pole_gp = pole_char.map(|pole| {
+                space_conversion
+                    .root_to_bone_space(
+                        Transform::from_translation(pole),
+                        &pose,
+                        skeleton,
+                        grandparent_parent,
+                    )
+                    .translation
+            });
 
-            let target_pos_gp = target_gp.translation;
-
-            let parent_id = pose.paths.get(&parent_path).unwrap();
-            let parent_transform = {
-                let parent_bone = pose.bones.get_mut(*parent_id).unwrap();
-                parent_bone.to_transform()
-            };
+            let parent_id = pose.insert_bone(parent_path);
+            let parent_transform = pose.transform_with_base(parent_id, Transform::default());
 
-            let grandparent_id = pose.paths.get(&grandparent_path).unwrap();
-            let grandparent_bone = pose.bones.get_mut(*grandparent_id).unwrap();
-            let grandparent_transform = grandparent_bone.to_transform();
+            let grandparent_id = pose.insert_bone(grandparent_path);
+            let grandparent_transform =
+                pose.transform_with_base(grandparent_id, Transform::default());
 
-            let bone_transform = bone.to_transform();
+            let bone_transform = pose.transform_with_base(bone_id, Transform::default());
 
             let parent_gp_transform = grandparent_transform * parent_transform;
             let bone_gp_transform = parent_gp_transform * bone_transform;
@@ -96,8 +133,17 @@ impl NodeLike for TwoBoneIKNode {
                 bone_gp_transform,
                 parent_gp_transform,
                 grandparent_transform,
-                target_pos_gp,
+                target_gp.translation,
+                pole_gp,
+                self.softness,
             );
+            let bone_gp_transform = match target_rot_char {
+                Some(_) => Transform {
+                    rotation: target_gp.rotation,
+                    ..bone_gp_transform
+                },
+                None => bone_gp_transform,
+            };
 
             let parent_transform =
                 Transform::from_matrix(grandparent_transform.to_matrix().inverse())
@@ -105,9 +151,14 @@ impl NodeLike for TwoBoneIKNode {
             let bone_transform = Transform::from_matrix(parent_gp_transform.to_matrix().inverse())
                 * bone_gp_transform;
 
-            pose.bones[*grandparent_id].rotation = Some(grandparent_transform.rotation);
-            pose.bones[*parent_id].rotation = Some(parent_transform.rotation);
-            pose.bones[*bone_id].rotation = Some(bone_transform.rotation);
+            for (index, rotation) in [
+                (grandparent_id, grandparent_transform.rotation),
+                (parent_id, parent_transform.rotation),
+                (bone_id, bone_transform.rotation),
+            ] {
+                let input_rotation = pose.rotation(index).unwrap_or(Quat::IDENTITY);
+                pose.set_rotation(index, input_rotation.slerp(rotation, weight));
+            }
 
             // Debug render (if enabled)
             ctx.graph_context.use_debug_gizmos(|mut gizmos| {
@@ -126,6 +177,9 @@ impl NodeLike for TwoBoneIKNode {
         ctx //
             .add_input_data(Self::TARGETBONE, DataSpec::EntityPath)
             .add_input_data(Self::TARGETPOS, DataSpec::Vec3)
+            .add_input_data(Self::POLETARGET, DataSpec::Vec3)
+            .add_input_data(Self::TARGETROT, DataSpec::Quat)
+            .add_input_data(Self::WEIGHT, DataSpec::F32)
             .add_input_data(Self::IN_POSE, DataSpec::Pose)
             .add_input_time(Self::IN_TIME);
         ctx //
@@ -139,80 +193,3 @@ impl NodeLike for TwoBoneIKNode {
         "Two Bone IK".into()
     }
 }
-
-// Adapted from https://blog.littlepolygon.com/posts/twobone/
-fn two_bone_ik(
-    bone: Transform,
-    parent: Transform,
-    grandparent: Transform,
-    target_pos: Vec3,
-) -> (Transform, Transform, Transform) {
-    const MAX_LEN_OFFSET: f32 = 0.01;
-
-    // compute joint positions
-    let in_end_loc = bone.translation;
-    let in_mid_loc = parent.translation;
-    let in_root_loc = grandparent.translation;
-
-    // compute bone lengths
-    let upper_len = in_root_loc.distance(in_mid_loc);
-    let lower_len = in_mid_loc.distance(in_end_loc);
-    let max_len = upper_len + lower_len - MAX_LEN_OFFSET;
-
-    // compute input planar basis vectors
-    let to_end = (in_end_loc - in_root_loc).normalize();
-    let in_pole_vec = (in_mid_loc - in_root_loc).reject_from(to_end).normalize();
-
-    // compute final planar basis vectors
-    let to_target_offset = (target_pos - in_root_loc).clamp_length_max(max_len);
-    let to_target_dist = to_target_offset.length();
-    let to_target = to_target_offset / to_target_dist;
-
-    let to_target_swing = Quat::from_rotation_arc(to_end, to_target);
-    let out_pole_vec = to_target_swing * in_pole_vec;
-
-    // apply law of cosines to get middle joint angle
-    let denom = 2. * upper_len * to_target_dist;
-    let mut cos_angle = 0.;
-    if denom > f32::EPSILON {
-        cos_angle = (to_target_dist * to_target_dist + upper_len * upper_len
-            - lower_len * lower_len)
-            / denom;
-    }
-    let angle = cos_angle.acos();
-
-    // compute final joint positions
-    let pole_dist = upper_len * angle.sin();
-    let eff_dist = upper_len * cos_angle;
-    let out_end_loc = in_root_loc + to_target_offset;
-    let out_mid_loc = in_root_loc + eff_dist * to_target + pole_dist * out_pole_vec;
-
-    // compute final rotations
-    let in_to_mid = in_mid_loc - in_root_loc;
-    let out_to_mid = out_mid_loc - in_root_loc;
-    let root_swing = Quat::from_rotation_arc(in_to_mid.normalize(), out_to_mid.normalize());
-    let in_end_loc_with_root_swing = in_root_loc + root_swing * (in_end_loc - in_root_loc);
-    let to_in_end = in_end_loc_with_root_swing - out_mid_loc;
-    let to_out_end = out_end_loc - out_mid_loc;
-    let mid_swing =
-        Quat::from_rotation_arc(to_in_end.normalize(), to_out_end.normalize()) * root_swing;
-
-    // set up output transforms
-    let out_grandparent = Transform {
-        rotation: root_swing * grandparent.rotation,
-        ..grandparent
-    };
-
-    let out_parent = Transform {
-        translation: out_mid_loc,
-        rotation: mid_swing * parent.rotation,
-        ..parent
-    };
-    let out_bone = Transform {
-        translation: out_end_loc,
-        rotation: mid_swing * bone.rotation,
-        ..bone
-    };
-
-    (out_bone, out_parent, out_grandparent)
-}
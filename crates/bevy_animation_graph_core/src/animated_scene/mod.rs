@@ -2,19 +2,21 @@ pub mod loader;
 
 use bevy::{
     asset::{Asset, Assets, Handle, ReflectAsset},
-    camera::visibility::Visibility,
+    camera::visibility::{ViewVisibility, Visibility},
     ecs::{
         component::Component,
         entity::Entity,
         hierarchy::Children,
+        lifecycle::RemovedComponents,
         name::Name,
         observer::On,
         query::{With, Without},
-        reflect::AppTypeRegistry,
+        reflect::{AppTypeRegistry, ReflectComponent},
         system::{Commands, Query, Res, ResMut},
     },
+    mesh::skinning::SkinnedMesh,
     platform::collections::HashMap,
-    reflect::Reflect,
+    reflect::{Reflect, std_traits::ReflectDefault},
     scene::{Scene, SceneInstanceReady, SceneRoot},
     transform::components::Transform,
 };
@@ -65,6 +67,15 @@ impl AnimatedSceneInstance {
     }
 }
 
+/// Opts an animated scene into visibility culling: while none of the skinned meshes in the scene
+/// are visible, its [`AnimationGraphPlayer`] is culled (see [`AnimationGraphPlayer::set_culled`]).
+///
+/// Add it to the entity with the [`AnimatedSceneHandle`]. Visibility is taken from the previous
+/// frame, so players are culled and uncull one frame late.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct AnimationVisibilityCulling;
+
 #[derive(Component, Default)]
 #[require(Transform, Visibility)]
 pub struct AnimatedSceneHandle {
@@ -246,3 +257,34 @@ pub(crate) fn locate_animated_scene_player(
         return;
     }
 }
+
+pub(crate) fn update_animation_visibility_culling(
+    scenes: Query<(Entity, &AnimatedSceneInstance), With<AnimationVisibilityCulling>>,
+    unculled_scenes: Query<&AnimatedSceneInstance, Without<AnimationVisibilityCulling>>,
+    mut removed: RemovedComponents<AnimationVisibilityCulling>,
+    children_query: Query<&Children>,
+    skinned_meshes: Query<&ViewVisibility, With<SkinnedMesh>>,
+    mut players: Query<&mut AnimationGraphPlayer>,
+) {
+    for (root_entity, instance) in &scenes {
+        let mut visibilities =
+            skinned_meshes.iter_many(children_query.iter_descendants(root_entity));
+        let Some(first) = visibilities.next() else {
+            // Without skinned meshes there is nothing to decide visibility on
+            continue;
+        };
+        let visible = first.get() || visibilities.any(|visibility| visibility.get());
+
+        if let Ok(mut player) = players.get_mut(instance.player_entity)
+            && player.is_culled() == visible
+        {
+            player.set_culled(!visible);
+        }
+    }
+
+    for instance in unculled_scenes.iter_many(removed.read()) {
+        if let Ok(mut player) = players.get_mut(instance.player_entity) {
+            player.set_culled(false);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use bevy::{
+    animation::{
+        AnimationClip, VariableCurve, animated_field,
+        animation_curves::{
+            AnimatableCurve, AnimatableKeyframeCurve, AnimatableProperty, AnimatedField,
+            AnimationCompatibleCurve, WeightsCurve,
+        },
+        gltf_curves::{
+            CubicKeyframeCurve, CubicRotationCurve, SteppedKeyframeCurve, WideCubicKeyframeCurve,
+            WideLinearKeyframeCurve, WideSteppedKeyframeCurve,
+        },
+    },
+    asset::AssetPath,
+    math::{
+        Quat, Vec3, Vec4,
+        curve::{ConstantCurve, Interval},
+    },
+    prelude::Transform,
+    reflect::prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{EntityPath, Interpolation};
+use crate::errors::KeyframeError;
+
+/// Keyframes of a single animated property, e.g. the rotation of a bone
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct KeyframeTrack<T> {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    /// Timestamps of the keyframes in seconds
+    pub times: Vec<f32>,
+    /// Value at each timestamp. With [`Interpolation::CubicSpline`], each keyframe has three
+    /// values instead: its in tangent, its value and its out tangent, like in glTF.
+    pub values: Vec<T>,
+}
+
+impl<T> KeyframeTrack<T> {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            interpolation,
+            times: vec![],
+            values: vec![],
+        }
+    }
+
+    /// Adds a keyframe to a linear or step track
+    pub fn push(&mut self, time: f32, value: T) {
+        self.times.push(time);
+        self.values.push(value);
+    }
+
+    /// Adds a keyframe to a cubic spline track
+    pub fn push_with_tangents(&mut self, time: f32, in_tangent: T, value: T, out_tangent: T) {
+        self.times.push(time);
+        self.values.extend([in_tangent, value, out_tangent]);
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    pub(super) fn values_per_keyframe(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => 3,
+            Interpolation::Linear | Interpolation::Step => 1,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let expected = self.times.len() * self.values_per_keyframe();
+        if self.times.is_empty() {
+            Err("no keyframes".into())
+        } else if self.values.len() != expected {
+            Err(format!(
+                "{} {:?} keyframes need {expected} values, found {}",
+                self.times.len(),
+                self.interpolation,
+                self.values.len()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Value of the only keyframe of the track, if it has a single one
+    fn single_value(&self) -> Option<&T> {
+        (self.times.len() == 1).then(|| &self.values[self.values_per_keyframe() / 2])
+    }
+
+    fn timed_values(&self) -> impl Iterator<Item = (f32, T)> + '_
+    where
+        T: Clone,
+    {
+        self.times.iter().copied().zip(self.values.iter().cloned())
+    }
+}
+
+/// Keyframes of each animated property of a bone
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BoneKeyframes {
+    pub path: EntityPath,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<KeyframeTrack<Vec3>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<KeyframeTrack<Quat>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<KeyframeTrack<Vec3>>,
+    /// Morph target weights. Every value holds the weight of each morph target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<KeyframeTrack<Vec<f32>>>,
+}
+
+impl BoneKeyframes {
+    pub fn new(path: EntityPath) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+}
+
+/// Animation data of a clip that does not come from a glTF file, see
+/// [`GraphClipSource::Keyframes`](super::loader::GraphClipSource::Keyframes)
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ClipKeyframes {
+    /// Duration of the clip in seconds
+    pub duration: f32,
+    pub bones: Vec<BoneKeyframes>,
+}
+
+impl ClipKeyframes {
+    /// Builds the curves of the keyframes
+    pub fn to_bevy_clip(&self) -> Result<AnimationClip, KeyframeError> {
+        let mut clip = AnimationClip::default();
+
+        for bone in &self.bones {
+            let target = bone.path.id().animation_target_id();
+            let curves = [
+                bone.translation
+                    .as_ref()
+                    .map(|track| vec3_curve(animated_field!(Transform::translation), track)),
+                bone.rotation.as_ref().map(rotation_curve),
+                bone.scale
+                    .as_ref()
+                    .map(|track| vec3_curve(animated_field!(Transform::scale), track)),
+                bone.weights.as_ref().map(weights_curve),
+            ];
+
+            for curve in curves.into_iter().flatten() {
+                let curve = curve.map_err(|error| KeyframeError {
+                    path: bone.path.clone(),
+                    reason: error,
+                })?;
+                clip.add_variable_curve_to_target(target, curve);
+            }
+        }
+
+        clip.set_duration(self.duration);
+        Ok(clip)
+    }
+
+    /// Encodes the keyframes in the format of [`KeyframeStorage::File`]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        // Fields are stored by name, so that fields skipped or added later are handled like in
+        // RON
+        rmp_serde::to_vec_named(self)
+    }
+
+    /// Decodes keyframes encoded with [`ClipKeyframes::to_msgpack`]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(bytes)
+    }
+}
+
+/// Where the keyframes of a [`GraphClipSource::Keyframes`](super::loader::GraphClipSource::Keyframes)
+/// clip are stored
+#[derive(Reflect, Serialize, Deserialize, Clone, Debug)]
+pub enum KeyframeStorage {
+    /// In the `.anim.ron` file itself
+    Inline(ClipKeyframes),
+    /// In a separate binary file, as written by [`ClipKeyframes::to_msgpack`]. It is much
+    /// smaller and faster to load than RON for long or densely sampled clips.
+    File(AssetPath<'static>),
+}
+
+fn property_curve<P, C>(property: P, curve: C) -> VariableCurve
+where
+    P: AnimatableProperty + Clone,
+    C: AnimationCompatibleCurve<P::Property>,
+{
+    VariableCurve::new(AnimatableCurve::new(property, curve))
+}
+
+fn vec3_curve<P>(property: P, track: &KeyframeTrack<Vec3>) -> Result<VariableCurve, String>
+where
+    P: AnimatableProperty<Property = Vec3> + Clone,
+{
+    track.validate()?;
+    if let Some(value) = track.single_value() {
+        return Ok(property_curve(
+            property,
+            ConstantCurve::new(Interval::EVERYWHERE, *value),
+        ));
+    }
+
+    match track.interpolation {
+        Interpolation::Linear => AnimatableKeyframeCurve::new(track.timed_values())
+            .map(|curve| property_curve(property, curve))
+            .map_err(|error| error.to_string()),
+        Interpolation::Step => SteppedKeyframeCurve::new(track.timed_values())
+            .map(|curve| property_curve(property, curve))
+            .map_err(|error| error.to_string()),
+        Interpolation::CubicSpline => {
+            CubicKeyframeCurve::new(track.times.iter().copied(), track.values.iter().copied())
+                .map(|curve| property_curve(property, curve))
+                .map_err(|error| error.to_string())
+        }
+    }
+}
+
+fn rotation_curve(track: &KeyframeTrack<Quat>) -> Result<VariableCurve, String> {
+    let property = animated_field!(Transform::rotation);
+    track.validate()?;
+    if let Some(value) = track.single_value() {
+        return Ok(property_curve(
+            property,
+            ConstantCurve::new(Interval::EVERYWHERE, *value),
+        ));
+    }
+
+    match track.interpolation {
+        Interpolation::Linear => AnimatableKeyframeCurve::new(track.timed_values())
+            .map(|curve| property_curve(property, curve))
+            .map_err(|error| error.to_string()),
+        Interpolation::Step => SteppedKeyframeCurve::new(track.timed_values())
+            .map(|curve| property_curve(property, curve))
+            .map_err(|error| error.to_string()),
+        Interpolation::CubicSpline => CubicRotationCurve::new(
+            track.times.iter().copied(),
+            track.values.iter().copied().map(Vec4::from),
+        )
+        .map(|curve| property_curve(property, curve))
+        .map_err(|error| error.to_string()),
+    }
+}
+
+fn weights_curve(track: &KeyframeTrack<Vec<f32>>) -> Result<VariableCurve, String> {
+    track.validate()?;
+    let width = track.values[0].len();
+    if track.values.iter().any(|weights| weights.len() != width) {
+        return Err("all keyframes must have the same number of morph weights".into());
+    }
+    if let Some(value) = track.single_value() {
+        return Ok(VariableCurve::new(WeightsCurve(ConstantCurve::new(
+            Interval::EVERYWHERE,
+            value.clone(),
+        ))));
+    }
+
+    let times = track.times.iter().copied();
+    let values = track.values.iter().flatten().copied();
+    match track.interpolation {
+        Interpolation::Linear => WideLinearKeyframeCurve::new(times, values)
+            .map(|curve| VariableCurve::new(WeightsCurve(curve)))
+            .map_err(|error| error.to_string()),
+        Interpolation::Step => WideSteppedKeyframeCurve::new(times, values)
+            .map(|curve| VariableCurve::new(WeightsCurve(curve)))
+            .map_err(|error| error.to_string()),
+        Interpolation::CubicSpline => WideCubicKeyframeCurve::new(times, values)
+            .map(|curve| VariableCurve::new(WeightsCurve(curve)))
+            .map_err(|error| error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::animation::AnimationTargetId;
+
+    use super::*;
+
+    fn target() -> AnimationTargetId {
+        EntityPath::from(vec!["root".to_string()])
+            .id()
+            .animation_target_id()
+    }
+
+    fn keyframes() -> ClipKeyframes {
+        let mut bone = BoneKeyframes::new(EntityPath::from(vec!["root".to_string()]));
+        let mut translation = KeyframeTrack::default();
+        translation.push(0., Vec3::ZERO);
+        translation.push(1., Vec3::X);
+        bone.translation = Some(translation);
+        bone.rotation = Some(KeyframeTrack {
+            interpolation: Interpolation::Step,
+            times: vec![0.],
+            values: vec![Quat::IDENTITY],
+        });
+        let mut scale = KeyframeTrack::new(Interpolation::CubicSpline);
+        scale.push_with_tangents(0., Vec3::ZERO, Vec3::ONE, Vec3::ZERO);
+        scale.push_with_tangents(1., Vec3::ZERO, Vec3::splat(2.), Vec3::ZERO);
+        bone.scale = Some(scale);
+        let mut weights = KeyframeTrack::default();
+        weights.push(0., vec![0., 1.]);
+        weights.push(1., vec![1., 0.]);
+        bone.weights = Some(weights);
+
+        ClipKeyframes {
+            duration: 2.,
+            bones: vec![bone],
+        }
+    }
+
+    #[test]
+    fn test_keyframes_ron_roundtrip() {
+        let keyframes = keyframes();
+        let serialized = ron::to_string(&keyframes).unwrap();
+        let deserialized: ClipKeyframes = ron::from_str(&serialized).unwrap();
+        assert_eq!(keyframes, deserialized);
+    }
+
+    #[test]
+    fn test_keyframes_msgpack_roundtrip() {
+        let keyframes = keyframes();
+        let bytes = keyframes.to_msgpack().unwrap();
+        assert_eq!(ClipKeyframes::from_msgpack(&bytes).unwrap(), keyframes);
+    }
+
+    #[test]
+    fn test_interpolation_defaults_to_linear() {
+        let track: KeyframeTrack<f32> = ron::from_str("(times: [0.0], values: [1.0])").unwrap();
+        assert!(matches!(track.interpolation, Interpolation::Linear));
+    }
+
+    #[test]
+    fn test_keyframes_to_bevy_clip() {
+        let clip = keyframes().to_bevy_clip().unwrap();
+        assert_eq!(clip.duration(), 2.);
+        assert_eq!(clip.curves()[&target()].len(), 4);
+    }
+
+    #[test]
+    fn test_keyframes_with_missing_values_are_rejected() {
+        let mut keyframes = keyframes();
+        keyframes.bones[0]
+            .translation
+            .as_mut()
+            .unwrap()
+            .values
+            .pop();
+        assert!(matches!(
+            keyframes.to_bevy_clip(),
+            Err(KeyframeError { .. })
+        ));
+
+        let mut keyframes = self::keyframes();
+        keyframes.bones[0].scale.as_mut().unwrap().values.pop();
+        assert!(matches!(
+            keyframes.to_bevy_clip(),
+            Err(KeyframeError { .. })
+        ));
+
+        let mut keyframes = self::keyframes();
+        keyframes.bones[0].weights.as_mut().unwrap().values[1].push(0.);
+        assert!(matches!(
+            keyframes.to_bevy_clip(),
+            Err(KeyframeError { .. })
+        ));
+    }
+}
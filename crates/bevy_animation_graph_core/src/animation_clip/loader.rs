@@ -6,8 +6,15 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::GraphClip;
-use crate::{errors::AssetLoaderError, event_track::EventTrack, utils::normalize_asset_path};
+use super::{
+    GraphClip,
+    keyframes::{ClipKeyframes, KeyframeStorage},
+    range::{ClipRange, unslice_event_tracks},
+};
+use crate::{
+    bvh::loader::parse_bvh, errors::AssetLoaderError, event_track::EventTrack,
+    utils::normalize_asset_path,
+};
 
 #[derive(Reflect, Serialize, Deserialize, Clone, Debug)]
 pub enum GraphClipSource {
@@ -15,14 +22,33 @@ pub enum GraphClipSource {
         path: AssetPath<'static>,
         animation_name: String,
     },
+    /// Keyframes stored in the clip asset itself or in a file next to it
+    Keyframes(KeyframeStorage),
+    /// Every frame of a BVH motion capture file
+    Bvh {
+        path: AssetPath<'static>,
+        /// Factor applied to positions, see
+        /// [`BvhLoaderSettings::unit_scale`](crate::bvh::loader::BvhLoaderSettings::unit_scale)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unit_scale: Option<f32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct GraphClipSerial {
     pub source: GraphClipSource,
     pub skeleton: AssetPath<'static>,
+    /// Event tracks, timed in seconds of the source animation
     #[serde(default)]
     pub event_tracks: HashMap<String, EventTrack>,
+    /// Range of the source animation that the clip plays, in seconds. The whole animation is
+    /// played if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<ClipRange>,
+    /// Range that playback loops over once it reaches its end, in seconds of the source
+    /// animation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_range: Option<ClipRange>,
 }
 
 #[derive(Default, TypePath)]
@@ -43,7 +69,7 @@ impl AssetLoader for GraphClipLoader {
         reader.read_to_end(&mut bytes).await?;
         let serial: GraphClipSerial = ron::de::from_bytes(&bytes)?;
 
-        let bevy_clip = match &serial.source {
+        let (bevy_clip, keyframes) = match &serial.source {
             GraphClipSource::GltfNamed {
                 path,
                 animation_name,
@@ -74,18 +100,46 @@ impl AssetLoader for GraphClipLoader {
                     .unwrap()
                     .clone();
 
-                clip_bevy
+                (clip_bevy, None)
+            }
+            GraphClipSource::Keyframes(KeyframeStorage::Inline(keyframes)) => {
+                (keyframes.to_bevy_clip()?, Some(keyframes.clone()))
+            }
+            GraphClipSource::Keyframes(KeyframeStorage::File(path)) => {
+                let bytes = load_context.read_asset_bytes(path).await?;
+                let keyframes = ClipKeyframes::from_msgpack(&bytes)?;
+                (keyframes.to_bevy_clip()?, Some(keyframes))
+            }
+            GraphClipSource::Bvh { path, unit_scale } => {
+                let bytes = load_context.read_asset_bytes(path).await?;
+                let keyframes = parse_bvh(&bytes)?.keyframes(unit_scale.unwrap_or(1.));
+                (keyframes.to_bevy_clip()?, Some(keyframes))
             }
         };
 
         let skeleton = load_context.loader().load(serial.skeleton);
 
-        let clip_mine = GraphClip::from_bevy_clip(
+        let mut clip_mine = GraphClip::from_bevy_clip(
             bevy_clip,
             skeleton,
             serial.event_tracks,
             Some(serial.source.clone()),
         );
+        clip_mine.set_keyframes(keyframes);
+
+        if let Some(range) = serial.range {
+            if !range.is_within(clip_mine.duration()) {
+                return Err(AssetLoaderError::InvalidClipRange(range));
+            }
+            clip_mine.trim(range);
+        }
+        if let Some(loop_range) = serial.loop_range {
+            let clip_loop_range = loop_range.shifted(serial.range.map_or(0., |range| range.start));
+            if !clip_loop_range.is_within(clip_mine.duration()) {
+                return Err(AssetLoaderError::InvalidClipRange(loop_range));
+            }
+            clip_mine.loop_range = Some(clip_loop_range);
+        }
 
         Ok(clip_mine)
     }
@@ -103,10 +157,18 @@ impl TryFrom<&GraphClip> for GraphClipSerial {
             return Err(());
         };
 
+        let event_tracks = match value.range {
+            Some(range) => unslice_event_tracks(&value.event_tracks, range),
+            None => value.event_tracks.clone(),
+        };
+        let offset = value.range.map_or(0., |range| range.start);
+
         Ok(Self {
             source,
             skeleton: normalize_asset_path(value.skeleton.path().cloned().ok_or(())?),
-            event_tracks: value.event_tracks.clone(),
+            event_tracks,
+            range: value.range,
+            loop_range: value.loop_range.map(|range| range.shifted(-offset)),
         })
     }
 }
@@ -1,4 +1,7 @@
+pub mod keyframes;
 pub mod loader;
+pub mod range;
+pub mod sampling;
 
 use bevy::{
     animation::{AnimationCurves, AnimationTargetId},
@@ -7,13 +10,20 @@ use bevy::{
     platform::collections::HashMap,
     reflect::prelude::*,
 };
+use keyframes::{BoneKeyframes, ClipKeyframes};
 use loader::GraphClipSource;
+use range::{ClipRange, slice_curves, slice_event_tracks};
 use serde::{Deserialize, Serialize};
 
-use super::{event_track::EventTrack, id, skeleton::Skeleton};
+use super::{
+    errors::KeyframeError,
+    event_track::EventTrack,
+    id::{self, BoneId},
+    skeleton::Skeleton,
+};
 
 /// Interpolation method to use between keyframes.
-#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[reflect(Default)]
 pub enum Interpolation {
     /// Linear interpolation between the two closest keyframes.
@@ -167,6 +177,20 @@ pub struct GraphClip {
     pub duration: f32,
     pub skeleton: Handle<Skeleton>,
     pub event_tracks: HashMap<String, EventTrack>,
+    /// Keyframes of the curves, for clips built from keyframes: those with a
+    /// [`GraphClipSource::Keyframes`] or [`GraphClipSource::Bvh`] source, and baked clips. They
+    /// are sampled instead of the curves, so that their interpolation can be overridden. Like the
+    /// source animation, they are not trimmed to [`GraphClip::range`].
+    ///
+    /// glTF clips have none, they are sampled from the curves Bevy loaded.
+    keyframes: Option<ClipKeyframes>,
+    /// Id of the bone of each entry of `keyframes`, resolved once when they are set
+    #[reflect(ignore)]
+    keyframe_bone_ids: Vec<BoneId>,
+    /// Range of the source animation that the clip was trimmed to
+    pub range: Option<ClipRange>,
+    /// Range of the clip that playback loops over once it reaches its end
+    pub loop_range: Option<ClipRange>,
 }
 
 impl GraphClip {
@@ -206,6 +230,45 @@ impl GraphClip {
         &mut self.event_tracks
     }
 
+    /// Keyframes the clip was built from, see [`GraphClip::set_keyframes`]
+    pub fn keyframes(&self) -> Option<&ClipKeyframes> {
+        self.keyframes.as_ref()
+    }
+
+    /// Sets the keyframes that are sampled instead of the curves. They must match the curves.
+    pub fn set_keyframes(&mut self, keyframes: Option<ClipKeyframes>) {
+        self.keyframe_bone_ids = keyframes
+            .iter()
+            .flat_map(|keyframes| &keyframes.bones)
+            .map(|bone| bone.path.id())
+            .collect();
+        self.keyframes = keyframes;
+    }
+
+    /// Keyframes of each animated bone, along with its id
+    pub fn keyframe_bones(&self) -> impl Iterator<Item = (BoneId, &BoneKeyframes)> {
+        self.keyframe_bone_ids
+            .iter()
+            .copied()
+            .zip(self.keyframes.iter().flat_map(|keyframes| &keyframes.bones))
+    }
+
+    /// Keeps only the given range of the clip, which then starts at time zero
+    pub fn trim(&mut self, range: ClipRange) {
+        self.curves = slice_curves(&self.curves, range);
+        self.event_tracks = slice_event_tracks(&self.event_tracks, range);
+        self.duration = range.duration();
+        self.loop_range = self
+            .loop_range
+            .map(|loop_range| loop_range.shifted(range.start));
+        self.range = Some(match self.range {
+            Some(previous) => {
+                ClipRange::new(previous.start + range.start, previous.start + range.end)
+            }
+            None => range,
+        });
+    }
+
     pub fn from_bevy_clip(
         bevy_clip: bevy::animation::AnimationClip,
         skeleton: Handle<Skeleton>,
@@ -218,8 +281,25 @@ impl GraphClip {
             skeleton,
             event_tracks,
             source,
+            keyframes: None,
+            keyframe_bone_ids: Vec::new(),
+            range: None,
+            loop_range: None,
         }
     }
+
+    /// Builds the clip from keyframes, which are kept for sampling
+    pub fn from_keyframes(
+        keyframes: ClipKeyframes,
+        skeleton: Handle<Skeleton>,
+        event_tracks: HashMap<String, EventTrack>,
+        source: Option<GraphClipSource>,
+    ) -> Result<Self, KeyframeError> {
+        let mut clip =
+            Self::from_bevy_clip(keyframes.to_bevy_clip()?, skeleton, event_tracks, source);
+        clip.set_keyframes(Some(keyframes));
+        Ok(clip)
+    }
 }
 
 //tests
@@ -238,6 +318,24 @@ mod tests {
         assert_eq!(path, path_rountrip);
     }
 
+    #[test]
+    fn keyframe_bones_are_resolved_when_set() {
+        let path = EntityPath::default().child("root").child("arm");
+        let mut clip = GraphClip::default();
+        clip.set_keyframes(Some(ClipKeyframes {
+            duration: 1.,
+            bones: vec![BoneKeyframes::new(path.clone())],
+        }));
+
+        let bones: Vec<_> = clip.keyframe_bones().collect();
+        assert_eq!(bones.len(), 1);
+        assert_eq!(bones[0].0, path.id());
+        assert_eq!(bones[0].1.path, path);
+
+        clip.set_keyframes(None);
+        assert_eq!(clip.keyframe_bones().count(), 0);
+    }
+
     #[test]
     fn from_slashed_string_with_escaped_slashes() {
         let path = "simple/path/here/with escaled\\/part".to_string();
@@ -0,0 +1,269 @@
+use bevy::{
+    animation::{
+        AnimationCurves, AnimationEvaluationError, VariableCurve,
+        animation_curves::{AnimationCurve, AnimationCurveEvaluator, EvaluatorId},
+        graph::AnimationNodeIndex,
+    },
+    math::curve::Interval,
+    platform::collections::HashMap,
+    reflect::prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::event_track::EventTrack;
+
+/// A time range of a clip, in seconds
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ClipRange {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl ClipRange {
+    pub fn new(start: f32, end: f32) -> Self {
+        Self { start, end }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.end - self.start
+    }
+
+    /// The same range, moved back by `offset` seconds
+    pub fn shifted(&self, offset: f32) -> Self {
+        Self::new(self.start - offset, self.end - offset)
+    }
+
+    /// Whether the range is not empty and lies within `0..=duration`, up to rounding errors in
+    /// hand-written ranges
+    pub fn is_within(&self, duration: f32) -> bool {
+        const EPSILON: f32 = 1e-4;
+        self.start >= -EPSILON && self.start < self.end && self.end <= duration + EPSILON
+    }
+
+    /// Maps times past the end of the range back into it, so that playback loops over the
+    /// range once it has been reached
+    pub fn wrap(&self, time: f32) -> f32 {
+        if time <= self.end || self.duration() <= 0. {
+            time
+        } else {
+            self.start + (time - self.start).rem_euclid(self.duration())
+        }
+    }
+}
+
+/// Curves of the given range, re-based so that the range starts at time zero
+pub(crate) fn slice_curves(curves: &AnimationCurves, range: ClipRange) -> AnimationCurves {
+    curves
+        .iter()
+        .map(|(target, curves)| {
+            let sliced = curves
+                .iter()
+                .map(|curve| {
+                    VariableCurve(Box::new(SlicedCurve {
+                        curve: curve.0.clone_value(),
+                        range,
+                    }))
+                })
+                .collect();
+            (*target, sliced)
+        })
+        .collect()
+}
+
+/// Event tracks of the given range, re-based so that the range starts at time zero. Items
+/// outside of the range are dropped and items crossing its bounds are cut.
+pub(crate) fn slice_event_tracks(
+    tracks: &HashMap<String, EventTrack>,
+    range: ClipRange,
+) -> HashMap<String, EventTrack> {
+    tracks
+        .iter()
+        .map(|(name, track)| {
+            let mut track = track.clone();
+            track.events.retain_mut(|item| {
+                item.value.start_time = item.value.start_time.max(range.start) - range.start;
+                item.value.end_time = item.value.end_time.min(range.end) - range.start;
+                item.value.start_time < item.value.end_time
+            });
+            (name.clone(), track)
+        })
+        .collect()
+}
+
+/// Moves event tracks re-based by [`slice_event_tracks`] back to the time of the source
+/// animation
+pub(crate) fn unslice_event_tracks(
+    tracks: &HashMap<String, EventTrack>,
+    range: ClipRange,
+) -> HashMap<String, EventTrack> {
+    tracks
+        .iter()
+        .map(|(name, track)| {
+            let mut track = track.clone();
+            for item in &mut track.events {
+                item.value.start_time += range.start;
+                item.value.end_time += range.start;
+            }
+            (name.clone(), track)
+        })
+        .collect()
+}
+
+/// Plays the given range of a curve, starting at time zero
+#[derive(Debug)]
+struct SlicedCurve {
+    curve: Box<dyn AnimationCurve>,
+    range: ClipRange,
+}
+
+impl AnimationCurve for SlicedCurve {
+    fn clone_value(&self) -> Box<dyn AnimationCurve> {
+        Box::new(Self {
+            curve: self.curve.clone_value(),
+            range: self.range,
+        })
+    }
+
+    fn domain(&self) -> Interval {
+        Interval::new(0., self.range.duration().max(0.)).unwrap_or(Interval::UNIT)
+    }
+
+    fn evaluator_id(&self) -> EvaluatorId<'_> {
+        self.curve.evaluator_id()
+    }
+
+    fn create_evaluator(&self) -> Box<dyn AnimationCurveEvaluator> {
+        self.curve.create_evaluator()
+    }
+
+    fn apply(
+        &self,
+        curve_evaluator: &mut dyn AnimationCurveEvaluator,
+        t: f32,
+        weight: f32,
+        graph_node: AnimationNodeIndex,
+    ) -> Result<(), AnimationEvaluationError> {
+        let t = (t + self.range.start).clamp(self.range.start, self.range.end);
+        self.curve.apply(curve_evaluator, t, weight, graph_node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bevy::{
+        animation::{
+            animated_field,
+            animation_curves::{AnimatableCurve, AnimatedField},
+        },
+        math::{Vec3, curve::ConstantCurve},
+        prelude::Transform,
+    };
+
+    use super::*;
+    use crate::event_track::{TrackItem, TrackItemValue};
+
+    /// Records the times at which it is sampled
+    #[derive(Debug, Clone, Default)]
+    struct TimeProbe(Arc<Mutex<Vec<f32>>>);
+
+    impl TimeProbe {
+        fn constant_curve() -> VariableCurve {
+            VariableCurve::new(AnimatableCurve::new(
+                animated_field!(Transform::translation),
+                ConstantCurve::new(Interval::EVERYWHERE, Vec3::ZERO),
+            ))
+        }
+    }
+
+    impl AnimationCurve for TimeProbe {
+        fn clone_value(&self) -> Box<dyn AnimationCurve> {
+            Box::new(self.clone())
+        }
+
+        fn domain(&self) -> Interval {
+            Interval::EVERYWHERE
+        }
+
+        fn evaluator_id(&self) -> EvaluatorId<'_> {
+            EvaluatorId::Type(std::any::TypeId::of::<Self>())
+        }
+
+        fn create_evaluator(&self) -> Box<dyn AnimationCurveEvaluator> {
+            Self::constant_curve().0.create_evaluator()
+        }
+
+        fn apply(
+            &self,
+            _curve_evaluator: &mut dyn AnimationCurveEvaluator,
+            t: f32,
+            _weight: f32,
+            _graph_node: AnimationNodeIndex,
+        ) -> Result<(), AnimationEvaluationError> {
+            self.0.lock().unwrap().push(t);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sliced_curve_plays_the_range_from_zero() {
+        let probe = TimeProbe::default();
+        let curve = SlicedCurve {
+            curve: Box::new(probe.clone()),
+            range: ClipRange::new(1., 3.),
+        };
+        assert_eq!(curve.domain(), Interval::new(0., 2.).unwrap());
+
+        let mut evaluator = curve.create_evaluator();
+        for t in [0., 0.5, 2., 5.] {
+            curve
+                .apply(evaluator.as_mut(), t, 1., AnimationNodeIndex::default())
+                .unwrap();
+        }
+        assert_eq!(*probe.0.lock().unwrap(), [1., 1.5, 3., 3.]);
+    }
+
+    #[test]
+    fn test_slice_event_tracks() {
+        let item = |start_time, end_time| TrackItemValue {
+            start_time,
+            end_time,
+            ..Default::default()
+        };
+        let track = EventTrack {
+            name: "steps".into(),
+            events: [
+                item(0., 0.5),
+                item(0.75, 1.25),
+                item(1.5, 1.75),
+                item(2.5, 4.),
+            ]
+            .into_iter()
+            .map(TrackItem::new)
+            .collect(),
+        };
+        let tracks = HashMap::from_iter([("steps".to_string(), track)]);
+        let range = ClipRange::new(1., 3.);
+
+        let sliced = slice_event_tracks(&tracks, range);
+        let times: Vec<_> = sliced["steps"]
+            .events
+            .iter()
+            .map(|item| (item.value.start_time, item.value.end_time))
+            .collect();
+        assert_eq!(times, [(0., 0.25), (0.5, 0.75), (1.5, 2.)]);
+
+        let unsliced = unslice_event_tracks(&sliced, range);
+        assert_eq!(unsliced["steps"].events[1].value.start_time, 1.5);
+    }
+
+    #[test]
+    fn test_wrap_loops_over_the_range() {
+        let range = ClipRange::new(1., 2.);
+        assert_eq!(range.wrap(0.5), 0.5);
+        assert_eq!(range.wrap(1.5), 1.5);
+        assert_eq!(range.wrap(2.25), 1.25);
+        assert_eq!(range.wrap(3.5), 1.5);
+    }
+}
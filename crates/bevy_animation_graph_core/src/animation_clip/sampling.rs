@@ -0,0 +1,364 @@
+use bevy::math::{Quat, Vec3, Vec4};
+
+use super::{
+    Interpolation,
+    keyframes::{BoneKeyframes, KeyframeTrack},
+};
+
+/// Values that keyframes can be interpolated between
+pub trait KeyframeValue: Clone {
+    fn interpolate_linear(a: &Self, b: &Self, t: f32) -> Self;
+
+    /// Cubic Hermite spline from `a` to `b`, which are `dt` seconds apart. Tangents are in units
+    /// per second, like in glTF.
+    fn interpolate_hermite(a: &Self, a_out: &Self, b: &Self, b_in: &Self, dt: f32, t: f32) -> Self;
+
+    /// Smooth cubic curve from `a` to `b` for keyframes without tangents, shaped by the
+    /// keyframes before `a` and after `b` if there are any. Keyframes are given with their time.
+    fn interpolate_smooth(
+        prev: Option<(f32, &Self)>,
+        a: (f32, &Self),
+        b: (f32, &Self),
+        next: Option<(f32, &Self)>,
+        t: f32,
+    ) -> Self;
+}
+
+impl<T: KeyframeValue> KeyframeTrack<T> {
+    /// Value of the track at `time`, which is held before the first and after the last keyframe.
+    ///
+    /// `interpolation` overrides the interpolation of the track. Cubic spline interpolation uses
+    /// the tangents of the track if it has them, and a Catmull-Rom spline (squad for rotations)
+    /// through the keyframes otherwise. Returns `None` for tracks with no or missing values.
+    pub fn sample(&self, time: f32, interpolation: Option<Interpolation>) -> Option<T> {
+        let stride = self.values_per_keyframe();
+        if self.is_empty() || self.values.len() != self.len() * stride {
+            return None;
+        }
+        let value = |index: usize| &self.values[index * stride + stride / 2];
+        let keyframe = |index: usize| (self.times[index], value(index));
+
+        let next = self
+            .times
+            .partition_point(|keyframe_time| *keyframe_time <= time);
+        if next == 0 {
+            return Some(value(0).clone());
+        }
+        if next == self.len() {
+            return Some(value(self.len() - 1).clone());
+        }
+
+        let (a, b) = (next - 1, next);
+        let dt = self.times[b] - self.times[a];
+        let t = (time - self.times[a]) / dt;
+
+        let sampled = match interpolation.unwrap_or(self.interpolation) {
+            Interpolation::Step => value(a).clone(),
+            Interpolation::Linear => T::interpolate_linear(value(a), value(b), t),
+            Interpolation::CubicSpline if self.interpolation == Interpolation::CubicSpline => {
+                let out_tangent = &self.values[a * stride + 2];
+                let in_tangent = &self.values[b * stride];
+                T::interpolate_hermite(value(a), out_tangent, value(b), in_tangent, dt, t)
+            }
+            Interpolation::CubicSpline => T::interpolate_smooth(
+                a.checked_sub(1).map(keyframe),
+                keyframe(a),
+                keyframe(b),
+                (b + 1 < self.len()).then(|| keyframe(b + 1)),
+                t,
+            ),
+        };
+
+        Some(sampled)
+    }
+}
+
+/// Values of the animated properties of a bone at some time, see [`BoneKeyframes::sample`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BoneSample {
+    pub translation: Option<Vec3>,
+    pub rotation: Option<Quat>,
+    pub scale: Option<Vec3>,
+    pub weights: Option<Vec<f32>>,
+}
+
+impl BoneKeyframes {
+    /// Samples every track of the bone, see [`KeyframeTrack::sample`]
+    pub fn sample(&self, time: f32, interpolation: Option<Interpolation>) -> BoneSample {
+        BoneSample {
+            translation: self
+                .translation
+                .as_ref()
+                .and_then(|track| track.sample(time, interpolation)),
+            rotation: self
+                .rotation
+                .as_ref()
+                .and_then(|track| track.sample(time, interpolation)),
+            scale: self
+                .scale
+                .as_ref()
+                .and_then(|track| track.sample(time, interpolation)),
+            weights: self
+                .weights
+                .as_ref()
+                .and_then(|track| track.sample(time, interpolation)),
+        }
+    }
+}
+
+/// Values that splines are built from weighted sums of
+trait LinearCombination: Sized {
+    fn linear_combination(terms: &[(f32, &Self)]) -> Self;
+}
+
+impl LinearCombination for Vec3 {
+    fn linear_combination(terms: &[(f32, &Self)]) -> Self {
+        terms.iter().map(|(weight, value)| *weight * **value).sum()
+    }
+}
+
+impl LinearCombination for Vec4 {
+    fn linear_combination(terms: &[(f32, &Self)]) -> Self {
+        terms.iter().map(|(weight, value)| *weight * **value).sum()
+    }
+}
+
+impl LinearCombination for Vec<f32> {
+    fn linear_combination(terms: &[(f32, &Self)]) -> Self {
+        let width = terms
+            .iter()
+            .map(|(_, value)| value.len())
+            .min()
+            .unwrap_or(0);
+        (0..width)
+            .map(|i| terms.iter().map(|(weight, value)| weight * value[i]).sum())
+            .collect()
+    }
+}
+
+fn hermite<T: LinearCombination>(a: &T, a_out: &T, b: &T, b_in: &T, dt: f32, t: f32) -> T {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    T::linear_combination(&[
+        (2. * t3 - 3. * t2 + 1., a),
+        ((t3 - 2. * t2 + t) * dt, a_out),
+        (-2. * t3 + 3. * t2, b),
+        ((t3 - t2) * dt, b_in),
+    ])
+}
+
+/// Slope between two keyframes, in units per second
+fn slope<T: LinearCombination>((from_time, from): (f32, &T), (to_time, to): (f32, &T)) -> T {
+    let dt = (to_time - from_time).max(f32::EPSILON);
+    T::linear_combination(&[(1. / dt, to), (-1. / dt, from)])
+}
+
+/// Hermite spline whose tangent at each keyframe is the slope between its neighbours. At the
+/// ends of the track, the slope of the segment is used instead.
+fn catmull_rom<T: LinearCombination>(
+    prev: Option<(f32, &T)>,
+    a: (f32, &T),
+    b: (f32, &T),
+    next: Option<(f32, &T)>,
+    t: f32,
+) -> T {
+    let a_tangent = slope(prev.unwrap_or(a), b);
+    let b_tangent = slope(a, next.unwrap_or(b));
+    hermite(a.1, &a_tangent, b.1, &b_tangent, b.0 - a.0, t)
+}
+
+impl KeyframeValue for Vec3 {
+    fn interpolate_linear(a: &Self, b: &Self, t: f32) -> Self {
+        a.lerp(*b, t)
+    }
+
+    fn interpolate_hermite(a: &Self, a_out: &Self, b: &Self, b_in: &Self, dt: f32, t: f32) -> Self {
+        hermite(a, a_out, b, b_in, dt, t)
+    }
+
+    fn interpolate_smooth(
+        prev: Option<(f32, &Self)>,
+        a: (f32, &Self),
+        b: (f32, &Self),
+        next: Option<(f32, &Self)>,
+        t: f32,
+    ) -> Self {
+        catmull_rom(prev, a, b, next, t)
+    }
+}
+
+impl KeyframeValue for Vec<f32> {
+    fn interpolate_linear(a: &Self, b: &Self, t: f32) -> Self {
+        Self::linear_combination(&[(1. - t, a), (t, b)])
+    }
+
+    fn interpolate_hermite(a: &Self, a_out: &Self, b: &Self, b_in: &Self, dt: f32, t: f32) -> Self {
+        hermite(a, a_out, b, b_in, dt, t)
+    }
+
+    fn interpolate_smooth(
+        prev: Option<(f32, &Self)>,
+        a: (f32, &Self),
+        b: (f32, &Self),
+        next: Option<(f32, &Self)>,
+        t: f32,
+    ) -> Self {
+        catmull_rom(prev, a, b, next, t)
+    }
+}
+
+impl KeyframeValue for Quat {
+    fn interpolate_linear(a: &Self, b: &Self, t: f32) -> Self {
+        a.slerp(*b, t)
+    }
+
+    /// The spline is evaluated on the quaternion components and normalized, as glTF specifies
+    fn interpolate_hermite(a: &Self, a_out: &Self, b: &Self, b_in: &Self, dt: f32, t: f32) -> Self {
+        let [a, a_out, b, b_in] = [a, a_out, b, b_in].map(|q| Vec4::from(*q));
+        Quat::from_vec4(hermite(&a, &a_out, &b, &b_in, dt, t)).normalize()
+    }
+
+    /// Spherical quadrangle interpolation (squad). Keyframe times are not taken into account, so
+    /// the curve is smoothest for evenly spaced keyframes.
+    fn interpolate_smooth(
+        prev: Option<(f32, &Self)>,
+        (_, a): (f32, &Self),
+        (_, b): (f32, &Self),
+        next: Option<(f32, &Self)>,
+        t: f32,
+    ) -> Self {
+        let a = *a;
+        let b = same_hemisphere(*b, a);
+        let a_control = prev.map_or(a, |(_, prev)| {
+            squad_control_point(same_hemisphere(*prev, a), a, b)
+        });
+        let b_control = next.map_or(b, |(_, next)| {
+            squad_control_point(a, b, same_hemisphere(*next, b))
+        });
+
+        a.slerp(b, t)
+            .slerp(a_control.slerp(b_control, t), 2. * t * (1. - t))
+    }
+}
+
+/// Control point of a squad curve at `q`, that makes the curve through `prev`, `q` and `next`
+/// smooth at `q`
+fn squad_control_point(prev: Quat, q: Quat, next: Quat) -> Quat {
+    let inverse = q.inverse();
+    let to_prev = (inverse * prev).to_scaled_axis();
+    let to_next = (inverse * next).to_scaled_axis();
+    q * Quat::from_scaled_axis(-(to_prev + to_next) / 4.)
+}
+
+/// `q` or `-q`, whichever is closer to `reference`. Both represent the same rotation.
+fn same_hemisphere(q: Quat, reference: Quat) -> Quat {
+    if q.dot(reference) < 0. { -q } else { q }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{Quat, Vec3};
+
+    use super::*;
+
+    fn track<T>(
+        interpolation: Interpolation,
+        keyframes: impl IntoIterator<Item = (f32, T)>,
+    ) -> KeyframeTrack<T> {
+        let mut track = KeyframeTrack::new(interpolation);
+        for (time, value) in keyframes {
+            track.push(time, value);
+        }
+        track
+    }
+
+    /// Keyframes of `x²`, which Catmull-Rom splines reproduce exactly between the inner keyframes
+    fn squares() -> KeyframeTrack<Vec3> {
+        track(
+            Interpolation::Linear,
+            [0., 1., 2., 3.].map(|x| (x, Vec3::splat(x * x))),
+        )
+    }
+
+    #[test]
+    fn test_sample_holds_values_outside_of_the_track() {
+        let track = squares();
+        assert_eq!(track.sample(-1., None), Some(Vec3::ZERO));
+        assert_eq!(track.sample(5., None), Some(Vec3::splat(9.)));
+        assert_eq!(KeyframeTrack::<Vec3>::default().sample(0., None), None);
+    }
+
+    #[test]
+    fn test_sample_overrides_interpolation() {
+        let track = squares();
+        assert_eq!(track.sample(1.5, None), Some(Vec3::splat(2.5)));
+        assert_eq!(
+            track.sample(1.5, Some(Interpolation::Step)),
+            Some(Vec3::splat(1.))
+        );
+        assert_eq!(
+            track.sample(1.5, Some(Interpolation::CubicSpline)),
+            Some(Vec3::splat(2.25))
+        );
+        // The first segment has a one-sided tangent at the start of the track
+        assert_eq!(
+            track.sample(0.5, Some(Interpolation::CubicSpline)),
+            Some(Vec3::splat(0.375))
+        );
+    }
+
+    #[test]
+    fn test_sample_uses_gltf_tangents() {
+        let mut track = KeyframeTrack::new(Interpolation::CubicSpline);
+        track.push_with_tangents(0., Vec3::ZERO, Vec3::ZERO, Vec3::X);
+        track.push_with_tangents(2., Vec3::ZERO, Vec3::X, Vec3::ZERO);
+        assert_eq!(track.sample(1., None), Some(Vec3::new(0.75, 0., 0.)));
+        assert_eq!(track.sample(2., None), Some(Vec3::X));
+        assert_eq!(
+            track.sample(1., Some(Interpolation::Linear)),
+            Some(Vec3::new(0.5, 0., 0.))
+        );
+
+        let zero = Quat::from_xyzw(0., 0., 0., 0.);
+        let mut rotations = KeyframeTrack::new(Interpolation::CubicSpline);
+        rotations.push_with_tangents(0., zero, Quat::IDENTITY, zero);
+        rotations.push_with_tangents(1., zero, Quat::from_rotation_y(1.), zero);
+        assert!(
+            rotations
+                .sample(0.5, None)
+                .unwrap()
+                .abs_diff_eq(Quat::from_rotation_y(0.5), 1e-5)
+        );
+    }
+
+    #[test]
+    fn test_squad_follows_rotation_angles() {
+        // Rotations about a single axis, by 0.1 x² radians
+        let track = track(
+            Interpolation::Linear,
+            [0., 1., 2., 3.].map(|x| (x, Quat::from_rotation_y(0.1 * x * x))),
+        );
+        let sampled = track.sample(1.5, Some(Interpolation::CubicSpline)).unwrap();
+        assert!(sampled.abs_diff_eq(Quat::from_rotation_y(0.225), 1e-5));
+
+        let linear = track.sample(1.5, None).unwrap();
+        assert!(linear.abs_diff_eq(Quat::from_rotation_y(0.25), 1e-5));
+    }
+
+    #[test]
+    fn test_sample_morph_weights() {
+        let track = track(
+            Interpolation::Step,
+            [0., 1., 2., 3.].map(|x| (x, vec![x * x, 1.])),
+        );
+        assert_eq!(track.sample(1.5, None), Some(vec![1., 1.]));
+        assert_eq!(
+            track.sample(1.5, Some(Interpolation::Linear)),
+            Some(vec![2.5, 1.])
+        );
+        assert_eq!(
+            track.sample(1.5, Some(Interpolation::CubicSpline)),
+            Some(vec![2.25, 1.])
+        );
+    }
+}
@@ -66,6 +66,7 @@ impl AssetLoader for AnimationGraphLoader {
         for (target_pin, source_pin) in serial.edges_inverted.clone().into_iter() {
             graph.add_edge(source_pin, target_pin);
         }
+        graph.compile();
 
         // Static validation
         graph.validate()?;
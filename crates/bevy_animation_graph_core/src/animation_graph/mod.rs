@@ -1,7 +1,11 @@
 pub mod loader;
+pub mod plan;
 pub mod serial;
 
-use std::any::Any;
+use std::{
+    any::Any,
+    sync::{Arc, OnceLock},
+};
 
 use bevy::{
     asset::{Asset, ReflectAsset},
@@ -14,6 +18,9 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
+    animation_graph::plan::{
+        ExecutionPlan, NodeIndex, PlannedDataSource, PlannedTimeSource, PlannedTimeTarget,
+    },
     animation_node::{AnimationNode, NodeLike},
     context::{
         deferred_gizmos::DeferredGizmos,
@@ -225,17 +232,25 @@ pub type PinMap<V> = HashMap<PinId, V>;
 #[derive(Debug, Clone, Asset, Reflect)]
 #[reflect(Asset)]
 pub struct AnimationGraph {
+    /// If modified directly, [`AnimationGraph::compile`] must be called afterwards.
     #[reflect(ignore)]
     pub nodes: HashMap<NodeId, AnimationNode>,
 
-    /// Indexed by start pin.
+    /// Indexed by start pin. If modified directly, [`AnimationGraph::compile`] must be called
+    /// afterwards.
     #[reflect(ignore)]
     pub edges: HashMap<SourcePin, TargetPin>,
 
-    /// Inverted, indexed by end pin.
+    /// Inverted, indexed by end pin. If modified directly, [`AnimationGraph::compile`] must be
+    /// called afterwards.
     #[reflect(ignore)]
     pub edges_inverted: HashMap<TargetPin, SourcePin>,
 
+    /// Compiled form of the nodes and edges, used for evaluation. Built on first use after the
+    /// nodes or edges change.
+    #[reflect(ignore)]
+    plan: OnceLock<Arc<ExecutionPlan>>,
+
     /// Defines inputs and outputs for this graph.
     pub io_spec: GraphSpec,
 
@@ -266,15 +281,37 @@ impl AnimationGraph {
             default_data: HashMap::new(),
 
             editor_metadata: EditorMetadata::default(),
+
+            plan: OnceLock::new(),
         }
     }
 
+    /// Recompiles the [`ExecutionPlan`] used for evaluation. Methods that add or remove nodes and
+    /// edges only discard the current plan, and a new one is compiled when it is next needed.
+    pub fn compile(&mut self) {
+        self.plan = OnceLock::from(Arc::new(ExecutionPlan::new(self)));
+    }
+
+    pub fn execution_plan(&self) -> &ExecutionPlan {
+        self.plan()
+    }
+
+    fn plan(&self) -> &Arc<ExecutionPlan> {
+        self.plan.get_or_init(|| Arc::new(ExecutionPlan::new(self)))
+    }
+
+    /// Discards the current plan after the nodes or edges changed
+    fn invalidate_plan(&mut self) {
+        self.plan = OnceLock::new();
+    }
+
     // --- Core graph interface: add nodes and edges
     // ----------------------------------------------------------------------------------------
     /// Add a new node to the graph
     pub fn add_node(&mut self, node: AnimationNode) {
         self.editor_metadata.node_added(node.id);
         self.nodes.insert(node.id, node);
+        self.invalidate_plan();
     }
 
     /// Add a new node to the graph
@@ -282,6 +319,7 @@ impl AnimationGraph {
         let node_id = node_id.into();
         self.nodes.remove(&node_id);
         self.editor_metadata.node_positions.remove(&node_id);
+        self.invalidate_plan();
     }
 
     /// Add a new edge to the graph
@@ -289,6 +327,7 @@ impl AnimationGraph {
         self.edges_inverted
             .insert(target_pin.clone(), source_pin.clone());
         self.edges.insert(source_pin, target_pin);
+        self.invalidate_plan();
     }
 
     /// Remove an edge from the graph.
@@ -298,6 +337,7 @@ impl AnimationGraph {
         if let Some(source_pin) = &source_pin {
             self.edges.remove(source_pin);
         }
+        self.invalidate_plan();
 
         source_pin
     }
@@ -691,82 +731,140 @@ impl AnimationGraph {
         target_pin: TargetPin,
         mut ctx: GraphContext,
     ) -> Result<DataValue, GraphError> {
-        // Get source pin
-        let Some(source_pin) = self.edges_inverted.get(&target_pin) else {
+        ctx.sync_plan(self.plan());
+
+        let source = match &target_pin {
+            TargetPin::NodeData(node_id, pin_id) => self
+                .plan()
+                .node_index(node_id)
+                .and_then(|node| self.plan().node(node).data_input(pin_id)),
+            TargetPin::OutputData(pin_id) => self.plan().output(pin_id),
+            _ => None,
+        };
+
+        let Some(source) = source else {
             return Err(GraphError::MissingEdgeToTarget(target_pin));
         };
 
-        let source_value = match source_pin {
-            SourcePin::NodeData(node_id, node_pin) => {
+        self.get_planned_data(source, ctx)
+    }
+
+    pub(crate) fn get_node_data(
+        &self,
+        node: NodeIndex,
+        pin_id: &PinId,
+        ctx: GraphContext,
+    ) -> Result<DataValue, GraphError> {
+        let planned_node = self.plan().node(node);
+        let Some(source) = planned_node.data_input(pin_id) else {
+            return Err(GraphError::MissingEdgeToTarget(TargetPin::NodeData(
+                planned_node.id,
+                pin_id.clone(),
+            )));
+        };
+
+        self.get_planned_data(source, ctx)
+    }
+
+    fn get_planned_data(
+        &self,
+        source: &PlannedDataSource,
+        mut ctx: GraphContext,
+    ) -> Result<DataValue, GraphError> {
+        match source {
+            PlannedDataSource::Node { node, slot } => {
                 let key = ctx.state_key;
 
-                if !ctx.node_caches().is_update_started(node_id.to_owned(), key) {
-                    let node = &self.nodes[node_id];
-                    self.node_update_wrapper(node, &mut ctx)?;
+                if !ctx.node_caches().is_update_started_at(*node, key) {
+                    self.node_update_wrapper(*node, &mut ctx)?;
                 }
 
-                ctx.node_caches()
-                    .get_output_data(*node_id, key, node_pin.clone())
-                    .ok_or_else(|| GraphError::OutputMissing {
-                        graph: ctx.context_id,
-                        node: *node_id,
-                        pin: node_pin.clone(),
-                    })?
+                ctx.node_caches_mut()
+                    .clone_output_data_at(*node, key, *slot)
+                    .ok_or_else(|| {
+                        let planned_node = self.plan().node(*node);
+                        GraphError::OutputMissing {
+                            graph: ctx.context_id,
+                            node: planned_node.id,
+                            pin: planned_node.data_outputs[*slot].clone(),
+                        }
+                    })
             }
-            SourcePin::InputData(graph_input_pin) => ctx
+            PlannedDataSource::Input(graph_input_pin) => ctx
                 .io
                 .get_data_back(graph_input_pin.clone(), ctx.clone())
-                .or_else(|e| self.default_data.get(graph_input_pin).cloned().ok_or(e))?,
-            SourcePin::NodeTime(_) => {
-                // TODO: Make a graph error
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-            SourcePin::InputTime(_) => {
-                // TODO: Make a graph error
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-        };
-
-        Ok(source_value)
+                .or_else(|e| self.default_data.get(graph_input_pin).cloned().ok_or(e)),
+        }
     }
 
     pub fn get_duration(
         &self,
         target_pin: TargetPin,
-        ctx: GraphContext,
+        mut ctx: GraphContext,
     ) -> Result<DurationData, GraphError> {
-        let Some(source_pin) = self.edges_inverted.get(&target_pin) else {
+        ctx.sync_plan(self.plan());
+
+        let source = match &target_pin {
+            TargetPin::NodeTime(node_id, pin_id) => self
+                .plan()
+                .node_index(node_id)
+                .and_then(|node| self.plan().node(node).time_input(pin_id)),
+            TargetPin::OutputTime => self.plan().output_time(),
+            _ => None,
+        };
+
+        let Some(source) = source else {
             return Err(GraphError::MissingEdgeToTarget(target_pin));
         };
 
-        let source_value = match source_pin {
-            SourcePin::NodeData(_, _) => {
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-            SourcePin::InputData(_) => {
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-            SourcePin::NodeTime(node_id) => {
+        self.get_planned_duration(source, ctx)
+    }
+
+    pub(crate) fn get_node_duration(
+        &self,
+        node: NodeIndex,
+        pin_id: &PinId,
+        ctx: GraphContext,
+    ) -> Result<DurationData, GraphError> {
+        let planned_node = self.plan().node(node);
+        let Some(source) = planned_node.time_input(pin_id) else {
+            return Err(GraphError::MissingEdgeToTarget(TargetPin::NodeTime(
+                planned_node.id,
+                pin_id.clone(),
+            )));
+        };
+
+        self.get_planned_duration(source, ctx)
+    }
+
+    fn get_planned_duration(
+        &self,
+        source: &PlannedTimeSource,
+        ctx: GraphContext,
+    ) -> Result<DurationData, GraphError> {
+        match source {
+            PlannedTimeSource::Node(node) => {
                 let key = ctx.state_key;
 
-                if let Ok(dur) = ctx.node_caches().get_duration(*node_id, key) {
-                    dur
-                } else {
-                    let node = &self.nodes[node_id];
-                    let should_debug = node.should_debug;
-                    node.duration(
-                        ctx.create_node_context(*node_id, self)
-                            .with_debugging(should_debug),
-                    )?;
-                    ctx.node_caches().get_duration(*node_id, key)?
+                if let Some(duration) = ctx.node_caches().get_duration_at(*node, key) {
+                    return Ok(duration);
                 }
+
+                let animation_node = self.animation_node(*node);
+                animation_node.duration(
+                    ctx.create_node_context(*node, self)
+                        .with_debugging(animation_node.should_debug),
+                )?;
+                ctx.node_caches()
+                    .get_duration_at(*node, key)
+                    .ok_or(GraphError::DurationMissing(SourcePin::NodeTime(
+                        animation_node.id,
+                    )))
             }
-            SourcePin::InputTime(pin_id) => {
-                ctx.io.get_duration_back(pin_id.clone(), ctx.clone())?
+            PlannedTimeSource::Input(pin_id) => {
+                ctx.io.get_duration_back(pin_id.clone(), ctx.clone())
             }
-        };
-
-        Ok(source_value)
+        }
     }
 
     pub fn get_time_update(
@@ -774,42 +872,81 @@ impl AnimationGraph {
         source_pin: SourcePin,
         mut ctx: GraphContext,
     ) -> Result<TimeUpdate, GraphError> {
-        let Some(target_pin) = self.edges.get(&source_pin) else {
+        ctx.sync_plan(self.plan());
+
+        let target = match &source_pin {
+            SourcePin::NodeTime(node_id) => self
+                .plan()
+                .node_index(node_id)
+                .and_then(|node| self.plan().node(node).time_output.as_ref()),
+            SourcePin::InputTime(pin) => self.plan().input_time(pin),
+            _ => None,
+        };
+
+        let Some(target) = target else {
             return Err(GraphError::MissingEdgeToSource(source_pin));
         };
 
+        self.get_planned_time_update(target, ctx)
+    }
+
+    pub(crate) fn get_node_time_update(
+        &self,
+        node: NodeIndex,
+        ctx: GraphContext,
+    ) -> Result<TimeUpdate, GraphError> {
+        let planned_node = self.plan().node(node);
+        let Some(target) = &planned_node.time_output else {
+            return Err(GraphError::MissingEdgeToSource(SourcePin::NodeTime(
+                planned_node.id,
+            )));
+        };
+
+        self.get_planned_time_update(target, ctx)
+    }
+
+    fn get_planned_time_update(
+        &self,
+        target: &PlannedTimeTarget,
+        mut ctx: GraphContext,
+    ) -> Result<TimeUpdate, GraphError> {
         let key = ctx.state_key;
 
-        let time_update = match target_pin {
-            TargetPin::NodeData(_, _) => {
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-            TargetPin::OutputData(_) => {
-                panic!("Incompatible pins connected: {source_pin:?} --> {target_pin:?}")
-            }
-            TargetPin::NodeTime(node_id, target_pin) => {
-                let node = &self.nodes[node_id];
-                if !ctx.node_caches().is_update_started(node_id.to_owned(), key) {
-                    self.node_update_wrapper(node, &mut ctx)?;
+        match target {
+            PlannedTimeTarget::Node { node, slot } => {
+                if !ctx.node_caches().is_update_started_at(*node, key) {
+                    self.node_update_wrapper(*node, &mut ctx)?;
                 }
-                ctx.node_caches()
-                    .get_input_time_update(*node_id, key, target_pin.clone())
-                    .or_else(|e| {
-                        node.try_get_time(
-                            ctx.create_node_context(node.id, self)
-                                .with_debugging(node.should_debug),
-                            target_pin.clone(),
-                        )
-                        .or(Err(e))
-                    })?
+
+                if let Some(update) = ctx
+                    .node_caches()
+                    .get_input_time_update_at(*node, key, *slot)
+                {
+                    return Ok(update);
+                }
+
+                let animation_node = self.animation_node(*node);
+                let pin_id = self.plan().node(*node).time_inputs[*slot].0.clone();
+                animation_node
+                    .try_get_time(
+                        ctx.create_node_context(*node, self)
+                            .with_debugging(animation_node.should_debug),
+                        pin_id.clone(),
+                    )
+                    .or(Err(GraphError::TimeUpdateMissingBack(TargetPin::NodeTime(
+                        animation_node.id,
+                        pin_id,
+                    ))))
             }
-            TargetPin::OutputTime => match ctx.context().query_output_time.get(key) {
-                Some(update) => update,
-                None => ctx.io.get_time_fwd(ctx.clone())?,
+            PlannedTimeTarget::Output => match ctx.context().query_output_time.get(key) {
+                Some(update) => Ok(update),
+                None => ctx.io.get_time_fwd(ctx.clone()),
             },
-        };
+        }
+    }
 
-        Ok(time_update)
+    fn animation_node(&self, node: NodeIndex) -> &AnimationNode {
+        &self.nodes[&self.plan().node(node).id]
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -832,6 +969,7 @@ impl AnimationGraph {
             entity_map,
             deferred_gizmos,
             global_input_data,
+            false,
         )
     }
 
@@ -846,6 +984,7 @@ impl AnimationGraph {
         entity_map: &HashMap<BoneId, Entity>,
         deferred_gizmos: &mut DeferredGizmos,
         global_input_data: &HashMap<PinId, DataValue>,
+        culled: bool,
     ) -> Result<HashMap<PinId, DataValue>, GraphError> {
         context_arena.next_frame();
 
@@ -858,7 +997,8 @@ impl AnimationGraph {
             entity_map,
             deferred_gizmos,
             global_input_data,
-        );
+        )
+        .with_culling(culled);
         ctx.context_mut().query_output_time = QueryOutputTime::Forced(time_update);
         let mut outputs = HashMap::new();
         for (k, _) in self.io_spec.iter_output_data() {
@@ -872,12 +1012,14 @@ impl AnimationGraph {
 
     fn node_update_wrapper(
         &self,
-        node: &AnimationNode,
+        node_index: NodeIndex,
         ctx: &mut GraphContext,
     ) -> Result<(), GraphError> {
         let key = ctx.state_key;
+        let node = self.animation_node(node_index);
 
-        ctx.node_caches_mut().mark_update_started(node.id, key);
+        ctx.node_caches_mut()
+            .mark_update_started_at(node_index, key);
 
         {
             let _node_update_span = info_span!(
@@ -886,12 +1028,12 @@ impl AnimationGraph {
             )
             .entered();
             node.update(
-                ctx.create_node_context(node.id, self)
+                ctx.create_node_context(node_index, self)
                     .with_debugging(node.should_debug),
             )?;
         }
 
-        ctx.node_caches_mut().mark_updated(node.id, key);
+        ctx.node_caches_mut().mark_updated_at(node_index, key);
 
         Ok(())
     }
@@ -0,0 +1,581 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bevy::platform::collections::HashMap;
+
+use super::{AnimationGraph, GraphInputPin, NodeId, PinId, SourcePin, TargetPin};
+
+/// Index of a node in an [`ExecutionPlan`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeIndex(pub(crate) usize);
+
+impl NodeIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Where the value of a connected data input comes from
+#[derive(Clone, Debug)]
+pub enum PlannedDataSource {
+    /// Output slot of a node
+    Node {
+        node: NodeIndex,
+        slot: usize,
+    },
+    Input(GraphInputPin),
+}
+
+/// Where the duration of a connected time input comes from
+#[derive(Clone, Debug)]
+pub enum PlannedTimeSource {
+    Node(NodeIndex),
+    Input(GraphInputPin),
+}
+
+/// Where the time update for a connected time output comes from
+#[derive(Clone, Debug)]
+pub enum PlannedTimeTarget {
+    /// Time input slot of a node
+    Node {
+        node: NodeIndex,
+        slot: usize,
+    },
+    Output,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlannedNode {
+    pub id: NodeId,
+    /// Connected data inputs
+    pub data_inputs: Vec<(PinId, PlannedDataSource)>,
+    /// Connected data outputs, in slot order
+    pub data_outputs: Vec<PinId>,
+    /// Connected time inputs, in slot order
+    pub time_inputs: Vec<(PinId, PlannedTimeSource)>,
+    pub time_output: Option<PlannedTimeTarget>,
+}
+
+impl PlannedNode {
+    fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            data_inputs: Vec::new(),
+            data_outputs: Vec::new(),
+            time_inputs: Vec::new(),
+            time_output: None,
+        }
+    }
+
+    pub fn data_input(&self, pin_id: &str) -> Option<&PlannedDataSource> {
+        self.data_inputs
+            .iter()
+            .find_map(|(id, source)| (id == pin_id).then_some(source))
+    }
+
+    pub fn data_output_slot(&self, pin_id: &str) -> Option<usize> {
+        self.data_outputs.iter().position(|id| id == pin_id)
+    }
+
+    pub fn time_input(&self, pin_id: &str) -> Option<&PlannedTimeSource> {
+        self.time_inputs
+            .iter()
+            .find_map(|(id, source)| (id == pin_id).then_some(source))
+    }
+
+    pub fn time_input_slot(&self, pin_id: &str) -> Option<usize> {
+        self.time_inputs.iter().position(|(id, _)| id == pin_id)
+    }
+}
+
+static NEXT_PLAN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Index-based form of an [`AnimationGraph`], compiled whenever its nodes or edges change.
+///
+/// Nodes are stored in topological order, and every connected pin is resolved ahead of time to a
+/// node index and a slot, so that evaluation and the per-frame node caches do not need to hash
+/// node and pin ids. Nodes are still evaluated on demand, so that nodes whose outputs are not
+/// needed in a frame (e.g. inactive branches of a blend) are skipped just like before.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionPlan {
+    /// Unique among all plans compiled in this process, `0` for the empty default plan
+    id: u64,
+    nodes: Vec<PlannedNode>,
+    node_indices: HashMap<NodeId, NodeIndex>,
+    outputs: Vec<(PinId, PlannedDataSource)>,
+    output_time: Option<PlannedTimeSource>,
+    input_times: Vec<(GraphInputPin, PlannedTimeTarget)>,
+}
+
+impl ExecutionPlan {
+    pub fn new(graph: &AnimationGraph) -> Self {
+        let order = topological_order(graph);
+        let node_indices: HashMap<NodeId, NodeIndex> = order
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| (*node_id, NodeIndex(index)))
+            .collect();
+        let mut nodes: Vec<PlannedNode> = order.into_iter().map(PlannedNode::new).collect();
+
+        // Output slots first, so that data sources can be resolved to them below
+        for source in graph.edges_inverted.values() {
+            if let SourcePin::NodeData(node_id, pin_id) = source
+                && let Some(index) = node_indices.get(node_id)
+            {
+                let node = &mut nodes[index.0];
+                if node.data_output_slot(pin_id).is_none() {
+                    node.data_outputs.push(pin_id.clone());
+                }
+            }
+        }
+
+        let data_source = |nodes: &[PlannedNode], source: &SourcePin| match source {
+            SourcePin::NodeData(node_id, pin_id) => {
+                let node = *node_indices.get(node_id)?;
+                let slot = nodes[node.0].data_output_slot(pin_id)?;
+                Some(PlannedDataSource::Node { node, slot })
+            }
+            SourcePin::InputData(pin) => Some(PlannedDataSource::Input(pin.clone())),
+            _ => None,
+        };
+
+        let time_source = |source: &SourcePin| match source {
+            SourcePin::NodeTime(node_id) => {
+                Some(PlannedTimeSource::Node(*node_indices.get(node_id)?))
+            }
+            SourcePin::InputTime(pin) => Some(PlannedTimeSource::Input(pin.clone())),
+            _ => None,
+        };
+
+        let mut outputs = Vec::new();
+        let mut output_time = None;
+
+        for (target, source) in &graph.edges_inverted {
+            match target {
+                TargetPin::NodeData(node_id, pin_id) => {
+                    if let Some(index) = node_indices.get(node_id)
+                        && let Some(source) = data_source(&nodes, source)
+                    {
+                        nodes[index.0].data_inputs.push((pin_id.clone(), source));
+                    }
+                }
+                TargetPin::OutputData(pin_id) => {
+                    if let Some(source) = data_source(&nodes, source) {
+                        outputs.push((pin_id.clone(), source));
+                    }
+                }
+                TargetPin::NodeTime(node_id, pin_id) => {
+                    if let Some(index) = node_indices.get(node_id)
+                        && let Some(source) = time_source(source)
+                    {
+                        nodes[index.0].time_inputs.push((pin_id.clone(), source));
+                    }
+                }
+                TargetPin::OutputTime => output_time = time_source(source),
+            }
+        }
+
+        // Time updates flow from the target of a time edge back to its source
+        let time_target = |nodes: &[PlannedNode], target: &TargetPin| match target {
+            TargetPin::NodeTime(node_id, pin_id) => {
+                let node = *node_indices.get(node_id)?;
+                let slot = nodes[node.0].time_input_slot(pin_id)?;
+                Some(PlannedTimeTarget::Node { node, slot })
+            }
+            TargetPin::OutputTime => Some(PlannedTimeTarget::Output),
+            _ => None,
+        };
+
+        let mut input_times = Vec::new();
+
+        for (source, target) in &graph.edges {
+            match source {
+                SourcePin::NodeTime(node_id) => {
+                    if let Some(index) = node_indices.get(node_id) {
+                        nodes[index.0].time_output = time_target(&nodes, target);
+                    }
+                }
+                SourcePin::InputTime(pin) => {
+                    if let Some(target) = time_target(&nodes, target) {
+                        input_times.push((pin.clone(), target));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            id: NEXT_PLAN_ID.fetch_add(1, Ordering::Relaxed),
+            nodes,
+            node_indices,
+            outputs,
+            output_time,
+            input_times,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Nodes in topological order: every node comes after the nodes it takes inputs from,
+    /// unless they form a cycle.
+    pub fn nodes(&self) -> &[PlannedNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, index: NodeIndex) -> &PlannedNode {
+        &self.nodes[index.0]
+    }
+
+    pub fn node_index(&self, node_id: &NodeId) -> Option<NodeIndex> {
+        self.node_indices.get(node_id).copied()
+    }
+
+    pub fn output(&self, pin_id: &str) -> Option<&PlannedDataSource> {
+        self.outputs
+            .iter()
+            .find_map(|(id, source)| (id == pin_id).then_some(source))
+    }
+
+    pub fn output_time(&self) -> Option<&PlannedTimeSource> {
+        self.output_time.as_ref()
+    }
+
+    pub fn input_time(&self, pin: &GraphInputPin) -> Option<&PlannedTimeTarget> {
+        self.input_times
+            .iter()
+            .find_map(|(id, target)| (id == pin).then_some(target))
+    }
+}
+
+/// Kahn's algorithm over data and time edges. Ties are broken by node id so that the order is
+/// deterministic. Nodes that are part of a cycle are appended at the end.
+fn topological_order(graph: &AnimationGraph) -> Vec<NodeId> {
+    let mut node_ids: Vec<NodeId> = graph.nodes.keys().copied().collect();
+    node_ids.sort_by_key(|id| id.uuid());
+
+    let mut dependents: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    let mut pending_dependencies: HashMap<NodeId, usize> =
+        node_ids.iter().map(|id| (*id, 0)).collect();
+
+    for (target, source) in &graph.edges_inverted {
+        let (SourcePin::NodeData(source_id, _) | SourcePin::NodeTime(source_id)) = source else {
+            continue;
+        };
+        let (TargetPin::NodeData(target_id, _) | TargetPin::NodeTime(target_id, _)) = target else {
+            continue;
+        };
+        if !pending_dependencies.contains_key(source_id) {
+            continue;
+        }
+        let Some(count) = pending_dependencies.get_mut(target_id) else {
+            continue;
+        };
+        *count += 1;
+        dependents.entry(*source_id).or_default().push(*target_id);
+    }
+
+    let mut order = Vec::with_capacity(node_ids.len());
+    let mut ready: Vec<NodeId> = node_ids
+        .iter()
+        .filter(|id| pending_dependencies[*id] == 0)
+        .rev()
+        .copied()
+        .collect();
+
+    while let Some(node_id) = ready.pop() {
+        order.push(node_id);
+        let Some(node_dependents) = dependents.get_mut(&node_id) else {
+            continue;
+        };
+        node_dependents.sort_by_key(|id| std::cmp::Reverse(id.uuid()));
+        for dependent in node_dependents.iter() {
+            let count = pending_dependencies.get_mut(dependent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(*dependent);
+            }
+        }
+    }
+
+    if order.len() < node_ids.len() {
+        for node_id in node_ids {
+            if pending_dependencies[&node_id] > 0 {
+                order.push(node_id);
+            }
+        }
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AssetId, Assets},
+        ecs::{entity::Entity, system::SystemState, world::World},
+        reflect::Reflect,
+    };
+
+    use super::*;
+    use crate::{
+        animation_clip::GraphClip,
+        animation_graph::TimeUpdate,
+        animation_node::{AnimationNode, NodeLike},
+        context::{
+            deferred_gizmos::DeferredGizmos, graph_context_arena::GraphContextArena,
+            new_context::NodeContext, spec_context::SpecContext, system_resources::SystemResources,
+        },
+        edge_data::{DataSpec, DataValue},
+        errors::GraphError,
+        skeleton::Skeleton,
+        state_machine::high_level::StateMachine,
+    };
+
+    #[derive(Reflect, Clone, Debug)]
+    struct TestNode;
+
+    impl NodeLike for TestNode {
+        fn update(&self, _: NodeContext) -> Result<(), GraphError> {
+            Ok(())
+        }
+
+        fn spec(&self, _: SpecContext) -> Result<(), GraphError> {
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Test".into()
+        }
+    }
+
+    fn add_node(graph: &mut AnimationGraph, node: AnimationNode) -> NodeId {
+        let id = node.id;
+        graph.add_node(node);
+        id
+    }
+
+    fn add_test_node(graph: &mut AnimationGraph) -> NodeId {
+        add_node(graph, AnimationNode::new("test", TestNode))
+    }
+
+    #[test]
+    fn test_plan_orders_nodes_after_their_inputs() {
+        let mut graph = AnimationGraph::new();
+        let c = add_test_node(&mut graph);
+        let b = add_test_node(&mut graph);
+        let a = add_test_node(&mut graph);
+        graph.add_node_parameter_edge(a, "out", b, "in");
+        graph.add_node_parameter_edge(b, "out", c, "in");
+        graph.add_node_pose_edge(a, c, "time");
+
+        let plan = graph.execution_plan();
+        let order: Vec<NodeId> = plan.nodes().iter().map(|node| node.id).collect();
+        assert_eq!(order, [a, b, c]);
+    }
+
+    #[test]
+    fn test_plan_resolves_pins_to_slots() {
+        let mut graph = AnimationGraph::new();
+        let a = add_test_node(&mut graph);
+        let b = add_test_node(&mut graph);
+        graph.add_node_parameter_edge(a, "out", b, "in");
+        graph.add_output_data_edge(b, "pose", "pose");
+        graph.add_node_pose_edge(a, b, "time");
+        graph.add_output_pose_edge(b);
+
+        let plan = graph.execution_plan();
+        let a_index = plan.node_index(&a).unwrap();
+        let b_index = plan.node_index(&b).unwrap();
+
+        assert!(matches!(
+            plan.node(b_index).data_input("in"),
+            Some(PlannedDataSource::Node { node, slot: 0 }) if *node == a_index
+        ));
+        assert!(matches!(
+            plan.output("pose"),
+            Some(PlannedDataSource::Node { node, slot: 0 }) if *node == b_index
+        ));
+        assert!(matches!(
+            plan.node(b_index).time_input("time"),
+            Some(PlannedTimeSource::Node(node)) if *node == a_index
+        ));
+        assert!(matches!(
+            plan.node(a_index).time_output,
+            Some(PlannedTimeTarget::Node { node, slot: 0 }) if node == b_index
+        ));
+        assert!(matches!(
+            plan.node(b_index).time_output,
+            Some(PlannedTimeTarget::Output)
+        ));
+    }
+
+    #[test]
+    fn test_plan_is_recompiled_on_edge_removal() {
+        let mut graph = AnimationGraph::new();
+        let a = add_test_node(&mut graph);
+        let b = add_test_node(&mut graph);
+        graph.add_node_parameter_edge(a, "out", b, "in");
+        let plan_id = graph.execution_plan().id();
+
+        graph.remove_edge_by_target(&TargetPin::NodeData(b, "in".into()));
+
+        let plan = graph.execution_plan();
+        assert_ne!(plan.id(), plan_id);
+        let b_index = plan.node_index(&b).unwrap();
+        assert!(plan.node(b_index).data_input("in").is_none());
+    }
+
+    /// Advances its time with the time updates it gets and outputs it
+    #[derive(Reflect, Clone, Debug)]
+    struct ClockNode;
+
+    impl NodeLike for ClockNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let prev_time = ctx.prev_time();
+            let time = ctx
+                .time_update_fwd()?
+                .partial_update_basic(prev_time)
+                .unwrap_or(prev_time);
+            ctx.set_time(time);
+            ctx.set_data_fwd("time", time);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_output_data("time", DataSpec::F32).add_output_time();
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Clock".into()
+        }
+    }
+
+    /// Speeds up the time of its input and scales its value by the same factor
+    #[derive(Reflect, Clone, Debug)]
+    struct ScaleNode;
+
+    impl NodeLike for ScaleNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let factor = ctx.data_back("factor")?.as_f32()?;
+            let input = match ctx.time_update_fwd()? {
+                TimeUpdate::Delta(dt) => TimeUpdate::Delta(dt * factor),
+                other => other,
+            };
+            ctx.set_time_update_back("time", input);
+            let value = ctx.data_back("in")?.as_f32()?;
+            ctx.set_time(value);
+            ctx.set_data_fwd("out", value * factor);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_input_data("factor", DataSpec::F32)
+                .add_input_data("in", DataSpec::F32)
+                .add_input_time("time");
+            ctx.add_output_data("out", DataSpec::F32).add_output_time();
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Scale".into()
+        }
+    }
+
+    #[derive(Reflect, Clone, Debug)]
+    struct SumNode;
+
+    impl NodeLike for SumNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let a = ctx.data_back("in_a")?.as_f32()?;
+            let b = ctx.data_back("in_b")?.as_f32()?;
+            ctx.set_data_fwd("out", a + b);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_input_data("in_a", DataSpec::F32)
+                .add_input_data("in_b", DataSpec::F32)
+                .add_output_data("out", DataSpec::F32);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Sum".into()
+        }
+    }
+
+    /// Clock sped up by the `speed` input. The sum reads the clock both directly and through the
+    /// scale node, so the clock must only be updated once per frame.
+    fn clock_graph() -> AnimationGraph {
+        let mut graph = AnimationGraph::new();
+        let clock = AnimationNode::new("clock", ClockNode);
+        let scale = AnimationNode::new("scale", ScaleNode);
+        let sum = AnimationNode::new("sum", SumNode);
+        let (clock, scale, sum) = (
+            add_node(&mut graph, clock),
+            add_node(&mut graph, scale),
+            add_node(&mut graph, sum),
+        );
+
+        let speed = GraphInputPin::Passthrough("speed".into());
+        graph.add_input_data(speed.clone(), DataSpec::F32);
+        graph.set_default_data(speed.clone(), DataValue::F32(2.));
+        graph.add_input_data_edge(speed, scale, "factor");
+        graph.add_node_parameter_edge(clock, "time", scale, "in");
+        graph.add_node_pose_edge(clock, scale, "time");
+        graph.add_node_parameter_edge(scale, "out", sum, "in_a");
+        graph.add_node_parameter_edge(clock, "time", sum, "in_b");
+        graph.add_output_data("sum".into(), DataSpec::F32);
+        graph.add_output_data("scaled".into(), DataSpec::F32);
+        graph.add_output_data_edge(sum, "out", "sum");
+        graph.add_output_data_edge(scale, "out", "scaled");
+        graph.add_output_time();
+        graph.add_output_pose_edge(scale);
+        graph
+    }
+
+    #[test]
+    fn test_planned_evaluation_matches_previous_evaluator() {
+        let graph = clock_graph();
+
+        let mut world = World::new();
+        world.init_resource::<Assets<GraphClip>>();
+        world.init_resource::<Assets<AnimationGraph>>();
+        world.init_resource::<Assets<StateMachine>>();
+        world.init_resource::<Assets<Skeleton>>();
+        let mut system_state = SystemState::<SystemResources>::new(&mut world);
+        let resources = system_state.get_mut(&mut world);
+
+        let mut arena = GraphContextArena::new(AssetId::default());
+        let outputs: Vec<(f32, f32)> = [
+            TimeUpdate::Delta(0.1),
+            TimeUpdate::Delta(0.1),
+            TimeUpdate::Absolute(1.),
+            TimeUpdate::Delta(0.25),
+        ]
+        .into_iter()
+        .map(|time_update| {
+            let outputs = graph
+                .query(
+                    time_update,
+                    &mut arena,
+                    &resources,
+                    Entity::PLACEHOLDER,
+                    &HashMap::default(),
+                    &mut DeferredGizmos::default(),
+                    &HashMap::default(),
+                )
+                .unwrap();
+            let output = |pin: &str| outputs[pin].as_f32().unwrap();
+            (output("scaled"), output("sum"))
+        })
+        .collect();
+
+        // Outputs of the evaluator that resolved pins by name on every query, before graphs were
+        // compiled into plans
+        let expected = [(0.4, 0.6), (0.8, 1.2), (2., 3.), (3., 4.5)];
+        assert_eq!(outputs, expected);
+    }
+}
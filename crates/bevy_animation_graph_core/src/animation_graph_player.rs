@@ -6,9 +6,10 @@ use bevy::{
     ecs::prelude::*,
     gizmos::gizmos::Gizmos,
     platform::collections::HashMap,
-    reflect::prelude::*,
+    reflect::{TypeRegistry, prelude::*},
     transform::components::Transform,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     animation_graph::{AnimationGraph, DEFAULT_OUTPUT_POSE, GraphInputPin, PinId, TimeUpdate},
@@ -23,15 +24,17 @@ use crate::{
     },
     edge_data::{
         DataValue,
-        events::{AnimationEvent, EventQueue, SampledEvent},
+        events::{AnimationEvent, EventPayload, EventQueue, SampledEvent},
     },
-    errors::GraphError,
+    errors::{GraphError, SnapshotError},
+    interpolation::inertialization::{InertializationSettings, PoseHistory, PoseInertialization},
     pose::{BoneId, Pose, RootMotionDelta},
     ragdoll::{bone_mapping::RagdollBoneMap, definition::Ragdoll, spawning::SpawnedRagdoll},
     skeleton::Skeleton,
+    snapshot::PlayerSnapshot,
 };
 
-#[derive(Default, Reflect, Clone, Copy)]
+#[derive(Default, Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PlaybackState {
     Paused,
     #[default]
@@ -46,6 +49,7 @@ impl PlaybackState {
 }
 
 #[derive(Reflect, Clone, Default)]
+#[allow(clippy::large_enum_variant)]
 pub enum AnimationSource {
     Graph(Handle<AnimationGraph>),
     Pose(Pose),
@@ -88,9 +92,28 @@ pub struct AnimationGraphPlayer {
     pub(crate) queued_events: EventQueue,
     pub(crate) outputs: HashMap<PinId, DataValue>,
 
-    io_overrides: IoOverrides,
+    /// Default output pose of the frame before the last one, used to estimate bone velocities
+    /// when starting an inertialized transition
+    previous_pose: Option<Pose>,
+    /// Time elapsed between `previous_pose` and the current default output pose
+    last_delta: f32,
+    /// Source poses of an inertialized transition requested by
+    /// [`AnimationGraphPlayer::set_animation_inertialized`], to be captured against the first
+    /// pose of the new animation
+    pending_inertialization: Option<(PoseHistory, InertializationSettings)>,
+    inertialization: Option<PoseInertialization>,
 
-    global_input_data: HashMap<PinId, DataValue>,
+    pub(crate) io_overrides: IoOverrides,
+
+    pub(crate) global_input_data: HashMap<PinId, DataValue>,
+
+    /// Whether the player is currently culled, see [`AnimationGraphPlayer::set_culled`]
+    culled: bool,
+    /// Whether the default output pose is held from before the player was culled
+    held_last_pose: bool,
+
+    /// See [`AnimationGraphPlayer::set_seed`]
+    seed: u64,
 
     /// Error that ocurred during graph evaluation in the last frame
     #[reflect(ignore)]
@@ -112,10 +135,62 @@ impl AnimationGraphPlayer {
         self.context_arena.as_ref()
     }
 
+    /// While culled, the graph keeps advancing time, state and events, but nodes skip pose
+    /// sampling where possible and the output pose is not applied to the scene. The default
+    /// output pose stays the last one evaluated before culling, with the root motion of every
+    /// culled frame. This is set automatically for scenes with
+    /// [`AnimationVisibilityCulling`](crate::animated_scene::AnimationVisibilityCulling).
+    pub fn set_culled(&mut self, culled: bool) {
+        self.culled = culled;
+    }
+
+    pub fn is_culled(&self) -> bool {
+        self.culled
+    }
+
+    /// Sets the seed from which nodes derive their random numbers, see
+    /// [`NodeContext::random_seed`](crate::context::new_context::NodeContext::random_seed).
+    /// Players with the same graph and seed make the same random choices, so give each player
+    /// its own seed unless they should be in sync. Defaults to 0.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn set_animation(&mut self, animation: AnimationSource) {
         self.animation = animation;
     }
 
+    /// Like [`AnimationGraphPlayer::set_animation`], but the switch is smoothed out by
+    /// inertializing the default output pose: the offset between the last pose of the previous
+    /// animation and the new one decays over the given duration. Only the new animation is
+    /// evaluated during the transition.
+    ///
+    /// Switching to a fixed pose is not inertialized.
+    pub fn set_animation_inertialized(
+        &mut self,
+        animation: AnimationSource,
+        settings: InertializationSettings,
+    ) {
+        if animation.is_graph() {
+            let history = PoseHistory {
+                last: self.get_default_output_pose().cloned(),
+                previous: self.previous_pose.take(),
+                delta: self.last_delta,
+            };
+            self.pending_inertialization = Some((history, settings));
+        }
+        self.animation = animation;
+    }
+
     pub fn skeleton(&self) -> &Handle<Skeleton> {
         &self.skeleton
     }
@@ -181,6 +256,7 @@ impl AnimationGraphPlayer {
             weight: 1.,
             percentage: 1.,
             track: None,
+            payload: EventPayload::None,
         });
     }
 
@@ -188,8 +264,70 @@ impl AnimationGraphPlayer {
         self.pending_update = self.pending_update.combine(&update);
     }
 
+    /// Takes a [`PlayerSnapshot`] of the graph being played. The types of all node states must be
+    /// registered in the given registry.
+    pub fn snapshot(&self, registry: &TypeRegistry) -> Result<PlayerSnapshot, SnapshotError> {
+        let context_arena = self
+            .context_arena
+            .as_ref()
+            .ok_or(SnapshotError::MissingGraph)?;
+
+        Ok(PlayerSnapshot {
+            playback_state: self.playback_state,
+            elapsed: self.elapsed,
+            graph_states: context_arena.snapshot(registry)?,
+        })
+    }
+
+    /// Restores a [`PlayerSnapshot`], e.g. to load a saved game or to roll back to an earlier
+    /// frame. The player should be playing the same graph the snapshot was taken from.
+    ///
+    /// Outputs are not part of the snapshot, they are produced again in the next update.
+    /// Transitions started with [`AnimationGraphPlayer::set_animation_inertialized`] are not
+    /// part of it either, and are cancelled.
+    pub fn restore(
+        &mut self,
+        snapshot: &PlayerSnapshot,
+        registry: &TypeRegistry,
+        asset_server: &AssetServer,
+    ) -> Result<(), SnapshotError> {
+        let AnimationSource::Graph(graph) = &self.animation else {
+            return Err(SnapshotError::MissingGraph);
+        };
+        self.context_arena
+            .get_or_insert_with(|| GraphContextArena::new(graph.id()))
+            .restore(&snapshot.graph_states, registry, asset_server)?;
+
+        self.playback_state = snapshot.playback_state;
+        self.elapsed = snapshot.elapsed;
+        self.pending_update = TimeUpdate::Delta(0.);
+        self.outputs.clear();
+        self.previous_pose = None;
+        self.pending_inertialization = None;
+        self.inertialization = None;
+
+        Ok(())
+    }
+
     /// Query the animation graph with the latest time update and inputs
     pub(crate) fn update(&mut self, system_resources: &SystemResources, root_entity: Entity) {
+        let last_pose = self
+            .outputs
+            .remove(DEFAULT_OUTPUT_POSE)
+            .and_then(|pose| pose.into_pose().ok());
+        // A pose held while culled is stale, so no velocities can be estimated from it
+        self.previous_pose = last_pose.clone().filter(|_| !self.held_last_pose);
+        if self.culled {
+            // Culled poses are incomplete, so there is nothing to inertialize from or towards
+            self.pending_inertialization = None;
+            self.inertialization = None;
+        }
+        self.held_last_pose = self.culled;
+        self.last_delta = match self.pending_update {
+            TimeUpdate::Delta(delta) => delta,
+            // Time jumps are discontinuities, we can't infer velocities across them
+            _ => 0.,
+        };
         self.outputs.clear();
         self.io_overrides.data.insert(
             GraphInputPin::Passthrough(Self::USER_EVENTS.into()),
@@ -204,15 +342,19 @@ impl AnimationGraphPlayer {
             return;
         };
 
+        let context_arena = self.context_arena.as_mut().unwrap();
+        context_arena.set_seed(self.seed);
+
         match graph.query_with_env(
             self.pending_update.clone(),
-            self.context_arena.as_mut().unwrap(),
+            context_arena,
             system_resources,
             &self.io_overrides,
             root_entity,
             &self.entity_map,
             &mut self.deferred_gizmos,
             &self.global_input_data,
+            self.culled,
         ) {
             Ok(outputs) => {
                 self.error = None;
@@ -223,6 +365,12 @@ impl AnimationGraphPlayer {
             }
         };
 
+        if self.culled {
+            self.hold_last_pose(last_pose);
+        } else {
+            self.inertialize_output();
+        }
+
         if let Some(pose) = self.outputs.get(DEFAULT_OUTPUT_POSE) {
             let _ = pose.as_pose().map(|p| self.elapsed = p.timestamp);
         }
@@ -230,6 +378,38 @@ impl AnimationGraphPlayer {
         self.pending_update = TimeUpdate::Delta(0.);
     }
 
+    /// Replaces the culled output pose with the last full one, keeping its time and root motion
+    fn hold_last_pose(&mut self, last_pose: Option<Pose>) {
+        let Some(DataValue::Pose(pose)) = self.outputs.get_mut(DEFAULT_OUTPUT_POSE) else {
+            return;
+        };
+        let Some(mut last_pose) = last_pose else {
+            return;
+        };
+
+        last_pose.timestamp = pose.timestamp;
+        last_pose.root_motion = pose.root_motion.take();
+        *pose = last_pose;
+    }
+
+    fn inertialize_output(&mut self) {
+        let Some(DataValue::Pose(pose)) = self.outputs.get_mut(DEFAULT_OUTPUT_POSE) else {
+            return;
+        };
+
+        if let Some((history, settings)) = self.pending_inertialization.take() {
+            self.inertialization = history.inertialize(pose, settings);
+        }
+
+        if let Some(inertialization) = &mut self.inertialization {
+            inertialization.apply(pose);
+            inertialization.advance(self.last_delta);
+            if inertialization.is_finished() {
+                self.inertialization = None;
+            }
+        }
+    }
+
     pub fn gizmo_for_bones(&mut self, bones: impl IntoIterator<Item = BoneId>) {
         self.debug_draw_bones
             .extend(bones.into_iter().map(|b| (b, WHITE.into(), false)));
@@ -359,6 +539,38 @@ impl AnimationGraphPlayer {
         self.get_default_output_pose()?.root_motion.as_ref()
     }
 
+    pub(crate) fn default_output_pose_mut(&mut self) -> Option<&mut Pose> {
+        match self.outputs.get_mut(DEFAULT_OUTPUT_POSE)? {
+            DataValue::Pose(pose) => Some(pose),
+            _ => None,
+        }
+    }
+
+    /// Keeps the outputs of the last evaluation around in a frame in which the graph is not
+    /// evaluated, optionally replacing the default output pose. Events and root motion are cleared
+    /// so that they are not processed twice.
+    pub(crate) fn hold_outputs(&mut self, pose: Option<Pose>) {
+        for value in self.outputs.values_mut() {
+            match value {
+                DataValue::EventQueue(queue) => queue.events.clear(),
+                DataValue::Pose(pose) => pose.root_motion = None,
+                _ => {}
+            }
+        }
+
+        if let Some(pose) = pose {
+            self.set_default_output_pose(pose);
+        }
+    }
+
+    /// Takes over the outputs of the player evaluating a
+    /// [`SharedEvaluation`](crate::shared_evaluation::SharedEvaluation) in place of this one.
+    pub(crate) fn share_outputs(&mut self, shared: &AnimationGraphPlayer) {
+        self.outputs.clone_from(&shared.outputs);
+        self.elapsed = shared.elapsed;
+        self.pending_update = TimeUpdate::Delta(0.);
+    }
+
     pub fn set_default_output_pose(&mut self, pose: Pose) {
         self.outputs.insert(DEFAULT_OUTPUT_POSE.into(), pose.into());
     }
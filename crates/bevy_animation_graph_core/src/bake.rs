@@ -0,0 +1,352 @@
+use bevy::{
+    asset::{AssetPath, Handle},
+    platform::collections::HashMap,
+};
+
+use crate::{
+    animation_clip::{
+        GraphClip,
+        keyframes::{BoneKeyframes, ClipKeyframes, KeyframeStorage},
+        loader::{GraphClipSerial, GraphClipSource},
+    },
+    animation_graph::TimeUpdate,
+    edge_data::events::{AnimationEvent, EventPayload},
+    errors::{BakeError, KeyframeError},
+    event_track::{EventTrack, TrackItemValue},
+    headless::HeadlessEvaluator,
+    id::BoneId,
+    skeleton::Skeleton,
+};
+
+/// How [`bake`] samples a graph
+#[derive(Clone, Debug)]
+pub struct BakeSettings {
+    /// Graph time of the first sample, which becomes the start of the baked clip
+    pub start: f32,
+    /// Graph time of the last sample
+    pub end: f32,
+    /// Samples per second
+    pub sample_rate: f32,
+    /// Whether events output by the graph are recorded into event tracks
+    pub events: bool,
+}
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        Self {
+            start: 0.,
+            end: 1.,
+            sample_rate: 30.,
+            events: true,
+        }
+    }
+}
+
+/// Keyframes and event tracks recorded by [`bake`]
+#[derive(Clone, Debug, Default)]
+pub struct BakedClip {
+    pub keyframes: ClipKeyframes,
+    pub event_tracks: HashMap<String, EventTrack>,
+}
+
+impl BakedClip {
+    /// A clip asset that stores the keyframes inline, to be saved as an `.anim.ron` file
+    pub fn into_serial(self, skeleton: AssetPath<'static>) -> GraphClipSerial {
+        GraphClipSerial {
+            source: GraphClipSource::Keyframes(KeyframeStorage::Inline(self.keyframes)),
+            skeleton,
+            event_tracks: self.event_tracks,
+            range: None,
+            loop_range: None,
+        }
+    }
+
+    /// Builds the clip directly, without going through an asset file
+    pub fn to_graph_clip(&self, skeleton: Handle<Skeleton>) -> Result<GraphClip, KeyframeError> {
+        GraphClip::from_keyframes(
+            self.keyframes.clone(),
+            skeleton,
+            self.event_tracks.clone(),
+            Some(GraphClipSource::Keyframes(KeyframeStorage::Inline(
+                self.keyframes.clone(),
+            ))),
+        )
+    }
+}
+
+/// Records the output pose of a graph at a fixed rate into keyframes.
+///
+/// The graph is first evaluated at [`BakeSettings::start`] and then advanced by the sample
+/// interval until [`BakeSettings::end`], so stateful nodes (e.g. springs or state machine
+/// transitions) play out as they would at runtime. Inputs of the graph are left as they are set
+/// on the player of the evaluator. Every bone and property present in the output pose gets a
+/// keyframe at each sample, and root motion is not recorded.
+///
+/// Events with a track keep it, others are recorded into a track named after the output pin.
+/// Events with the same value sampled in consecutive samples are merged into a single track
+/// item.
+pub fn bake(
+    evaluator: &mut HeadlessEvaluator,
+    settings: &BakeSettings,
+) -> Result<BakedClip, BakeError> {
+    let duration = settings.end - settings.start;
+    if !(settings.sample_rate > 0. && duration >= 0.) {
+        return Err(BakeError::InvalidSettings);
+    }
+
+    let sample_count = (duration * settings.sample_rate).ceil() as usize + 1;
+    let sample_time = |i: usize| (i as f32 / settings.sample_rate).min(duration);
+
+    let mut bones: HashMap<BoneId, BoneKeyframes> = HashMap::default();
+    let mut events = EventRecorder::default();
+
+    for i in 0..sample_count {
+        let time = sample_time(i);
+        let time_update = if i == 0 {
+            TimeUpdate::Absolute(settings.start)
+        } else {
+            TimeUpdate::Delta(time - sample_time(i - 1))
+        };
+        let step = evaluator.step(time_update)?;
+
+        if let Some(pose) = step.pose() {
+            for (index, bone_id) in pose.iter_bones() {
+                let keyframes = bones.entry(bone_id).or_default();
+                if let Some(translation) = pose.translation(index) {
+                    let track = keyframes.translation.get_or_insert_default();
+                    track.push(time, translation);
+                }
+                if let Some(rotation) = pose.rotation(index) {
+                    let track = keyframes.rotation.get_or_insert_default();
+                    track.push(time, rotation);
+                }
+                if let Some(scale) = pose.scale(index) {
+                    let track = keyframes.scale.get_or_insert_default();
+                    track.push(time, scale);
+                }
+                if let Some(weights) = pose.weights(index) {
+                    let track = keyframes.weights.get_or_insert_default();
+                    track.push(time, weights.to_vec());
+                }
+            }
+        }
+
+        if settings.events {
+            let next_time = sample_time(i + 1);
+            for (pin, event) in step.events() {
+                let track = event.track.clone().unwrap_or_else(|| pin.clone());
+                events.record(track, &event.event, &event.payload, time, next_time);
+            }
+        }
+    }
+
+    let skeleton = evaluator.skeleton().ok_or(BakeError::SkeletonMissing)?;
+    let mut bones = bones
+        .into_iter()
+        .map(|(bone_id, mut keyframes)| {
+            keyframes.path = skeleton
+                .id_to_path(bone_id)
+                .ok_or(BakeError::BoneNotInSkeleton(bone_id))?;
+            Ok(keyframes)
+        })
+        .collect::<Result<Vec<_>, BakeError>>()?;
+    bones.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(BakedClip {
+        keyframes: ClipKeyframes { duration, bones },
+        event_tracks: events.tracks,
+    })
+}
+
+#[derive(Default)]
+struct EventRecorder {
+    tracks: HashMap<String, EventTrack>,
+}
+
+impl EventRecorder {
+    /// Records an event sampled at `time`, which lasts until the next sample
+    fn record(
+        &mut self,
+        track: String,
+        event: &AnimationEvent,
+        payload: &EventPayload,
+        time: f32,
+        next_time: f32,
+    ) {
+        let track = self
+            .tracks
+            .entry(track.clone())
+            .or_insert_with(|| EventTrack {
+                name: track,
+                events: vec![],
+            });
+
+        // Items are only extended from the previous sample, so an event that stops and starts
+        // again gets two items
+        if let Some(item) = track.events.iter_mut().find(|item| {
+            item.value.end_time == time
+                && &item.value.event == event
+                && &item.value.payload == payload
+        }) {
+            item.value.end_time = next_time;
+        } else if next_time > time {
+            track.add_item(TrackItemValue {
+                event: event.clone(),
+                start_time: time,
+                end_time: next_time,
+                payload: payload.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Vec3, prelude::Transform, reflect::Reflect};
+
+    use super::*;
+    use crate::{
+        animation_clip::EntityPath,
+        animation_graph::{AnimationGraph, DEFAULT_OUTPUT_POSE},
+        animation_node::{AnimationNode, NodeLike},
+        context::{new_context::NodeContext, spec_context::SpecContext},
+        edge_data::{
+            DataSpec,
+            events::{EventQueue, SampledEvent},
+        },
+        errors::GraphError,
+        headless::GraphAssets,
+        pose::Pose,
+    };
+
+    fn bone_path() -> EntityPath {
+        EntityPath::from(vec!["root".to_string()])
+    }
+
+    /// Moves a bone along the x axis at one unit per second, and sends an event during the first
+    /// half second
+    #[derive(Reflect, Clone, Debug)]
+    struct MoveNode;
+
+    impl NodeLike for MoveNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let prev_time = ctx.prev_time();
+            let time = ctx
+                .time_update_fwd()?
+                .partial_update_basic(prev_time)
+                .unwrap_or(prev_time);
+            ctx.set_time(time);
+
+            let mut pose = Pose {
+                timestamp: time,
+                ..Default::default()
+            };
+            let index = pose.insert_bone(bone_path().id());
+            pose.set_translation(index, Vec3::X * time);
+
+            let events = if time < 0.5 {
+                EventQueue::with_events([SampledEvent {
+                    event: AnimationEvent::StringId("step".into()),
+                    ..Default::default()
+                }])
+            } else {
+                EventQueue::default()
+            };
+
+            ctx.set_data_fwd(DEFAULT_OUTPUT_POSE, pose);
+            ctx.set_data_fwd("events", events);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_output_time()
+                .add_output_data(DEFAULT_OUTPUT_POSE, DataSpec::Pose)
+                .add_output_data("events", DataSpec::EventQueue);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Move".into()
+        }
+    }
+
+    fn evaluator() -> HeadlessEvaluator {
+        let mut graph = AnimationGraph::new();
+        let node = AnimationNode::new("move", MoveNode);
+        let id = node.id;
+        graph.add_node(node);
+        graph.add_output_data(DEFAULT_OUTPUT_POSE.into(), DataSpec::Pose);
+        graph.add_output_data("events".into(), DataSpec::EventQueue);
+        graph.add_output_time();
+        graph.add_output_data_edge(id, DEFAULT_OUTPUT_POSE, DEFAULT_OUTPUT_POSE);
+        graph.add_output_data_edge(id, "events", "events");
+        graph.add_output_pose_edge(id);
+
+        let mut skeleton = Skeleton::default();
+        skeleton.add_bone(bone_path(), Transform::IDENTITY, Transform::IDENTITY);
+
+        let mut assets = GraphAssets::default();
+        let graph = assets.graphs.add(graph);
+        let skeleton = assets.skeletons.add(skeleton);
+        HeadlessEvaluator::new(assets, graph, skeleton)
+    }
+
+    #[test]
+    fn test_bake_samples_pose_at_fixed_rate() {
+        let settings = BakeSettings {
+            start: 1.,
+            end: 2.,
+            sample_rate: 4.,
+            events: false,
+        };
+        let baked = bake(&mut evaluator(), &settings).unwrap();
+
+        assert_eq!(baked.keyframes.duration, 1.);
+        assert_eq!(baked.keyframes.bones.len(), 1);
+        let bone = &baked.keyframes.bones[0];
+        assert_eq!(bone.path, bone_path());
+        assert!(bone.rotation.is_none());
+
+        let translation = bone.translation.as_ref().unwrap();
+        assert_eq!(translation.times, [0., 0.25, 0.5, 0.75, 1.]);
+        let x: Vec<_> = translation.values.iter().map(|v| v.x).collect();
+        assert_eq!(x, [1., 1.25, 1.5, 1.75, 2.]);
+        assert!(baked.event_tracks.is_empty());
+    }
+
+    #[test]
+    fn test_bake_merges_consecutive_events() {
+        let settings = BakeSettings {
+            start: 0.,
+            end: 1.,
+            sample_rate: 4.,
+            events: true,
+        };
+        let baked = bake(&mut evaluator(), &settings).unwrap();
+
+        let track = &baked.event_tracks["events"];
+        assert_eq!(track.events.len(), 1);
+        let item = &track.events[0].value;
+        assert_eq!(item.event, AnimationEvent::StringId("step".into()));
+        assert_eq!((item.start_time, item.end_time), (0., 0.5));
+
+        let clip = baked.to_graph_clip(Handle::default()).unwrap();
+        assert_eq!(clip.duration(), 1.);
+        assert!(
+            clip.curves()
+                .contains_key(&bone_path().id().animation_target_id())
+        );
+    }
+
+    #[test]
+    fn test_bake_rejects_invalid_settings() {
+        let settings = BakeSettings {
+            sample_rate: 0.,
+            ..Default::default()
+        };
+        assert!(matches!(
+            bake(&mut evaluator(), &settings),
+            Err(BakeError::InvalidSettings)
+        ));
+    }
+}
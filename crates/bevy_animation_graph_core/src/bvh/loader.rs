@@ -0,0 +1,68 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    reflect::TypePath,
+};
+use serde::{Deserialize, Serialize};
+
+use super::Bvh;
+use crate::{
+    animation_clip::{GraphClip, loader::GraphClipSource},
+    errors::{AssetLoaderError, BvhError},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BvhLoaderSettings {
+    /// Factor applied to offsets and positions, e.g. `0.01` for files in centimeters
+    pub unit_scale: f32,
+}
+
+impl Default for BvhLoaderSettings {
+    fn default() -> Self {
+        Self { unit_scale: 1. }
+    }
+}
+
+/// Loads a BVH file as a [`GraphClip`] with every frame of the motion. The skeleton of the file
+/// is added as the `Skeleton` labeled asset, and is the skeleton of the clip.
+#[derive(Default, TypePath)]
+pub struct BvhLoader;
+
+impl AssetLoader for BvhLoader {
+    type Asset = GraphClip;
+    type Settings = BvhLoaderSettings;
+    type Error = AssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let bvh = parse_bvh(&bytes)?;
+
+        let skeleton = load_context
+            .add_labeled_asset("Skeleton".to_string(), bvh.skeleton(settings.unit_scale));
+        let source = GraphClipSource::Bvh {
+            path: load_context.path().clone(),
+            unit_scale: Some(settings.unit_scale),
+        };
+
+        Ok(GraphClip::from_keyframes(
+            bvh.keyframes(settings.unit_scale),
+            skeleton,
+            Default::default(),
+            Some(source),
+        )?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bvh"]
+    }
+}
+
+pub(crate) fn parse_bvh(bytes: &[u8]) -> Result<Bvh, BvhError> {
+    let source = std::str::from_utf8(bytes).map_err(|_| BvhError::InvalidUtf8)?;
+    Bvh::parse(source)
+}
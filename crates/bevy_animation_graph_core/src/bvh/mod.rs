@@ -0,0 +1,426 @@
+//! Parsing of BVH motion capture files into a [`Skeleton`] and keyframes
+
+pub mod loader;
+
+use std::iter::Peekable;
+
+use bevy::{
+    math::{Quat, Vec3},
+    transform::components::Transform,
+};
+
+use crate::{
+    animation_clip::{
+        EntityPath,
+        keyframes::{BoneKeyframes, ClipKeyframes},
+    },
+    errors::BvhError,
+    skeleton::Skeleton,
+};
+
+/// A channel of a BVH joint, i.e. one of the values stored for the joint in every frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhChannel {
+    Xposition,
+    Yposition,
+    Zposition,
+    Xrotation,
+    Yrotation,
+    Zrotation,
+}
+
+impl BvhChannel {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Xposition" => Some(Self::Xposition),
+            "Yposition" => Some(Self::Yposition),
+            "Zposition" => Some(Self::Zposition),
+            "Xrotation" => Some(Self::Xrotation),
+            "Yrotation" => Some(Self::Yrotation),
+            "Zrotation" => Some(Self::Zrotation),
+            _ => None,
+        }
+    }
+
+    fn is_position(&self) -> bool {
+        matches!(self, Self::Xposition | Self::Yposition | Self::Zposition)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BvhJoint {
+    pub name: String,
+    /// Index of the parent joint, `None` for root joints
+    pub parent: Option<usize>,
+    /// Position of the joint in the space of its parent, in the units of the file
+    pub offset: Vec3,
+    /// Channels in the order in which they are stored in each frame
+    pub channels: Vec<BvhChannel>,
+}
+
+/// Contents of a BVH file
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    /// Joints in the order of the file, so every joint comes after its parent
+    pub joints: Vec<BvhJoint>,
+    /// Time between frames in seconds
+    pub frame_time: f32,
+    /// Values of the channels of every joint, for each frame
+    pub frames: Vec<Vec<f32>>,
+}
+
+impl Bvh {
+    pub fn parse(source: &str) -> Result<Self, BvhError> {
+        let mut tokens = Tokens {
+            inner: source
+                .lines()
+                .enumerate()
+                .flat_map(|(line, text)| text.split_whitespace().map(move |t| (line + 1, t)))
+                .peekable(),
+        };
+
+        tokens.expect("HIERARCHY")?;
+        let mut joints = vec![];
+        tokens.expect("ROOT")?;
+        parse_joint(&mut tokens, None, &mut joints)?;
+        while tokens.peek() == Some("ROOT") {
+            tokens.next()?;
+            parse_joint(&mut tokens, None, &mut joints)?;
+        }
+
+        tokens.expect("MOTION")?;
+        tokens.expect("Frames:")?;
+        let frame_count: usize = tokens.parse()?;
+        tokens.expect("Frame")?;
+        tokens.expect("Time:")?;
+        let frame_time: f32 = tokens.parse()?;
+
+        let channel_count = joints.iter().map(|joint| joint.channels.len()).sum();
+        let frames = (0..frame_count)
+            .map(|_| (0..channel_count).map(|_| tokens.parse()).collect())
+            .collect::<Result<Vec<Vec<f32>>, _>>()?;
+
+        Ok(Self {
+            joints,
+            frame_time,
+            frames,
+        })
+    }
+
+    /// Path of each joint, from the root joint
+    pub fn joint_paths(&self) -> Vec<EntityPath> {
+        let mut paths: Vec<EntityPath> = Vec::with_capacity(self.joints.len());
+        for joint in &self.joints {
+            let parent = joint
+                .parent
+                .map_or_else(EntityPath::default, |parent| paths[parent].clone());
+            paths.push(parent.child(joint.name.clone()));
+        }
+        paths
+    }
+
+    /// Skeleton of the joints in their rest pose. Offsets are multiplied by `unit_scale`.
+    pub fn skeleton(&self, unit_scale: f32) -> Skeleton {
+        let mut skeleton = Skeleton::default();
+        let mut global_transforms: Vec<Transform> = Vec::with_capacity(self.joints.len());
+
+        for (index, (joint, path)) in self.joints.iter().zip(self.joint_paths()).enumerate() {
+            let local = Transform::from_translation(joint.offset * unit_scale);
+            let global = match joint.parent {
+                Some(parent) => global_transforms[parent] * local,
+                None => local,
+            };
+            global_transforms.push(global);
+
+            if index == 0 {
+                skeleton.set_root(path.id());
+            }
+            skeleton.add_bone(path, local, global);
+        }
+
+        skeleton
+    }
+
+    /// Keyframes of every frame. Joints get a rotation track if they have rotation channels and
+    /// a translation track if they have position channels, in which case the offset of the joint
+    /// is replaced. Positions are multiplied by `unit_scale`.
+    pub fn keyframes(&self, unit_scale: f32) -> ClipKeyframes {
+        let mut bones: Vec<BoneKeyframes> = self
+            .joint_paths()
+            .into_iter()
+            .map(BoneKeyframes::new)
+            .collect();
+
+        for (frame_index, frame) in self.frames.iter().enumerate() {
+            let time = frame_index as f32 * self.frame_time;
+            let mut values = frame.iter().copied();
+
+            for (joint, keyframes) in self.joints.iter().zip(&mut bones) {
+                let mut translation = joint.offset * unit_scale;
+                let mut rotation = Quat::IDENTITY;
+                // Rotations are applied in the order of the channels
+                for (channel, value) in joint.channels.iter().zip(&mut values) {
+                    match channel {
+                        BvhChannel::Xposition => translation.x = value * unit_scale,
+                        BvhChannel::Yposition => translation.y = value * unit_scale,
+                        BvhChannel::Zposition => translation.z = value * unit_scale,
+                        BvhChannel::Xrotation => {
+                            rotation *= Quat::from_rotation_x(value.to_radians())
+                        }
+                        BvhChannel::Yrotation => {
+                            rotation *= Quat::from_rotation_y(value.to_radians())
+                        }
+                        BvhChannel::Zrotation => {
+                            rotation *= Quat::from_rotation_z(value.to_radians())
+                        }
+                    }
+                }
+
+                if joint.channels.iter().any(BvhChannel::is_position) {
+                    let track = keyframes.translation.get_or_insert_default();
+                    track.push(time, translation);
+                }
+                if joint.channels.iter().any(|channel| !channel.is_position()) {
+                    let track = keyframes.rotation.get_or_insert_default();
+                    track.push(time, rotation);
+                }
+            }
+        }
+
+        ClipKeyframes {
+            duration: self.frames.len().saturating_sub(1) as f32 * self.frame_time,
+            bones: bones
+                .into_iter()
+                .filter(|bone| bone.translation.is_some() || bone.rotation.is_some())
+                .collect(),
+        }
+    }
+}
+
+fn parse_joint<'a>(
+    tokens: &mut Tokens<'a, impl Iterator<Item = (usize, &'a str)>>,
+    parent: Option<usize>,
+    joints: &mut Vec<BvhJoint>,
+) -> Result<(), BvhError> {
+    let name = tokens.next()?.to_string();
+    tokens.expect("{")?;
+    tokens.expect("OFFSET")?;
+    let offset = Vec3::new(tokens.parse()?, tokens.parse()?, tokens.parse()?);
+
+    let mut channels = vec![];
+    if tokens.peek() == Some("CHANNELS") {
+        tokens.next()?;
+        let count: usize = tokens.parse()?;
+        for _ in 0..count {
+            let (line, name) = tokens.next_with_line()?;
+            let channel = BvhChannel::parse(name).ok_or_else(|| BvhError::UnknownChannel {
+                line,
+                channel: name.to_string(),
+            })?;
+            channels.push(channel);
+        }
+    }
+
+    let index = joints.len();
+    joints.push(BvhJoint {
+        name,
+        parent,
+        offset,
+        channels,
+    });
+
+    loop {
+        let (line, token) = tokens.next_with_line()?;
+        match token {
+            "JOINT" => parse_joint(tokens, Some(index), joints)?,
+            "End" => {
+                // End sites only mark the length of the last bone of a chain
+                tokens.expect("Site")?;
+                tokens.expect("{")?;
+                tokens.expect("OFFSET")?;
+                for _ in 0..3 {
+                    tokens.parse::<f32>()?;
+                }
+                tokens.expect("}")?;
+            }
+            "}" => return Ok(()),
+            _ => {
+                return Err(BvhError::UnexpectedToken {
+                    line,
+                    expected: "JOINT, End Site or }".to_string(),
+                    found: token.to_string(),
+                });
+            }
+        }
+    }
+}
+
+struct Tokens<'a, I: Iterator<Item = (usize, &'a str)>> {
+    inner: Peekable<I>,
+}
+
+impl<'a, I: Iterator<Item = (usize, &'a str)>> Tokens<'a, I> {
+    fn peek(&mut self) -> Option<&'a str> {
+        self.inner.peek().map(|(_, token)| *token)
+    }
+
+    fn next_with_line(&mut self) -> Result<(usize, &'a str), BvhError> {
+        self.inner.next().ok_or(BvhError::UnexpectedEnd)
+    }
+
+    fn next(&mut self) -> Result<&'a str, BvhError> {
+        self.next_with_line().map(|(_, token)| token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), BvhError> {
+        match self.inner.next() {
+            Some((_, token)) if token == expected => Ok(()),
+            Some((line, token)) => Err(BvhError::UnexpectedToken {
+                line,
+                expected: expected.to_string(),
+                found: token.to_string(),
+            }),
+            None => Err(BvhError::UnexpectedEnd),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&mut self) -> Result<T, BvhError> {
+        let (line, token) = self.next_with_line()?;
+        token.parse().map_err(|_| BvhError::InvalidNumber {
+            line,
+            found: token.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAVE: &str = "HIERARCHY
+ROOT Hips
+{
+    OFFSET 0.0 90.0 0.0
+    CHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation
+    JOINT Chest
+    {
+        OFFSET 0.0 10.0 0.0
+        CHANNELS 3 Zrotation Xrotation Yrotation
+        JOINT Head
+        {
+            OFFSET 0.0 20.0 0.0
+            CHANNELS 3 Yrotation Xrotation Zrotation
+            End Site
+            {
+                OFFSET 0.0 5.0 0.0
+            }
+        }
+    }
+    JOINT Tail
+    {
+        OFFSET 0.0 -10.0 0.0
+        End Site
+        {
+            OFFSET 0.0 -5.0 0.0
+        }
+    }
+}
+MOTION
+Frames: 2
+Frame Time: 0.5
+0.0 90.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0
+10.0 80.0 0.0 90.0 45.0 0.0 30.0 60.0 90.0 30.0 60.0 90.0
+";
+
+    fn path(parts: &[&str]) -> EntityPath {
+        EntityPath::from(
+            parts
+                .iter()
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_parse_hierarchy_and_motion() {
+        let bvh = Bvh::parse(WAVE).unwrap();
+
+        assert_eq!(
+            bvh.joint_paths(),
+            [
+                path(&["Hips"]),
+                path(&["Hips", "Chest"]),
+                path(&["Hips", "Chest", "Head"]),
+                path(&["Hips", "Tail"]),
+            ]
+        );
+        assert_eq!(bvh.joints[3].parent, Some(0));
+        assert!(bvh.joints[3].channels.is_empty());
+        assert_eq!(bvh.frame_time, 0.5);
+        assert_eq!(bvh.frames.len(), 2);
+        assert_eq!(bvh.frames[1].len(), 12);
+    }
+
+    #[test]
+    fn test_rotations_follow_channel_order() {
+        let keyframes = Bvh::parse(WAVE).unwrap().keyframes(1.);
+        let rotation = |bone: usize| keyframes.bones[bone].rotation.as_ref().unwrap().values[1];
+        let (a, b, c) = (30f32.to_radians(), 60f32.to_radians(), 90f32.to_radians());
+
+        // Same values, in a different channel order
+        let chest = Quat::from_rotation_z(a) * Quat::from_rotation_x(b) * Quat::from_rotation_y(c);
+        let head = Quat::from_rotation_y(a) * Quat::from_rotation_x(b) * Quat::from_rotation_z(c);
+        assert!(rotation(1).abs_diff_eq(chest, 1e-5));
+        assert!(rotation(2).abs_diff_eq(head, 1e-5));
+        assert!(!rotation(1).abs_diff_eq(rotation(2), 0.1));
+    }
+
+    #[test]
+    fn test_unit_scale() {
+        let bvh = Bvh::parse(WAVE).unwrap();
+        let keyframes = bvh.keyframes(0.01);
+
+        assert_eq!(keyframes.duration, 0.5);
+        // Joints without channels are not animated
+        assert_eq!(keyframes.bones.len(), 3);
+        let hips = keyframes.bones[0].translation.as_ref().unwrap();
+        assert_eq!(hips.times, [0., 0.5]);
+        assert!(hips.values[1].abs_diff_eq(Vec3::new(0.1, 0.8, 0.), 1e-6));
+        assert!(keyframes.bones[1].translation.is_none());
+
+        let skeleton = bvh.skeleton(0.01);
+        let head = skeleton
+            .default_transforms(path(&["Hips", "Chest", "Head"]).id())
+            .unwrap();
+        assert!(
+            head.local
+                .translation
+                .abs_diff_eq(Vec3::new(0., 0.2, 0.), 1e-6)
+        );
+        assert!(
+            head.character
+                .translation
+                .abs_diff_eq(Vec3::new(0., 1.2, 0.), 1e-6)
+        );
+        assert_eq!(skeleton.root(), path(&["Hips"]).id());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Bvh::parse("HIERARCHY\nROOT Hips\n{\nOFFSET 0 0\n}"),
+            Err(BvhError::InvalidNumber { line: 5, .. })
+        ));
+        assert!(matches!(
+            Bvh::parse(&WAVE.replace(
+                "Zrotation Xrotation Yrotation\n        JOINT",
+                "Zrotation Xrotation Wrotation\n        JOINT"
+            )),
+            Err(BvhError::UnknownChannel { line: 9, .. })
+        ));
+        assert!(matches!(
+            Bvh::parse(&WAVE[..WAVE.len() - 10]),
+            Err(BvhError::UnexpectedEnd)
+        ));
+    }
+}
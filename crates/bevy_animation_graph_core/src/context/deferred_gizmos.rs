@@ -155,8 +155,8 @@ impl DeferredGizmosContext<'_> {
             return;
         };
 
-        for bone_id in pose.paths.keys() {
-            self.bone_gizmo(*bone_id, color, false, skeleton, Some(pose));
+        for (_, bone_id) in pose.iter_bones() {
+            self.bone_gizmo(bone_id, color, false, skeleton, Some(pose));
         }
     }
 
@@ -1,7 +1,9 @@
+use std::sync::Arc;
+
 use bevy::{asset::AssetId, platform::collections::HashMap, reflect::prelude::*};
 
 use crate::{
-    animation_graph::{AnimationGraph, TimeUpdate},
+    animation_graph::{AnimationGraph, TimeUpdate, plan::ExecutionPlan},
     context::{
         node_caches::NodeCaches,
         node_states::{NodeStates, StateKey},
@@ -26,11 +28,32 @@ impl GraphState {
         }
     }
 
+    /// Copy of the node states. Node caches only hold values for the current frame, so they
+    /// are left empty.
+    pub fn clone_states(&self) -> Self {
+        Self {
+            node_states: self.node_states.clone(),
+            node_caches: NodeCaches::default(),
+            query_output_time: QueryOutputTime::None,
+            graph_id: self.graph_id,
+        }
+    }
+
     pub fn next_frame(&mut self) {
         self.node_states.next_frame();
         self.node_caches.next_frame();
     }
 
+    /// Lays out node caches and states for the given plan, if they are not already.
+    pub(crate) fn sync_plan(&mut self, plan: &Arc<ExecutionPlan>) {
+        if self.node_caches.plan_id() != plan.id() {
+            self.node_caches.set_plan(plan.clone());
+        }
+        if self.node_states.plan_id() != plan.id() {
+            self.node_states.set_plan(plan.clone());
+        }
+    }
+
     pub fn get_graph_id(&self) -> AssetId<AnimationGraph> {
         self.graph_id
     }
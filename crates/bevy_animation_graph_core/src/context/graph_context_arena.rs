@@ -1,9 +1,18 @@
-use bevy::{asset::AssetId, platform::collections::HashMap, reflect::Reflect};
+use bevy::{
+    asset::{AssetId, AssetServer},
+    platform::collections::HashMap,
+    reflect::{Reflect, TypeRegistry},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     animation_graph::{AnimationGraph, NodeId},
-    context::graph_context::GraphState,
-    state_machine::low_level::LowLevelStateId,
+    context::{graph_context::GraphState, node_states::NodeState},
+    deterministic::mix_seed,
+    errors::SnapshotError,
+    snapshot::GraphStateSnapshot,
+    state_machine::{high_level::TransitionId, low_level::LowLevelStateId},
 };
 
 #[derive(Reflect, Clone, Copy, Debug, Eq, PartialEq, Hash, Default)]
@@ -16,11 +25,56 @@ pub struct SubContextId {
     pub state_id: Option<LowLevelStateId>,
 }
 
+/// Identifies a sub context within its parent context. Unlike [`SubContextId`], it does not
+/// depend on the order in which contexts were created, so it stays the same between runs.
+#[derive(Reflect, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SubContextKey {
+    pub node_id: NodeId,
+    pub state_id: Option<LowLevelStateId>,
+}
+
+impl From<&SubContextId> for SubContextKey {
+    fn from(value: &SubContextId) -> Self {
+        Self {
+            node_id: value.node_id,
+            state_id: value.state_id.clone(),
+        }
+    }
+}
+
+impl SubContextKey {
+    /// Random seed of the sub context, given the seed of its parent
+    pub(crate) fn seed(&self, parent_seed: u64) -> u64 {
+        let (tag, uuid) = match &self.state_id {
+            None => (0, Uuid::nil()),
+            Some(LowLevelStateId::HlState(state_id)) => (1, state_id.uuid()),
+            Some(LowLevelStateId::HlTransition(TransitionId::Direct(transition_id))) => {
+                (2, transition_id.uuid())
+            }
+            Some(LowLevelStateId::HlTransition(TransitionId::State(state_id))) => {
+                (3, state_id.uuid())
+            }
+            Some(LowLevelStateId::HlTransition(TransitionId::Fallback)) => (4, Uuid::nil()),
+        };
+        let (node_hi, node_lo) = self.node_id.uuid().as_u64_pair();
+        let (state_hi, state_lo) = uuid.as_u64_pair();
+        mix_seed([parent_seed, node_hi, node_lo, tag, state_hi, state_lo])
+    }
+}
+
+/// Graph states of a player: one for the top level graph and one for each subgraph (e.g. a graph
+/// node or an FSM state) that has been evaluated so far.
 #[derive(Reflect, Debug)]
 pub struct GraphContextArena {
     contexts: Vec<GraphState>,
     hierarchy: HashMap<SubContextId, GraphContextId>,
     top_level_context: GraphContextId,
+    /// Restored node states of sub contexts that have not been created yet, by path
+    #[reflect(ignore)]
+    restored: HashMap<Vec<SubContextKey>, HashMap<NodeId, NodeState>>,
+    /// Random seed of the top level context, see
+    /// [`NodeContext::random_seed`](crate::context::new_context::NodeContext::random_seed)
+    seed: u64,
 }
 
 impl GraphContextArena {
@@ -29,9 +83,30 @@ impl GraphContextArena {
             contexts: vec![GraphState::new(graph_id)],
             hierarchy: HashMap::default(),
             top_level_context: GraphContextId(0),
+            restored: HashMap::default(),
+            seed: 0,
+        }
+    }
+
+    /// Copy of the node states of every context, see [`GraphState::clone_states`]
+    pub fn clone_states(&self) -> Self {
+        Self {
+            contexts: self.contexts.iter().map(GraphState::clone_states).collect(),
+            hierarchy: self.hierarchy.clone(),
+            top_level_context: self.top_level_context,
+            restored: self.restored.clone(),
+            seed: self.seed,
         }
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
     pub fn iter_context_ids(&self) -> impl Iterator<Item = GraphContextId> {
         (0..self.contexts.len()).map(GraphContextId)
     }
@@ -72,6 +147,78 @@ impl GraphContextArena {
         id.0 < self.contexts.len()
     }
 
+    /// Path from the top level context to the given one, empty for the top level context
+    pub fn context_path(&self, id: GraphContextId) -> Vec<SubContextKey> {
+        let mut path = Vec::new();
+        let mut current = id;
+        while let Some(subctx_id) = self
+            .hierarchy
+            .iter()
+            .find_map(|(subctx_id, child)| (*child == current).then_some(subctx_id))
+        {
+            path.push(subctx_id.into());
+            current = subctx_id.ctx_id;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Node states of every context, see [`PlayerSnapshot`](crate::snapshot::PlayerSnapshot)
+    pub fn snapshot(
+        &self,
+        registry: &TypeRegistry,
+    ) -> Result<Vec<GraphStateSnapshot>, SnapshotError> {
+        let contexts = self.iter_context_ids().map(|id| {
+            GraphStateSnapshot::new(
+                self.context_path(id),
+                self.contexts[id.0].node_states.iter_by_id(),
+                registry,
+            )
+        });
+        let restored = self.restored.iter().map(|(path, states)| {
+            GraphStateSnapshot::new(
+                path.clone(),
+                states.iter().map(|(id, state)| (*id, state)),
+                registry,
+            )
+        });
+
+        contexts.chain(restored).collect()
+    }
+
+    /// Replaces the node states of every context with the ones in the snapshot. Sub contexts
+    /// that have not been created yet get their states when they are.
+    pub fn restore(
+        &mut self,
+        snapshot: &[GraphStateSnapshot],
+        registry: &TypeRegistry,
+        asset_server: &AssetServer,
+    ) -> Result<(), SnapshotError> {
+        let states = snapshot
+            .iter()
+            .map(|graph_state| {
+                Ok((
+                    graph_state.path.clone(),
+                    graph_state.node_states(registry, asset_server)?,
+                ))
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        self.restore_node_states(states);
+        Ok(())
+    }
+
+    fn restore_node_states(
+        &mut self,
+        mut states: HashMap<Vec<SubContextKey>, HashMap<NodeId, NodeState>>,
+    ) {
+        for index in 0..self.contexts.len() {
+            let id = GraphContextId(index);
+            let context_states = states.remove(&self.context_path(id)).unwrap_or_default();
+            self.contexts[id.0].node_states.restore(context_states);
+        }
+        self.restored = states;
+    }
+
     pub(super) fn get_sub_context_or_insert_default(
         &mut self,
         subctx_id: SubContextId,
@@ -81,12 +228,25 @@ impl GraphContextArena {
             panic!("Context does not exist");
         }
 
-        if !self.hierarchy.contains_key(&subctx_id) {
-            let child_node_id = self.new_context(subgraph_id);
-            self.hierarchy.insert(subctx_id.clone(), child_node_id);
+        if let Some(&id) = self.hierarchy.get(&subctx_id) {
+            // The node now plays another graph, e.g. after its state machine was reloaded. None
+            // of the old node states apply anymore.
+            if self.contexts[id.0].get_graph_id() != subgraph_id {
+                self.contexts[id.0] = GraphState::new(subgraph_id);
+            }
+            return id;
+        }
+
+        let child_node_id = self.new_context(subgraph_id);
+        self.hierarchy.insert(subctx_id, child_node_id);
+
+        if !self.restored.is_empty()
+            && let Some(states) = self.restored.remove(&self.context_path(child_node_id))
+        {
+            self.contexts[child_node_id.0].node_states.restore(states);
         }
 
-        *self.hierarchy.get(&subctx_id).unwrap()
+        child_node_id
     }
 }
 
@@ -112,3 +272,79 @@ impl GraphContextArenaRef {
         unsafe { self.context.as_ref().unwrap() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_restored_states_are_applied_to_new_sub_contexts() {
+        let node_id = NodeId::from(Uuid::from_u128(1));
+        let key = SubContextKey {
+            node_id,
+            state_id: None,
+        };
+        let mut arena = GraphContextArena::new(AssetId::default());
+        arena.restore_node_states(
+            [(
+                vec![key.clone()],
+                [(node_id, NodeState::restored(2., None))].into(),
+            )]
+            .into(),
+        );
+
+        let child = arena.get_sub_context_or_insert_default(
+            SubContextId {
+                ctx_id: arena.get_toplevel_id(),
+                node_id,
+                state_id: None,
+            },
+            AssetId::default(),
+        );
+        assert_eq!(arena.context_path(child), vec![key]);
+
+        let node_states: Vec<_> = arena
+            .get_context(child)
+            .unwrap()
+            .node_states
+            .iter_by_id()
+            .collect();
+        assert_eq!(node_states.len(), 1);
+        assert_eq!(node_states[0].0, node_id);
+        assert_eq!(node_states[0].1.current_time(), 2.);
+    }
+
+    #[test]
+    fn test_sub_context_is_reset_when_its_graph_changes() {
+        let node_id = NodeId::from(Uuid::from_u128(1));
+        let subctx_id = |arena: &GraphContextArena| SubContextId {
+            ctx_id: arena.get_toplevel_id(),
+            node_id,
+            state_id: None,
+        };
+        let graph = |uuid| AssetId::<AnimationGraph>::Uuid {
+            uuid: Uuid::from_u128(uuid),
+        };
+        let mut arena = GraphContextArena::new(AssetId::default());
+        let child = arena.get_sub_context_or_insert_default(subctx_id(&arena), graph(2));
+        arena
+            .get_context_mut(child)
+            .unwrap()
+            .node_states
+            .restore([(node_id, NodeState::restored(2., None))].into());
+
+        // Same graph, the states are kept
+        let same = arena.get_sub_context_or_insert_default(subctx_id(&arena), graph(2));
+        assert_eq!(same, child);
+        let context = arena.get_context(child).unwrap();
+        assert_eq!(context.node_states.iter_by_id().count(), 1);
+
+        let changed = arena.get_sub_context_or_insert_default(subctx_id(&arena), graph(3));
+        assert_eq!(changed, child);
+        let context = arena.get_context(child).unwrap();
+        assert_eq!(context.get_graph_id(), graph(3));
+        assert_eq!(context.node_states.iter_by_id().count(), 0);
+    }
+}
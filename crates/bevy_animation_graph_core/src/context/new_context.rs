@@ -1,13 +1,21 @@
-use bevy::{asset::AssetId, ecs::entity::Entity, platform::collections::HashMap};
-use uuid::Uuid;
+use std::sync::Arc;
+
+use bevy::{
+    asset::{AssetId, Handle},
+    ecs::entity::Entity,
+    platform::collections::HashMap,
+};
 
 use crate::{
-    animation_graph::{AnimationGraph, NodeId, PinId, SourcePin, TargetPin, TimeUpdate},
+    animation_graph::{
+        AnimationGraph, NodeId, PinId, TimeUpdate,
+        plan::{ExecutionPlan, NodeIndex},
+    },
     context::{
         deferred_gizmos::{DeferredGizmoRef, DeferredGizmosContext},
         graph_context::GraphState,
         graph_context_arena::{
-            GraphContextArena, GraphContextArenaRef, GraphContextId, SubContextId,
+            GraphContextArena, GraphContextArenaRef, GraphContextId, SubContextId, SubContextKey,
         },
         io_env::{GraphIoEnv, GraphIoEnvBox},
         node_caches::NodeCaches,
@@ -15,10 +23,13 @@ use crate::{
         pose_fallback::PoseFallbackContext,
         system_resources::SystemResources,
     },
+    deterministic::mix_seed,
     duration_data::DurationData,
     edge_data::DataValue,
     errors::GraphError,
     id::BoneId,
+    pose::Pose,
+    skeleton::Skeleton,
     space_conversion::SpaceConversionContext,
     state_machine::low_level::LowLevelStateId,
 };
@@ -29,6 +40,11 @@ pub struct GraphContext<'a> {
     pub root_entity: Entity,
     pub state_key: StateKey,
     pub should_debug: bool,
+    /// Whether the player is culled. Nodes may skip any work that only affects the output pose,
+    /// as it will not be shown, but must still update time, state and events.
+    pub culled: bool,
+    /// Random seed of the current context, see [`NodeContext::random_seed`]
+    pub seed: u64,
     pub io: GraphIoEnvBox<'a>,
 
     pub context_arena: GraphContextArenaRef,
@@ -51,11 +67,14 @@ impl<'a> GraphContext<'a> {
         deferred_gizmos: impl Into<DeferredGizmoRef>,
         global_input_data: &'a HashMap<PinId, DataValue>,
     ) -> Self {
+        let seed = context_arena.seed();
         Self {
             context_id,
             root_entity,
             state_key: StateKey::Default,
             should_debug: false,
+            culled: false,
+            seed,
             io: GraphIoEnvBox::new(io),
             context_arena: context_arena.into(),
             deferred_gizmos: deferred_gizmos.into(),
@@ -69,11 +88,12 @@ impl<'a> GraphContext<'a> {
     /// passing the context down to a node.
     pub fn create_node_context(
         &self,
-        node_id: NodeId,
+        node_index: NodeIndex,
         graph: &'a AnimationGraph,
     ) -> NodeContext<'a> {
         NodeContext {
-            node_id,
+            node_id: graph.execution_plan().node(node_index).id,
+            node_index,
             graph,
             graph_context: self.clone(),
         }
@@ -84,6 +104,11 @@ impl<'a> GraphContext<'a> {
         self
     }
 
+    pub fn with_culling(mut self, culled: bool) -> Self {
+        self.culled = culled;
+        self
+    }
+
     /// Return a mutable reference to the [`GraphState`]
     pub fn context_mut(&mut self) -> &mut GraphState {
         self.context_arena
@@ -142,9 +167,9 @@ impl<'a> GraphContext<'a> {
         }
     }
 
-    /// Returns a new pass context with the given temp cache value.
-    pub fn with_temp_state_key(mut self) -> Self {
-        self.state_key = StateKey::Temporary(Uuid::new_v4());
+    /// Returns a new pass context with a temporary state key for a query made by the given node.
+    pub fn with_temp_state_key(mut self, node_id: NodeId) -> Self {
+        self.state_key = self.state_key.temporary(node_id);
         self
     }
 
@@ -153,6 +178,12 @@ impl<'a> GraphContext<'a> {
         self
     }
 
+    /// Lays out the node caches and states of the current context for the given plan, if they
+    /// are not already.
+    pub(crate) fn sync_plan(&mut self, plan: &Arc<ExecutionPlan>) {
+        self.context_mut().sync_plan(plan);
+    }
+
     pub fn with_io(mut self, new_io: &'a dyn GraphIoEnv) -> Self {
         self.io = GraphIoEnvBox::new(new_io);
         self
@@ -162,6 +193,8 @@ impl<'a> GraphContext<'a> {
 #[derive(Clone)]
 pub struct NodeContext<'a> {
     pub node_id: NodeId,
+    /// Index of the node in the graph's [`ExecutionPlan`]
+    pub node_index: NodeIndex,
     pub graph: &'a AnimationGraph,
     pub graph_context: GraphContext<'a>,
 }
@@ -172,10 +205,19 @@ impl<'a> NodeContext<'a> {
         self
     }
 
+    /// Seed for random numbers of this node. It depends only on the seed of the player and on the
+    /// position of the node in the graph, so it is the same every frame and on every run.
+    /// Nodes should seed their generator with it once and keep the generator in their state,
+    /// which makes it part of [snapshots](crate::snapshot::PlayerSnapshot) too.
+    pub fn random_seed(&self) -> u64 {
+        let (node_hi, node_lo) = self.node_id.uuid().as_u64_pair();
+        mix_seed([self.graph_context.seed, node_hi, node_lo])
+    }
+
     /// Request an input parameter from the graph
     pub fn data_back(&self, pin_id: impl Into<PinId>) -> Result<DataValue, GraphError> {
-        let target_pin = TargetPin::NodeData(self.node_id, pin_id.into());
-        self.graph.get_data(target_pin, self.graph_context.clone())
+        self.graph
+            .get_node_data(self.node_index, &pin_id.into(), self.graph_context.clone())
     }
 
     /// Sets the output value at the given pin for the current node. It's up to the caller to
@@ -185,14 +227,49 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context_mut()
             .node_caches
-            .set_output_data(self.node_id, key, pin_id.into(), data.into());
+            .set_output_data_at(self.node_index, key, pin_id.into(), data.into());
+    }
+
+    /// Creates an empty pose for the given skeleton, reusing the buffers of poses from previous
+    /// frames when possible.
+    pub fn new_pose(&mut self, skeleton: &Handle<Skeleton>) -> Pose {
+        let layout = self
+            .graph_context
+            .resources
+            .skeleton_assets
+            .get(skeleton)
+            .map(|skeleton| skeleton.pose_layout().clone())
+            .unwrap_or_default();
+        let mut pose = self
+            .graph_context
+            .node_caches_mut()
+            .pose_pool_mut()
+            .take(&layout);
+        pose.skeleton = skeleton.clone();
+        pose
+    }
+
+    /// Returns a copy of the given pose, reusing the buffers of a pose from the pool
+    pub fn clone_pose(&mut self, source: &Pose) -> Pose {
+        self.graph_context
+            .node_caches_mut()
+            .pose_pool_mut()
+            .clone_pose(source)
+    }
+
+    /// Hands a pose that is no longer needed back to the pool, so that its buffers can be reused
+    /// by [`Self::new_pose`] or when copying input poses.
+    pub fn recycle_pose(&mut self, pose: Pose) {
+        self.graph_context
+            .node_caches_mut()
+            .pose_pool_mut()
+            .recycle(pose);
     }
 
     /// Request the duration of an input pose pin.
     pub fn duration_back(&self, pin_id: impl Into<PinId>) -> Result<DurationData, GraphError> {
-        let target_pin = TargetPin::NodeTime(self.node_id, pin_id.into());
         self.graph
-            .get_duration(target_pin, self.graph_context.clone())
+            .get_node_duration(self.node_index, &pin_id.into(), self.graph_context.clone())
     }
 
     /// Sets the duration of the current node with current settings.
@@ -201,7 +278,7 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context_mut()
             .node_caches
-            .set_duration(self.node_id, key, duration);
+            .set_duration_at(self.node_index, key, duration);
     }
 
     /// Sets the duration of the current node with current settings.
@@ -210,7 +287,7 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context_mut()
             .node_caches
-            .set_input_time_update(self.node_id, key, pin_id.into(), time_update);
+            .set_input_time_update_at(self.node_index, key, &pin_id.into(), time_update);
     }
 
     /// Sets the time state of the current node.
@@ -219,14 +296,26 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context_mut()
             .node_states
-            .set_time(self.node_id, key, time);
+            .set_time_at(self.node_index, key, time);
     }
 
     /// Request the cached time update query from the current frame
     pub fn time_update_fwd(&self) -> Result<TimeUpdate, GraphError> {
-        let source_pin = SourcePin::NodeTime(self.node_id);
         self.graph
-            .get_time_update(source_pin, self.graph_context.clone())
+            .get_node_time_update(self.node_index, self.graph_context.clone())
+    }
+
+    /// Forwards the time update of the current node to the given time input, and returns the time
+    /// that passed this frame. Jumps in time count as no time passing.
+    ///
+    /// Useful for nodes that simulate or smooth their output over time.
+    pub fn time_delta_fwd(&mut self, pin_id: impl Into<PinId>) -> Result<f32, GraphError> {
+        let input = self.time_update_fwd()?;
+        self.set_time_update_back(pin_id, input.clone());
+        Ok(match input {
+            TimeUpdate::Delta(dt) => dt.abs(),
+            _ => 0.,
+        })
     }
 
     /// Request the cached timestamp of the output animation in the last frame
@@ -234,7 +323,7 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context()
             .node_states
-            .get_last_time(self.node_id)
+            .get_last_time_at(self.node_index)
     }
 
     /// Request the cached timestamp of the output animation in the last frame
@@ -243,12 +332,14 @@ impl<'a> NodeContext<'a> {
         self.graph_context
             .context_mut()
             .node_states
-            .get_time(self.node_id, key)
+            .get_time_at(self.node_index, key)
     }
 
     pub fn state<T: GraphStateType>(&self) -> Result<&T, GraphError> {
         let key = self.graph_context.state_key;
-        self.graph_context.node_states().get::<T>(self.node_id, key)
+        self.graph_context
+            .node_states()
+            .get_at::<T>(self.node_index, key)
     }
 
     pub fn state_mut<T: GraphStateType + Default>(&mut self) -> Result<&mut T, GraphError> {
@@ -262,15 +353,15 @@ impl<'a> NodeContext<'a> {
         let key = self.graph_context.state_key;
         self.graph_context
             .node_states_mut()
-            .get_mut_or_insert_with(self.node_id, key, default)
+            .get_mut_or_insert_with_at(self.node_index, key, default)
     }
 
     pub fn with_temp_state_key(mut self) -> Self {
-        self.graph_context = self.graph_context.with_temp_state_key();
+        self.graph_context = self.graph_context.with_temp_state_key(self.node_id);
         let new_key = self.graph_context.state_key;
         self.graph_context
             .node_caches_mut()
-            .mark_update_started(self.node_id, new_key);
+            .mark_update_started_at(self.node_index, new_key);
         self
     }
 
@@ -289,6 +380,7 @@ impl<'a> NodeContext<'a> {
             node_id: self.node_id.to_owned(),
             state_id: fsm_state,
         };
+        let seed = SubContextKey::from(&subctx_id).seed(self.graph_context.seed);
 
         GraphContext {
             context_id: self
@@ -296,6 +388,7 @@ impl<'a> NodeContext<'a> {
                 .context_arena
                 .get_mut()
                 .get_sub_context_or_insert_default(subctx_id, subgraph_id),
+            seed,
             ..self.graph_context.clone()
         }
     }
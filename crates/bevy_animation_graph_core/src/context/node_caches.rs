@@ -1,138 +1,295 @@
-use bevy::{
-    platform::collections::{HashMap, HashSet},
-    reflect::Reflect,
-};
+use std::sync::Arc;
+
+use bevy::{platform::collections::HashMap, reflect::Reflect};
 
 use crate::{
-    animation_graph::{NodeId, PinId, SourcePin, TargetPin, TimeUpdate},
+    animation_graph::{
+        NodeId, PinId, SourcePin, TargetPin, TimeUpdate,
+        plan::{ExecutionPlan, NodeIndex, PlannedNode},
+    },
     context::node_states::StateKey,
     duration_data::DurationData,
     edge_data::DataValue,
     errors::GraphError,
+    pose::PosePool,
 };
 
+/// Values produced by a node in the current frame, for a single [`StateKey`]
 #[derive(Reflect, Default, Debug)]
 pub struct NodeCache {
-    pub output_data: HashMap<(StateKey, PinId), DataValue>,
+    /// Indexed by the output slot in the [`ExecutionPlan`]
+    pub output_data: Vec<Option<DataValue>>,
+    /// Output data on pins that are not connected to anything. It is only kept around for
+    /// debugging.
+    pub unconnected_output_data: HashMap<PinId, DataValue>,
     /// Time update coming from the "output time" pin. Perhaps should be called "input time
     /// update".
-    pub output_time_update: HashMap<StateKey, TimeUpdate>,
-    /// Time updates sent back to nodes via "input time" pins
-    pub input_time_updates: HashMap<(StateKey, PinId), TimeUpdate>,
-    pub duration: HashMap<StateKey, DurationData>,
+    pub output_time_update: Option<TimeUpdate>,
+    /// Time updates sent back to nodes via "input time" pins, indexed by the time input slot in
+    /// the [`ExecutionPlan`]
+    pub input_time_updates: Vec<Option<TimeUpdate>>,
+    pub duration: Option<DurationData>,
     /// Whether the node update is started
-    pub update_started: HashSet<StateKey>,
+    pub update_started: bool,
     /// Whether the node update is completed
-    pub updated: HashSet<StateKey>,
+    pub updated: bool,
 }
 
+impl NodeCache {
+    fn new(node: &PlannedNode) -> Self {
+        Self {
+            output_data: vec![None; node.data_outputs.len()],
+            input_time_updates: vec![None; node.time_inputs.len()],
+            ..Default::default()
+        }
+    }
+
+    /// Clears all values, keeping the slots allocated. Output poses are returned to the pool.
+    fn clear(&mut self, pose_pool: &mut PosePool) {
+        for value in &mut self.output_data {
+            if let Some(DataValue::Pose(pose)) = value.take() {
+                pose_pool.recycle(pose);
+            }
+        }
+        self.unconnected_output_data.clear();
+        self.output_time_update = None;
+        self.input_time_updates.fill(None);
+        self.duration = None;
+        self.update_started = false;
+        self.updated = false;
+    }
+}
+
+/// Per-frame node caches of a graph, laid out according to its [`ExecutionPlan`].
+///
+/// The caches for [`StateKey::Default`] are preallocated, caches for temporary state keys are
+/// created on demand.
 #[derive(Reflect, Default, Debug)]
 pub struct NodeCaches {
-    caches: HashMap<NodeId, NodeCache>,
+    caches: Vec<NodeCache>,
+    temporary_caches: HashMap<(usize, StateKey), NodeCache>,
+    #[reflect(ignore)]
+    plan: Arc<ExecutionPlan>,
+    /// Buffers of the poses output in previous frames
+    #[reflect(ignore)]
+    pose_pool: PosePool,
 }
 
 impl NodeCaches {
     pub fn next_frame(&mut self) {
-        self.caches.clear();
+        for cache in &mut self.caches {
+            cache.clear(&mut self.pose_pool);
+        }
+        for (_, mut cache) in self.temporary_caches.drain() {
+            cache.clear(&mut self.pose_pool);
+        }
     }
 
-    pub fn get_duration(&self, node_id: NodeId, key: StateKey) -> Result<DurationData, GraphError> {
-        let error = || GraphError::DurationMissing(SourcePin::NodeTime(node_id));
+    pub fn pose_pool_mut(&mut self) -> &mut PosePool {
+        &mut self.pose_pool
+    }
 
-        self.caches
-            .get(&node_id)
-            .ok_or_else(&error)
-            .and_then(|c| c.duration.get(&key).ok_or_else(&error))
-            .cloned()
+    /// Lays out the caches for a new plan. Cached values are discarded.
+    pub(crate) fn set_plan(&mut self, plan: Arc<ExecutionPlan>) {
+        self.caches = plan.nodes().iter().map(NodeCache::new).collect();
+        self.temporary_caches.clear();
+        self.plan = plan;
     }
 
-    pub fn set_duration(&mut self, node_id: NodeId, key: StateKey, duration: DurationData) {
-        self.cache_mut(node_id).duration.insert(key, duration);
+    pub(crate) fn plan_id(&self) -> u64 {
+        self.plan.id()
     }
 
-    pub fn get_output_data(&self, node_id: NodeId, key: StateKey, pin: PinId) -> Option<DataValue> {
-        self.caches
-            .get(&node_id)
-            .and_then(|c| c.output_data.get(&(key, pin.clone())))
-            .cloned()
+    fn cache(&self, node: NodeIndex, key: StateKey) -> Option<&NodeCache> {
+        match key {
+            StateKey::Default => self.caches.get(node.0),
+            StateKey::Temporary(_) => self.temporary_caches.get(&(node.0, key)),
+        }
     }
 
-    pub fn set_output_data(&mut self, node_id: NodeId, key: StateKey, pin: PinId, data: DataValue) {
-        self.cache_mut(node_id).output_data.insert((key, pin), data);
+    fn cache_mut(&mut self, node: NodeIndex, key: StateKey) -> &mut NodeCache {
+        match key {
+            StateKey::Default => &mut self.caches[node.0],
+            StateKey::Temporary(_) => self
+                .temporary_caches
+                .entry((node.0, key))
+                .or_insert_with(|| NodeCache::new(self.plan.node(node))),
+        }
     }
 
-    pub fn get_output_time_update(
+    // --- Index-based access, used during graph evaluation
+    // ----------------------------------------------------------------------------------------
+    pub fn get_duration_at(&self, node: NodeIndex, key: StateKey) -> Option<DurationData> {
+        self.cache(node, key).and_then(|c| c.duration)
+    }
+
+    pub fn set_duration_at(&mut self, node: NodeIndex, key: StateKey, duration: DurationData) {
+        self.cache_mut(node, key).duration = Some(duration);
+    }
+
+    pub fn get_output_data_at(
         &self,
-        node_id: NodeId,
+        node: NodeIndex,
         key: StateKey,
-    ) -> Result<TimeUpdate, GraphError> {
-        let error = || GraphError::TimeUpdateMissingFwd(SourcePin::NodeTime(node_id));
+        slot: usize,
+    ) -> Option<&DataValue> {
+        self.cache(node, key)
+            .and_then(|c| c.output_data.get(slot))
+            .and_then(|value| value.as_ref())
+    }
 
-        self.caches
-            .get(&node_id)
-            .ok_or_else(&error)
-            .and_then(|c| c.output_time_update.get(&key).ok_or_else(&error))
-            .cloned()
+    /// Copy of the output data in the given slot. Poses are copied into buffers from the pose
+    /// pool.
+    pub fn clone_output_data_at(
+        &mut self,
+        node: NodeIndex,
+        key: StateKey,
+        slot: usize,
+    ) -> Option<DataValue> {
+        let cache = match key {
+            StateKey::Default => self.caches.get(node.0),
+            StateKey::Temporary(_) => self.temporary_caches.get(&(node.0, key)),
+        }?;
+
+        Some(match cache.output_data.get(slot)?.as_ref()? {
+            DataValue::Pose(pose) => DataValue::Pose(self.pose_pool.clone_pose(pose)),
+            value => value.clone(),
+        })
     }
 
-    pub fn set_output_time_update(&mut self, node_id: NodeId, key: StateKey, update: TimeUpdate) {
-        self.cache_mut(node_id)
-            .output_time_update
-            .insert(key, update);
+    pub fn set_output_data_at(
+        &mut self,
+        node: NodeIndex,
+        key: StateKey,
+        pin: PinId,
+        data: DataValue,
+    ) {
+        let slot = self.plan.node(node).data_output_slot(&pin);
+        let cache = self.cache_mut(node, key);
+        match slot {
+            Some(slot) => cache.output_data[slot] = Some(data),
+            None => {
+                cache.unconnected_output_data.insert(pin, data);
+            }
+        }
     }
 
-    pub fn get_input_time_update(
+    pub fn get_output_time_update_at(&self, node: NodeIndex, key: StateKey) -> Option<TimeUpdate> {
+        self.cache(node, key)
+            .and_then(|c| c.output_time_update.clone())
+    }
+
+    pub fn set_output_time_update_at(
+        &mut self,
+        node: NodeIndex,
+        key: StateKey,
+        update: TimeUpdate,
+    ) {
+        self.cache_mut(node, key).output_time_update = Some(update);
+    }
+
+    pub fn get_input_time_update_at(
         &self,
-        node_id: NodeId,
+        node: NodeIndex,
         key: StateKey,
-        pin: PinId,
-    ) -> Result<TimeUpdate, GraphError> {
-        let error = || GraphError::TimeUpdateMissingBack(TargetPin::NodeTime(node_id, pin.clone()));
-
-        self.caches
-            .get(&node_id)
-            .ok_or_else(&error)
-            .and_then(|c| {
-                c.input_time_updates
-                    .get(&(key, pin.clone()))
-                    .ok_or_else(&error)
-            })
-            .cloned()
+        slot: usize,
+    ) -> Option<TimeUpdate> {
+        self.cache(node, key)
+            .and_then(|c| c.input_time_updates.get(slot))
+            .and_then(|update| update.clone())
     }
 
-    pub fn set_input_time_update(
+    /// Time updates sent back through pins that are not connected are dropped, as they cannot be
+    /// requested.
+    pub fn set_input_time_update_at(
         &mut self,
-        node_id: NodeId,
+        node: NodeIndex,
         key: StateKey,
-        pin: PinId,
+        pin: &str,
         update: TimeUpdate,
     ) {
-        self.cache_mut(node_id)
-            .input_time_updates
-            .insert((key, pin), update);
+        if let Some(slot) = self.plan.node(node).time_input_slot(pin) {
+            self.cache_mut(node, key).input_time_updates[slot] = Some(update);
+        }
     }
 
-    pub fn is_updated(&self, node_id: NodeId, key: StateKey) -> bool {
-        self.caches
-            .get(&node_id)
-            .is_some_and(|c| c.updated.contains(&key))
+    pub fn is_updated_at(&self, node: NodeIndex, key: StateKey) -> bool {
+        self.cache(node, key).is_some_and(|c| c.updated)
     }
 
-    pub fn mark_updated(&mut self, node_id: NodeId, key: StateKey) {
-        self.cache_mut(node_id).updated.insert(key);
+    pub fn mark_updated_at(&mut self, node: NodeIndex, key: StateKey) {
+        self.cache_mut(node, key).updated = true;
     }
 
-    pub fn is_update_started(&self, node_id: NodeId, key: StateKey) -> bool {
-        self.caches
-            .get(&node_id)
-            .is_some_and(|c| c.update_started.contains(&key))
+    pub fn is_update_started_at(&self, node: NodeIndex, key: StateKey) -> bool {
+        self.cache(node, key).is_some_and(|c| c.update_started)
+    }
+
+    pub fn mark_update_started_at(&mut self, node: NodeIndex, key: StateKey) {
+        self.cache_mut(node, key).update_started = true;
+    }
+    // ----------------------------------------------------------------------------------------
+
+    // --- Id-based access, e.g. for debugging in the editor
+    // ----------------------------------------------------------------------------------------
+    pub fn get_duration(&self, node_id: NodeId, key: StateKey) -> Result<DurationData, GraphError> {
+        self.plan
+            .node_index(&node_id)
+            .and_then(|node| self.get_duration_at(node, key))
+            .ok_or(GraphError::DurationMissing(SourcePin::NodeTime(node_id)))
+    }
+
+    pub fn get_output_data(&self, node_id: NodeId, key: StateKey, pin: PinId) -> Option<DataValue> {
+        let node = self.plan.node_index(&node_id)?;
+        match self.plan.node(node).data_output_slot(&pin) {
+            Some(slot) => self.get_output_data_at(node, key, slot).cloned(),
+            None => self
+                .cache(node, key)
+                .and_then(|c| c.unconnected_output_data.get(&pin))
+                .cloned(),
+        }
+    }
+
+    pub fn get_output_time_update(
+        &self,
+        node_id: NodeId,
+        key: StateKey,
+    ) -> Result<TimeUpdate, GraphError> {
+        self.plan
+            .node_index(&node_id)
+            .and_then(|node| self.get_output_time_update_at(node, key))
+            .ok_or(GraphError::TimeUpdateMissingFwd(SourcePin::NodeTime(
+                node_id,
+            )))
     }
 
-    pub fn mark_update_started(&mut self, node_id: NodeId, key: StateKey) {
-        self.cache_mut(node_id).update_started.insert(key);
+    pub fn get_input_time_update(
+        &self,
+        node_id: NodeId,
+        key: StateKey,
+        pin: PinId,
+    ) -> Result<TimeUpdate, GraphError> {
+        self.plan
+            .node_index(&node_id)
+            .and_then(|node| {
+                let slot = self.plan.node(node).time_input_slot(&pin)?;
+                self.get_input_time_update_at(node, key, slot)
+            })
+            .ok_or(GraphError::TimeUpdateMissingBack(TargetPin::NodeTime(
+                node_id, pin,
+            )))
     }
 
-    fn cache_mut(&mut self, node_id: NodeId) -> &mut NodeCache {
-        self.caches.entry(node_id).or_default()
+    pub fn is_updated(&self, node_id: NodeId, key: StateKey) -> bool {
+        self.plan
+            .node_index(&node_id)
+            .is_some_and(|node| self.is_updated_at(node, key))
+    }
+
+    pub fn is_update_started(&self, node_id: NodeId, key: StateKey) -> bool {
+        self.plan
+            .node_index(&node_id)
+            .is_some_and(|node| self.is_update_started_at(node, key))
     }
+    // ----------------------------------------------------------------------------------------
 }
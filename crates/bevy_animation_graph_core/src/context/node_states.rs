@@ -1,13 +1,25 @@
-use std::any::Any;
+use std::{any::Any, sync::Arc};
 
 use bevy::{
     platform::collections::HashMap,
-    reflect::{Reflect, Reflectable},
+    reflect::{Reflect, Reflectable, reflect_trait},
 };
 use uuid::Uuid;
 
-use crate::{animation_graph::NodeId, context::node_state_box::NodeStateBox, errors::GraphError};
+use crate::{
+    animation_graph::{
+        NodeId,
+        plan::{ExecutionPlan, NodeIndex},
+    },
+    context::node_state_box::NodeStateBox,
+    deterministic::mix_seed,
+    errors::GraphError,
+};
 
+/// Node states stored in [`NodeStates`]. Types must be registered with
+/// `#[reflect(GraphStateType)]` to be restored from a
+/// [`PlayerSnapshot`](crate::snapshot::PlayerSnapshot).
+#[reflect_trait]
 pub trait GraphStateType: Reflect + Any + std::fmt::Debug + Send + Sync + 'static {
     fn clone_box(&self) -> Box<dyn GraphStateType>;
 }
@@ -28,43 +40,70 @@ pub enum StateKey {
     Temporary(Uuid),
 }
 
-#[derive(Default, Debug, Reflect)]
+impl StateKey {
+    /// Key for the temporary states of a query made by the given node while evaluating with this
+    /// key. It is derived rather than random, so that evaluation is the same on every run.
+    pub fn temporary(self, node_id: NodeId) -> Self {
+        let parent = match self {
+            StateKey::Default => Uuid::nil(),
+            StateKey::Temporary(uuid) => uuid,
+        };
+        let (parent_hi, parent_lo) = parent.as_u64_pair();
+        let (node_hi, node_lo) = node_id.uuid().as_u64_pair();
+        let half = |salt| mix_seed([salt, parent_hi, parent_lo, node_hi, node_lo]);
+        StateKey::Temporary(Uuid::from_u64_pair(half(0), half(1)))
+    }
+}
+
+#[derive(Default, Debug, Clone, Reflect)]
 pub struct NodeState {
     last_state: Option<NodeStateBox>,
-    upcoming_state: HashMap<StateKey, NodeStateBox>,
+    upcoming_state: Option<NodeStateBox>,
+    upcoming_temporary_states: HashMap<StateKey, NodeStateBox>,
 
     /// Most nodes need to keep track of time. We handle this separately
     /// to avoid overhead of Box if possible
     last_time: f32,
-    upcoming_time: HashMap<StateKey, f32>,
+    upcoming_time: Option<f32>,
+    upcoming_temporary_times: HashMap<StateKey, f32>,
 }
 
 impl NodeState {
     pub fn next_frame(&mut self) {
-        if let Some(next_state) = self.upcoming_state.get(&StateKey::Default) {
-            self.last_state = Some(next_state.clone());
+        if let Some(next_state) = self.upcoming_state.take() {
+            self.last_state = Some(next_state);
         }
 
-        if let Some(next_time) = self.upcoming_time.get(&StateKey::Default) {
-            self.last_time = *next_time;
+        if let Some(next_time) = self.upcoming_time.take() {
+            self.last_time = next_time;
         }
 
-        self.upcoming_state.clear();
-        self.upcoming_time.clear();
+        self.upcoming_temporary_states.clear();
+        self.upcoming_temporary_times.clear();
     }
 
     pub fn get_all_upcoming_states<T: GraphStateType>(
         &self,
     ) -> Result<impl Iterator<Item = &T>, GraphError> {
-        Ok(self.upcoming_state.values().filter_map(|s| {
-            let v: &dyn Any = s.value.as_ref();
-            v.downcast_ref::<T>()
-        }))
+        Ok(self
+            .upcoming_state
+            .iter()
+            .chain(self.upcoming_temporary_states.values())
+            .filter_map(|s| {
+                let v: &dyn Any = s.value.as_ref();
+                v.downcast_ref::<T>()
+            }))
+    }
+
+    fn upcoming_state(&self, key: StateKey) -> Option<&NodeStateBox> {
+        match key {
+            StateKey::Default => self.upcoming_state.as_ref(),
+            StateKey::Temporary(_) => self.upcoming_temporary_states.get(&key),
+        }
     }
 
     pub fn get_state<T: GraphStateType>(&self, key: StateKey) -> Result<&T, GraphError> {
-        self.upcoming_state
-            .get(&key)
+        self.upcoming_state(key)
             .or(self.last_state.as_ref())
             .ok_or(GraphError::MissingStateValue)
             .and_then(|v| {
@@ -81,20 +120,27 @@ impl NodeState {
         let NodeState {
             last_state,
             upcoming_state,
+            upcoming_temporary_states,
             ..
         } = self;
-        let dyn_mut: &mut dyn Any = upcoming_state
-            .entry(key)
-            .or_insert_with(|| {
-                last_state
-                    .as_ref()
-                    .map(|s| s.clone())
-                    .unwrap_or_else(|| NodeStateBox {
-                        value: Box::new(default()),
-                    })
-            })
-            .value
-            .as_mut();
+        // The type of the last state differs when the node was replaced by one of another type
+        // while keeping its id, e.g. when its graph was reloaded. The old state is discarded.
+        let initial_state = || {
+            last_state
+                .as_ref()
+                .filter(|s| (s.value.as_ref() as &dyn Any).is::<T>())
+                .cloned()
+                .unwrap_or_else(|| NodeStateBox {
+                    value: Box::new(default()),
+                })
+        };
+        let state = match key {
+            StateKey::Default => upcoming_state.get_or_insert_with(initial_state),
+            StateKey::Temporary(_) => upcoming_temporary_states
+                .entry(key)
+                .or_insert_with(initial_state),
+        };
+        let dyn_mut: &mut dyn Any = state.value.as_mut();
 
         dyn_mut
             .downcast_mut::<T>()
@@ -102,81 +148,215 @@ impl NodeState {
     }
 
     pub fn get_time(&self, key: StateKey) -> f32 {
-        self.upcoming_time
-            .get(&key)
-            .copied()
-            .unwrap_or(self.last_time)
+        match key {
+            StateKey::Default => self.upcoming_time,
+            StateKey::Temporary(_) => self.upcoming_temporary_times.get(&key).copied(),
+        }
+        .unwrap_or(self.last_time)
     }
 
     pub fn set_time(&mut self, key: StateKey, time: f32) {
-        self.upcoming_time.insert(key, time);
+        match key {
+            StateKey::Default => self.upcoming_time = Some(time),
+            StateKey::Temporary(_) => {
+                self.upcoming_temporary_times.insert(key, time);
+            }
+        }
     }
 
     pub fn get_last_time(&self) -> f32 {
         self.last_time
     }
+
+    /// A state that starts the next frame with the given time and state
+    pub(crate) fn restored(time: f32, state: Option<Box<dyn GraphStateType>>) -> Self {
+        Self {
+            last_state: state.map(|value| NodeStateBox { value }),
+            last_time: time,
+            ..Default::default()
+        }
+    }
+
+    /// Time the next frame starts from
+    pub(crate) fn current_time(&self) -> f32 {
+        self.upcoming_time.unwrap_or(self.last_time)
+    }
+
+    /// State the next frame starts from
+    pub(crate) fn current_state(&self) -> Option<&dyn GraphStateType> {
+        self.upcoming_state
+            .as_ref()
+            .or(self.last_state.as_ref())
+            .map(|state| state.value.as_ref())
+    }
 }
 
-#[derive(Debug, Reflect, Default)]
+/// Persistent node states of a graph, laid out according to its [`ExecutionPlan`]
+#[derive(Debug, Reflect, Default, Clone)]
 pub struct NodeStates {
-    states: HashMap<NodeId, NodeState>,
+    states: Vec<NodeState>,
+    #[reflect(ignore)]
+    plan: Arc<ExecutionPlan>,
+    /// Restored states of nodes that are not in the plan yet, see [`NodeStates::restore`]
+    #[reflect(ignore)]
+    restored: HashMap<NodeId, NodeState>,
 }
 
 impl NodeStates {
     pub fn next_frame(&mut self) {
-        for node_state in self.states.values_mut() {
+        for node_state in &mut self.states {
             node_state.next_frame();
         }
     }
 
-    pub fn get_all_upcoming_states<T: GraphStateType>(
-        &self,
-        node_id: NodeId,
-    ) -> Result<impl Iterator<Item = &T>, GraphError> {
-        self.states
-            .get(&node_id)
-            .ok_or(GraphError::MissingStateValue)
-            .and_then(|n| n.get_all_upcoming_states())
+    /// Lays out the states for a new plan, keeping the state of nodes that are still present.
+    pub(crate) fn set_plan(&mut self, plan: Arc<ExecutionPlan>) {
+        let mut old_states: HashMap<NodeId, NodeState> = self
+            .plan
+            .nodes()
+            .iter()
+            .map(|node| node.id)
+            .zip(self.states.drain(..))
+            .collect();
+        old_states.extend(self.restored.drain());
+
+        self.states = plan
+            .nodes()
+            .iter()
+            .map(|node| old_states.remove(&node.id).unwrap_or_default())
+            .collect();
+        self.plan = plan;
     }
 
-    pub fn get<T: GraphStateType>(&self, node_id: NodeId, key: StateKey) -> Result<&T, GraphError> {
+    pub(crate) fn plan_id(&self) -> u64 {
+        self.plan.id()
+    }
+
+    /// Replaces all node states. States of nodes that are not in the current plan are applied
+    /// once a plan that includes them is set, e.g. when the graph is first evaluated.
+    pub(crate) fn restore(&mut self, mut states: HashMap<NodeId, NodeState>) {
+        for (node, state) in self.plan.nodes().iter().zip(&mut self.states) {
+            *state = states.remove(&node.id).unwrap_or_default();
+        }
+        self.restored = states;
+    }
+
+    /// All node states by node id, including the ones waiting for a plan
+    pub(crate) fn iter_by_id(&self) -> impl Iterator<Item = (NodeId, &NodeState)> {
+        self.plan
+            .nodes()
+            .iter()
+            .map(|node| node.id)
+            .zip(&self.states)
+            .chain(self.restored.iter().map(|(id, state)| (*id, state)))
+    }
+
+    // --- Index-based access, used during graph evaluation
+    // ----------------------------------------------------------------------------------------
+    pub fn get_at<T: GraphStateType>(
+        &self,
+        node: NodeIndex,
+        key: StateKey,
+    ) -> Result<&T, GraphError> {
         self.states
-            .get(&node_id)
+            .get(node.0)
             .ok_or(GraphError::MissingStateValue)
             .and_then(|n| n.get_state(key))
     }
 
-    pub fn get_mut_or_insert_with<T: GraphStateType>(
+    pub fn get_mut_or_insert_with_at<T: GraphStateType>(
         &mut self,
-        node_id: NodeId,
+        node: NodeIndex,
         key: StateKey,
         default: impl FnOnce() -> T,
     ) -> Result<&mut T, GraphError> {
         self.states
-            .entry(node_id)
-            .or_default()
-            .get_mut_or_insert_with(key, default)
+            .get_mut(node.0)
+            .ok_or(GraphError::MissingStateValue)
+            .and_then(|n| n.get_mut_or_insert_with(key, default))
     }
 
-    pub fn get_time(&self, node_id: NodeId, key: StateKey) -> f32 {
+    pub fn get_time_at(&self, node: NodeIndex, key: StateKey) -> f32 {
         self.states
-            .get(&node_id)
+            .get(node.0)
             .map(|n| n.get_time(key))
             .unwrap_or(0.)
     }
 
-    pub fn set_time(&mut self, node_id: NodeId, key: StateKey, time: f32) {
-        self.states.entry(node_id).or_default().set_time(key, time);
+    pub fn set_time_at(&mut self, node: NodeIndex, key: StateKey, time: f32) {
+        if let Some(node_state) = self.states.get_mut(node.0) {
+            node_state.set_time(key, time);
+        }
     }
 
-    pub fn get_last_time(&self, node_id: NodeId) -> f32 {
+    pub fn get_last_time_at(&self, node: NodeIndex) -> f32 {
         self.states
-            .get(&node_id)
+            .get(node.0)
             .map(|n| n.get_last_time())
             .unwrap_or(0.)
     }
+    // ----------------------------------------------------------------------------------------
+
+    // --- Id-based access, e.g. for debugging in the editor
+    // ----------------------------------------------------------------------------------------
+    fn state(&self, node_id: NodeId) -> Option<&NodeState> {
+        self.plan
+            .node_index(&node_id)
+            .and_then(|node| self.states.get(node.0))
+    }
+
+    pub fn get_all_upcoming_states<T: GraphStateType>(
+        &self,
+        node_id: NodeId,
+    ) -> Result<impl Iterator<Item = &T>, GraphError> {
+        self.state(node_id)
+            .ok_or(GraphError::MissingStateValue)
+            .and_then(|n| n.get_all_upcoming_states())
+    }
+
+    pub fn get<T: GraphStateType>(&self, node_id: NodeId, key: StateKey) -> Result<&T, GraphError> {
+        self.state(node_id)
+            .ok_or(GraphError::MissingStateValue)
+            .and_then(|n| n.get_state(key))
+    }
+
+    pub fn get_time(&self, node_id: NodeId, key: StateKey) -> f32 {
+        self.state(node_id).map(|n| n.get_time(key)).unwrap_or(0.)
+    }
+
+    pub fn get_last_time(&self, node_id: NodeId) -> f32 {
+        self.state(node_id).map(|n| n.get_last_time()).unwrap_or(0.)
+    }
+    // ----------------------------------------------------------------------------------------
 
     pub fn clear(&mut self) {
-        self.states.clear();
+        for node_state in &mut self.states {
+            *node_state = NodeState::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_state_of_another_type_is_discarded() {
+        let mut same_type = NodeState::restored(0., Some(Box::new(1_u32)));
+        assert_eq!(
+            *same_type
+                .get_mut_or_insert_with(StateKey::Default, || 5_u32)
+                .unwrap(),
+            1
+        );
+
+        // E.g. the node was replaced by one of another type with the same id
+        let mut other_type = NodeState::restored(0., Some(Box::new(1_u32)));
+        assert_eq!(
+            *other_type
+                .get_mut_or_insert_with(StateKey::Default, || 5_f32)
+                .unwrap(),
+            5.
+        );
     }
 }
@@ -5,7 +5,10 @@ use bevy::{
 };
 
 use crate::{
-    animation_clip::GraphClip, animation_graph::AnimationGraph, skeleton::Skeleton,
+    animation_clip::GraphClip,
+    animation_graph::AnimationGraph,
+    ik::foot::{CustomGroundQuery, GroundQuery},
+    skeleton::Skeleton,
     state_machine::high_level::StateMachine,
 };
 
@@ -24,4 +27,22 @@ pub struct SystemResources<'w, 's> {
     pub parent_query: Query<'w, 's, &'static ChildOf>,
     #[cfg(feature = "physics_avian")]
     pub rigidbody_query: Query<'w, 's, &'static avian3d::prelude::RigidBody>,
+    pub custom_ground_query: Option<Res<'w, CustomGroundQuery>>,
+    #[cfg(feature = "physics_avian")]
+    pub spatial_query_pipeline: Option<Res<'w, avian3d::prelude::SpatialQueryPipeline>>,
+}
+
+impl SystemResources<'_, '_> {
+    /// Ground that foot IK is placed on: the [`CustomGroundQuery`] if there is one, and the
+    /// physics engine otherwise
+    pub fn ground_query(&self) -> Option<&dyn GroundQuery> {
+        if let Some(custom) = &self.custom_ground_query {
+            return Some(custom.0.as_ref());
+        }
+        #[cfg(feature = "physics_avian")]
+        if let Some(pipeline) = &self.spatial_query_pipeline {
+            return Some(pipeline.as_ref());
+        }
+        None
+    }
 }
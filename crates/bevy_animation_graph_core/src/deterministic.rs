@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::prelude::*,
+    log::warn,
+    reflect::prelude::*,
+    time::{Fixed, Time},
+};
+
+/// Settings of the deterministic evaluation mode, enabled with
+/// [`AnimationGraphCorePlugin::with_deterministic_evaluation`](crate::plugin::AnimationGraphCorePlugin::with_deterministic_evaluation).
+///
+/// In this mode, two runs of the same build on the same platform that spawn the same players
+/// with the same inputs and seeds, and step them the same number of times, get bit-identical
+/// poses, events and node states, which is what lockstep and rollback networking need:
+/// - The animation systems must run in a fixed timestep schedule. Players advance by the fixed
+///   [`timestep`](Self::timestep) on every step, instead of by the delta of [`Time`]. A warning
+///   is logged if the schedule does not run once per step of [`Time<Fixed>`], or if the timestep
+///   of [`Time<Fixed>`] differs from the one of the settings.
+/// - [`AnimationVisibilityCulling`](crate::animated_scene::AnimationVisibilityCulling) and
+///   [`AnimationLod`](crate::lod::AnimationLod) are ignored, as they depend on the camera.
+///
+/// Results are not guaranteed to match across platforms or builds, e.g. between peers on
+/// different CPU architectures: floating point functions such as `sin` or `exp` may round
+/// differently, and the compiler may fuse or reorder float operations differently. Nodes that
+/// read the scene or the physics engine, such as IK targets, foot IK and ragdolls, are only as
+/// deterministic as the scene and the physics engine.
+///
+/// The following hold in every mode, and are only listed here as they matter for determinism:
+/// - Graph evaluation does not depend on the iteration order of hash maps.
+/// - Nodes that need random numbers seed them from [`NodeContext::random_seed`], which depends
+///   only on the seed of the player (see [`AnimationGraphPlayer::set_seed`]) and the position of
+///   the node in the graph.
+///
+/// [`NodeContext::random_seed`]: crate::context::new_context::NodeContext::random_seed
+/// [`AnimationGraphPlayer::set_seed`]: crate::animation_graph_player::AnimationGraphPlayer::set_seed
+#[derive(Resource, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Resource, Default)]
+pub struct DeterministicEvaluation {
+    /// Time in seconds by which players advance on every fixed step. It should match the
+    /// timestep of [`Time<Fixed>`], which is left as it is.
+    pub timestep: f32,
+}
+
+impl Default for DeterministicEvaluation {
+    fn default() -> Self {
+        Self { timestep: 1. / 64. }
+    }
+}
+
+impl DeterministicEvaluation {
+    pub fn new(timestep: f32) -> Self {
+        Self { timestep }
+    }
+}
+
+/// Time by which players advance in the current run of the animation systems
+pub(crate) fn frame_delta(time: &Time, deterministic: Option<&DeterministicEvaluation>) -> f32 {
+    deterministic.map_or_else(|| time.delta_secs(), |settings| settings.timestep)
+}
+
+/// Warns on the first run if the animation systems do not run in a fixed timestep schedule, or if
+/// the timestep of [`Time<Fixed>`] does not match the settings. In a fixed timestep schedule, [`Time`] advances
+/// by exactly the timestep of [`Time<Fixed>`].
+pub(crate) fn check_deterministic_schedule(
+    time: Res<Time>,
+    fixed: Option<Res<Time<Fixed>>>,
+    settings: Res<DeterministicEvaluation>,
+    mut checked: Local<bool>,
+) {
+    if *checked {
+        return;
+    }
+    *checked = true;
+
+    let timestep = fixed.map(|fixed| fixed.timestep());
+    if timestep != Some(time.delta()) {
+        warn!(
+            "The deterministic evaluation mode needs the animation systems to run in a fixed \
+             timestep schedule, such as FixedPostUpdate"
+        );
+    } else if timestep != Some(Duration::from_secs_f32(settings.timestep)) {
+        warn!(
+            "The timestep of Time<Fixed> ({:?}) differs from the timestep of the deterministic \
+             evaluation settings ({}s), so animations will not play at the right speed",
+            time.delta(),
+            settings.timestep
+        );
+    }
+}
+
+/// Run condition for systems that are disabled in the deterministic mode
+pub(crate) fn not_deterministic(deterministic: Option<Res<DeterministicEvaluation>>) -> bool {
+    deterministic.is_none()
+}
+
+/// Combines the given values into a well mixed seed. Unlike [`std::hash::Hash`], the result
+/// does not depend on the platform or on the version of the standard library.
+pub(crate) fn mix_seed(values: impl IntoIterator<Item = u64>) -> u64 {
+    values
+        .into_iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |seed, value| {
+            // SplitMix64 finalizer
+            let mut z = (seed ^ value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AssetId, Assets},
+        ecs::system::SystemState,
+        platform::collections::HashMap,
+    };
+
+    use super::*;
+    use crate::{
+        animation_clip::GraphClip,
+        animation_graph::{AnimationGraph, TimeUpdate},
+        animation_node::{AnimationNode, NodeLike},
+        context::{
+            deferred_gizmos::DeferredGizmos, graph_context_arena::GraphContextArena,
+            new_context::NodeContext, spec_context::SpecContext, system_resources::SystemResources,
+        },
+        edge_data::DataSpec,
+        errors::GraphError,
+        skeleton::Skeleton,
+        state_machine::high_level::StateMachine,
+    };
+
+    /// Outputs a new random number every frame
+    #[derive(Reflect, Clone, Debug)]
+    struct RandomNode;
+
+    #[derive(Reflect, Clone, Debug)]
+    struct RandomState(u64);
+
+    impl NodeLike for RandomNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let seed = ctx.random_seed();
+            let state = ctx.state_mut_or_else(|| RandomState(seed))?;
+            state.0 = mix_seed([state.0]);
+            let value = (state.0 >> 40) as f32 / (1 << 24) as f32;
+            ctx.set_data_fwd("out", value);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_output_data("out", DataSpec::F32);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Random".into()
+        }
+    }
+
+    /// Reads its input with a temporary state key, like the loop node does
+    #[derive(Reflect, Clone, Debug)]
+    struct TemporaryQueryNode;
+
+    impl NodeLike for TemporaryQueryNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let value = ctx.clone().with_temp_state_key().data_back("in")?;
+            ctx.set_data_fwd("out", value);
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_input_data("in", DataSpec::F32)
+                .add_output_data("out", DataSpec::F32);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Temporary query".into()
+        }
+    }
+
+    fn graph() -> AnimationGraph {
+        let mut graph = AnimationGraph::new();
+        let random = AnimationNode::new("random", RandomNode);
+        let temporary = AnimationNode::new("temporary", TemporaryQueryNode);
+        let (random_id, temporary_id) = (random.id, temporary.id);
+        graph.add_node(random);
+        graph.add_node(temporary);
+        graph.add_node_parameter_edge(random_id, "out", temporary_id, "in");
+        graph.add_output_data("direct".into(), DataSpec::F32);
+        graph.add_output_data("temporary".into(), DataSpec::F32);
+        graph.add_output_data_edge(random_id, "out", "direct");
+        graph.add_output_data_edge(temporary_id, "out", "temporary");
+        graph
+    }
+
+    /// Outputs of the first frames of a fresh evaluation of the graph
+    fn run(graph: &AnimationGraph, seed: u64) -> Vec<(f32, f32)> {
+        let mut world = World::new();
+        world.init_resource::<Assets<GraphClip>>();
+        world.init_resource::<Assets<AnimationGraph>>();
+        world.init_resource::<Assets<StateMachine>>();
+        world.init_resource::<Assets<Skeleton>>();
+        let mut system_state = SystemState::<SystemResources>::new(&mut world);
+        let resources = system_state.get_mut(&mut world);
+
+        let mut arena = GraphContextArena::new(AssetId::default());
+        arena.set_seed(seed);
+        (0..5)
+            .map(|_| {
+                let outputs = graph
+                    .query(
+                        TimeUpdate::Delta(0.1),
+                        &mut arena,
+                        &resources,
+                        Entity::PLACEHOLDER,
+                        &HashMap::default(),
+                        &mut DeferredGizmos::default(),
+                        &HashMap::default(),
+                    )
+                    .unwrap();
+                let output = |pin: &str| outputs[pin].as_f32().unwrap();
+                (output("direct"), output("temporary"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mix_seed_depends_on_order_and_values() {
+        assert_eq!(mix_seed([1, 2]), mix_seed([1, 2]));
+        assert_ne!(mix_seed([1, 2]), mix_seed([2, 1]));
+        assert_ne!(mix_seed([1, 2]), mix_seed([1, 3]));
+        assert_ne!(mix_seed([0]), mix_seed([0, 0]));
+    }
+
+    #[test]
+    fn test_two_runs_with_the_same_seed_are_identical() {
+        let graph = graph();
+        let first = run(&graph, 7);
+        let second = run(&graph, 7);
+        assert_eq!(
+            first
+                .iter()
+                .map(|(a, b)| (a.to_bits(), b.to_bits()))
+                .collect::<Vec<_>>(),
+            second
+                .iter()
+                .map(|(a, b)| (a.to_bits(), b.to_bits()))
+                .collect::<Vec<_>>(),
+        );
+
+        // The random state advances every frame. The temporary query starts from the state of
+        // the last frame too, so it gets the same number.
+        assert_ne!(first[0].0, first[1].0);
+        assert_eq!(first[1].0, first[1].1);
+
+        assert_ne!(run(&graph, 8), first);
+    }
+}
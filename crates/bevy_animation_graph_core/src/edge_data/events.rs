@@ -1,7 +1,17 @@
-use bevy::reflect::{Reflect, std_traits::ReflectDefault};
-use serde::{Deserialize, Serialize};
+use bevy::{
+    math::{Quat, Vec2, Vec3},
+    reflect::{
+        FromReflect, PartialReflect, Reflect, TypeRegistry,
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        std_traits::ReflectDefault,
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeSeed};
 
-use crate::state_machine::high_level::{StateId, TransitionId};
+use crate::{
+    animation_clip::EntityPath,
+    state_machine::high_level::{StateId, TransitionId},
+};
 
 /// Event data
 #[derive(Clone, Debug, Reflect, Serialize, Deserialize, PartialEq, Hash)]
@@ -33,6 +43,105 @@ impl Default for AnimationEvent {
     }
 }
 
+/// Typed data attached to an event, e.g. which foot a footstep event is for or how much damage a
+/// hit event deals.
+#[derive(Clone, Debug, Reflect, Serialize, Deserialize, PartialEq, Default)]
+#[reflect(Default)]
+pub enum EventPayload {
+    #[default]
+    None,
+    F32(f32),
+    Bool(bool),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Quat(Quat),
+    String(String),
+    EntityPath(EntityPath),
+    /// A value of any type registered in the type registry, stored in its RON representation.
+    /// Use [`EventPayload::reflected`] to create one and [`EventPayload::get`] to read it back.
+    Reflected {
+        type_path: String,
+        ron: String,
+    },
+}
+
+impl EventPayload {
+    /// Creates a payload holding an arbitrary reflected value. The value's type must be
+    /// registered in the given registry.
+    pub fn reflected(
+        value: &dyn PartialReflect,
+        registry: &TypeRegistry,
+    ) -> Result<Self, ron::Error> {
+        let type_path = value
+            .get_represented_type_info()
+            .ok_or_else(|| {
+                ron::Error::Message(format!(
+                    "{} does not represent a concrete type",
+                    value.reflect_type_path()
+                ))
+            })?
+            .type_path()
+            .to_string();
+        let ron = ron::to_string(&TypedReflectSerializer::new(value, registry))?;
+        Ok(Self::Reflected { type_path, ron })
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, EventPayload::None)
+    }
+
+    /// Returns the payload as a value of type `T`, if it holds one.
+    pub fn get<T: FromReflect>(&self, registry: &TypeRegistry) -> Option<T> {
+        match self {
+            EventPayload::None => None,
+            EventPayload::F32(value) => T::from_reflect(value),
+            EventPayload::Bool(value) => T::from_reflect(value),
+            EventPayload::Vec2(value) => T::from_reflect(value),
+            EventPayload::Vec3(value) => T::from_reflect(value),
+            EventPayload::Quat(value) => T::from_reflect(value),
+            EventPayload::String(value) => T::from_reflect(value),
+            EventPayload::EntityPath(value) => T::from_reflect(value),
+            EventPayload::Reflected { .. } => T::from_reflect(self.to_reflect(registry)?.as_ref()),
+        }
+    }
+
+    /// Deserializes a [`EventPayload::Reflected`] payload into a dynamic value.
+    pub fn to_reflect(&self, registry: &TypeRegistry) -> Option<Box<dyn PartialReflect>> {
+        let EventPayload::Reflected { type_path, ron } = self else {
+            return None;
+        };
+        let registration = registry.get_with_type_path(type_path)?;
+        let mut deserializer = ron::Deserializer::from_str(ron).ok()?;
+        TypedReflectDeserializer::new(registration, registry)
+            .deserialize(&mut deserializer)
+            .ok()
+    }
+}
+
+// Only meant for caching, see the note on the `TrackItemValue` hash implementation.
+impl core::hash::Hash for EventPayload {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        let hash_floats = |floats: &[f32], state: &mut H| {
+            floats.iter().for_each(|f| f.to_bits().hash(state));
+        };
+        match self {
+            EventPayload::None => {}
+            EventPayload::F32(value) => hash_floats(&[*value], state),
+            EventPayload::Bool(value) => value.hash(state),
+            EventPayload::Vec2(value) => hash_floats(&value.to_array(), state),
+            EventPayload::Vec3(value) => hash_floats(&value.to_array(), state),
+            EventPayload::Quat(value) => hash_floats(&value.to_array(), state),
+            EventPayload::String(value) => value.hash(state),
+            EventPayload::EntityPath(value) => value.hash(state),
+            EventPayload::Reflected { type_path, ron } => {
+                type_path.hash(state);
+                ron.hash(state);
+            }
+        }
+    }
+}
+
 /// Structure containing a sampled event and relevant metadata
 #[derive(Clone, Debug, Reflect, Serialize, Deserialize, PartialEq)]
 #[reflect(Default)]
@@ -45,6 +154,9 @@ pub struct SampledEvent {
     pub percentage: f32,
     /// If the event comes from a markup track, contains the track id
     pub track: Option<String>,
+    /// Data attached to the event
+    #[serde(default, skip_serializing_if = "EventPayload::is_none")]
+    pub payload: EventPayload,
 }
 
 impl Default for SampledEvent {
@@ -54,6 +166,7 @@ impl Default for SampledEvent {
             weight: 1.,
             percentage: 1.,
             track: None,
+            payload: EventPayload::None,
         }
     }
 }
@@ -65,8 +178,14 @@ impl SampledEvent {
             weight: 1.,
             percentage: 1.,
             track: None,
+            payload: EventPayload::None,
         }
     }
+
+    pub fn with_payload(mut self, payload: EventPayload) -> Self {
+        self.payload = payload;
+        self
+    }
 }
 
 /// Sequence of events
@@ -100,3 +219,46 @@ impl EventQueue {
         self.events.push(SampledEvent::instant(event));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::reflect::TypePath;
+
+    use super::*;
+
+    #[derive(Reflect, Debug, PartialEq)]
+    struct Hit {
+        damage: f32,
+        socket: String,
+    }
+
+    #[test]
+    fn test_reflected_payload_roundtrip() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Hit>();
+
+        let hit = Hit {
+            damage: 12.5,
+            socket: "hand_r".into(),
+        };
+        let payload = EventPayload::reflected(&hit, &registry).unwrap();
+        assert!(matches!(
+            &payload,
+            EventPayload::Reflected { type_path, .. } if type_path == Hit::type_path()
+        ));
+
+        // The payload must survive being saved in an asset
+        let serialized = ron::to_string(&payload).unwrap();
+        let payload: EventPayload = ron::from_str(&serialized).unwrap();
+
+        assert_eq!(payload.get::<Hit>(&registry), Some(hit));
+    }
+
+    #[test]
+    fn test_builtin_payload_get() {
+        let registry = TypeRegistry::default();
+        assert_eq!(EventPayload::F32(3.).get::<f32>(&registry), Some(3.));
+        assert_eq!(EventPayload::F32(3.).get::<bool>(&registry), None);
+        assert_eq!(EventPayload::None.get::<f32>(&registry), None);
+    }
+}
@@ -34,6 +34,7 @@ pub enum DataSpec {
 #[derive(Serialize, Deserialize, Reflect, Clone, Debug, ValueWrapper, PartialEq)]
 #[unwrap_error(error(crate::errors::GraphError), variant(MismatchedDataType))]
 #[reflect(Default)]
+#[allow(clippy::large_enum_variant)]
 pub enum DataValue {
     #[trivial_copy]
     F32(f32),
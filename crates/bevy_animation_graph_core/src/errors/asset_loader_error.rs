@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 use thiserror::Error;
 
-use super::GraphValidationError;
+use super::{BvhError, GraphValidationError, KeyframeError};
+use crate::animation_clip::range::ClipRange;
 
 /// Possible errors that can be produced by a custom asset loader
 // TODO: clean this up
@@ -22,6 +23,16 @@ pub enum AssetLoaderError {
     GltfError(#[from] bevy::gltf::GltfError),
     #[error("Could not find gltf named label: {0}")]
     GltfMissingLabel(String),
+    #[error("Could not load keyframes: {0}")]
+    InvalidKeyframes(#[from] KeyframeError),
+    #[error("Could not read keyframes file: {0}")]
+    ReadKeyframesError(#[from] bevy::asset::ReadAssetBytesError),
+    #[error("Could not decode keyframes file: {0}")]
+    DecodeKeyframesError(#[from] rmp_serde::decode::Error),
+    #[error("Could not parse BVH file: {0}")]
+    BvhError(#[from] BvhError),
+    #[error("Clip range {0:?} is empty or outside of the animation")]
+    InvalidClipRange(ClipRange),
     #[error("Could not complete direct asset load: {0}")]
     LoadDirectError(#[from] bevy::asset::LoadDirectError),
     #[error("Animated scene path is incorrect: {0}")]
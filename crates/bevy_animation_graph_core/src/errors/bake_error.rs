@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+use super::GraphError;
+use crate::id::BoneId;
+
+/// Possible errors that can be produced when baking a graph with
+/// [`bake`](crate::bake::bake)
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum BakeError {
+    #[error("The sample rate must be positive and the end time must not be before the start time")]
+    InvalidSettings,
+    #[error("The skeleton of the evaluated graph is missing")]
+    SkeletonMissing,
+    #[error("Bone {0:?} of the output pose is not in the skeleton")]
+    BoneNotInSkeleton(BoneId),
+    #[error("Could not evaluate the graph: {0}")]
+    Evaluation(#[from] GraphError),
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+/// Possible errors that can be produced when parsing a [`Bvh`](crate::bvh::Bvh) file
+#[non_exhaustive]
+#[derive(Debug, Error, Clone)]
+pub enum BvhError {
+    #[error("BVH file is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("BVH file ended unexpectedly")]
+    UnexpectedEnd,
+    #[error("Expected {expected} at line {line}, found {found}")]
+    UnexpectedToken {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("Expected a number at line {line}, found {found}")]
+    InvalidNumber { line: usize, found: String },
+    #[error("Unknown channel {channel} at line {line}")]
+    UnknownChannel { line: usize, channel: String },
+}
@@ -0,0 +1,12 @@
+use thiserror::Error;
+
+use crate::animation_clip::EntityPath;
+
+/// Keyframes of a [`ClipKeyframes`](crate::animation_clip::keyframes::ClipKeyframes) that
+/// cannot be turned into animation curves
+#[derive(Debug, Error, Clone)]
+#[error("Invalid keyframes for bone {path}: {reason}")]
+pub struct KeyframeError {
+    pub path: EntityPath,
+    pub reason: String,
+}
@@ -1,9 +1,17 @@
 mod asset_loader_error;
+mod bake_error;
+mod bvh_error;
 mod graph_error;
+mod keyframe_error;
 mod saving_error;
+mod snapshot_error;
 mod validation_error;
 
 pub use asset_loader_error::*;
+pub use bake_error::*;
+pub use bvh_error::*;
 pub use graph_error::*;
+pub use keyframe_error::*;
 pub use saving_error::*;
+pub use snapshot_error::*;
 pub use validation_error::*;
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use thiserror::Error;
+
+/// Possible errors that can be produced when taking or restoring a
+/// [`PlayerSnapshot`](crate::snapshot::PlayerSnapshot)
+#[non_exhaustive]
+#[derive(Debug, Error, Reflect, Clone)]
+pub enum SnapshotError {
+    #[error("The player is not playing an animation graph")]
+    MissingGraph,
+    #[error("Node state type is not registered: {0}")]
+    UnregisteredStateType(String),
+    #[error("Node state type {0} cannot be restored, is it missing #[reflect(GraphStateType)]?")]
+    NotAGraphStateType(String),
+    #[error("Could not serialize node state of type {0}: {1}")]
+    Serialization(String, String),
+    #[error("Could not deserialize node state of type {0}: {1}")]
+    Deserialization(String, String),
+}
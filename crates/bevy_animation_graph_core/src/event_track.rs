@@ -2,7 +2,7 @@ use bevy::reflect::Reflect;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::edge_data::events::{AnimationEvent, SampledEvent};
+use super::edge_data::events::{AnimationEvent, EventPayload, SampledEvent};
 
 #[derive(Debug, Reflect, Clone, Serialize, Deserialize, Default)]
 pub struct TrackItem {
@@ -15,6 +15,9 @@ pub struct TrackItemValue {
     pub event: AnimationEvent,
     pub start_time: f32,
     pub end_time: f32,
+    /// Data attached to the event every time it is sampled
+    #[serde(default, skip_serializing_if = "EventPayload::is_none")]
+    pub payload: EventPayload,
 }
 
 // **IMPORTANT**
@@ -25,6 +28,7 @@ impl core::hash::Hash for TrackItemValue {
         self.event.hash(state);
         self.start_time.to_bits().hash(state);
         self.end_time.to_bits().hash(state);
+        self.payload.hash(state);
     }
 }
 
@@ -87,6 +91,7 @@ impl EventTrack {
                 weight: 1.,
                 percentage: ev.percentage_at_time(time),
                 track: Some(self.name.clone()),
+                payload: ev.value.payload.clone(),
             })
             .collect()
     }
@@ -0,0 +1,254 @@
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{prelude::*, system::SystemState},
+    platform::collections::HashMap,
+};
+
+use crate::{
+    animation_clip::GraphClip,
+    animation_graph::{AnimationGraph, DEFAULT_OUTPUT_POSE, PinId, TimeUpdate},
+    animation_graph_player::{AnimationGraphPlayer, AnimationSource},
+    context::{deferred_gizmos::DeferredGizmos, system_resources::SystemResources},
+    edge_data::{DataValue, events::SampledEvent},
+    errors::GraphError,
+    pose::Pose,
+    skeleton::Skeleton,
+    state_machine::high_level::StateMachine,
+};
+
+/// Assets that a [`HeadlessEvaluator`] evaluates graphs against. Every graph, clip, state
+/// machine and skeleton referenced by the evaluated graph must be in here.
+#[derive(Default)]
+pub struct GraphAssets {
+    pub graphs: Assets<AnimationGraph>,
+    pub clips: Assets<GraphClip>,
+    pub state_machines: Assets<StateMachine>,
+    pub skeletons: Assets<Skeleton>,
+}
+
+/// Evaluates an animation graph outside of a Bevy app, e.g. in unit tests or offline tools.
+///
+/// The graph is played by an [`AnimationGraphPlayer`] that is not attached to any entity. Its
+/// inputs, events and seed are set through [`HeadlessEvaluator::player_mut`], and time only
+/// advances through [`HeadlessEvaluator::step`]. As there is no scene, nodes that read bone
+/// transforms from the scene (e.g. IK targets given as entities) cannot be evaluated.
+///
+/// ```ignore
+/// let mut assets = GraphAssets::default();
+/// let skeleton = assets.skeletons.add(skeleton);
+/// let graph = assets.graphs.add(graph);
+///
+/// let mut evaluator = HeadlessEvaluator::new(assets, graph, skeleton);
+/// evaluator.player_mut().set_input_data("speed", DataValue::F32(1.5));
+/// let steps = evaluator.run([TimeUpdate::Absolute(0.), TimeUpdate::Delta(0.5)])?;
+/// let pose = steps[1].pose().unwrap();
+/// ```
+pub struct HeadlessEvaluator {
+    /// Holds the assets only, no entities are spawned
+    world: World,
+    system_resources: SystemState<SystemResources<'static, 'static>>,
+    player: AnimationGraphPlayer,
+}
+
+impl HeadlessEvaluator {
+    pub fn new(
+        assets: GraphAssets,
+        graph: Handle<AnimationGraph>,
+        skeleton: Handle<Skeleton>,
+    ) -> Self {
+        let mut world = World::new();
+        world.insert_resource(assets.graphs);
+        world.insert_resource(assets.clips);
+        world.insert_resource(assets.state_machines);
+        world.insert_resource(assets.skeletons);
+        let system_resources = SystemState::new(&mut world);
+
+        Self {
+            world,
+            system_resources,
+            player: AnimationGraphPlayer::new(skeleton).with_graph(graph),
+        }
+    }
+
+    pub fn player(&self) -> &AnimationGraphPlayer {
+        &self.player
+    }
+
+    /// The skeleton of the player, if it is in the assets
+    pub fn skeleton(&self) -> Option<&Skeleton> {
+        self.world
+            .resource::<Assets<Skeleton>>()
+            .get(self.player.skeleton())
+    }
+
+    /// The player evaluating the graph, to set inputs, send events or restore a snapshot before
+    /// the next step
+    pub fn player_mut(&mut self) -> &mut AnimationGraphPlayer {
+        &mut self.player
+    }
+
+    /// Evaluates the graph once with the given time update
+    pub fn step(&mut self, time_update: TimeUpdate) -> Result<EvaluationStep, GraphError> {
+        let system_resources = self.system_resources.get_mut(&mut self.world);
+        let AnimationSource::Graph(graph) = self.player.get_animation_source() else {
+            return Err(GraphError::GraphAssetMissing);
+        };
+        if !system_resources.animation_graph_assets.contains(graph) {
+            return Err(GraphError::GraphAssetMissing);
+        }
+
+        self.player.queue_time_update(time_update);
+        self.player.update(&system_resources, Entity::PLACEHOLDER);
+        // There is nothing to draw them on
+        self.player.deferred_gizmos = DeferredGizmos::default();
+
+        if let Some(error) = self.player.get_error() {
+            return Err(error);
+        }
+
+        Ok(EvaluationStep {
+            time: self.player.elapsed(),
+            outputs: self.player.get_outputs().clone(),
+        })
+    }
+
+    /// Evaluates the graph once for each time update, stopping at the first error
+    pub fn run(
+        &mut self,
+        time_updates: impl IntoIterator<Item = TimeUpdate>,
+    ) -> Result<Vec<EvaluationStep>, GraphError> {
+        time_updates
+            .into_iter()
+            .map(|time_update| self.step(time_update))
+            .collect()
+    }
+}
+
+/// Outputs of a [`HeadlessEvaluator::step`]
+#[derive(Debug, Clone)]
+pub struct EvaluationStep {
+    /// Timestamp of the default output pose after the step
+    pub time: f32,
+    pub outputs: HashMap<PinId, DataValue>,
+}
+
+impl EvaluationStep {
+    /// The default output pose
+    pub fn pose(&self) -> Option<&Pose> {
+        self.outputs.get(DEFAULT_OUTPUT_POSE)?.as_pose().ok()
+    }
+
+    /// Events of every event queue output, ordered by output pin
+    pub fn events(&self) -> Vec<(&PinId, &SampledEvent)> {
+        let mut pins: Vec<_> = self.outputs.keys().collect();
+        pins.sort();
+        pins.into_iter()
+            .filter_map(|pin| Some((pin, self.outputs.get(pin)?.as_event_queue().ok()?)))
+            .flat_map(|(pin, queue)| queue.events.iter().map(move |event| (pin, event)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::reflect::Reflect;
+
+    use super::*;
+    use crate::{
+        animation_node::{AnimationNode, NodeLike},
+        context::{new_context::NodeContext, spec_context::SpecContext},
+        edge_data::{
+            DataSpec,
+            events::{AnimationEvent, EventQueue},
+        },
+    };
+
+    /// Outputs an empty pose and an event at the current time
+    #[derive(Reflect, Clone, Debug)]
+    struct TickNode;
+
+    impl NodeLike for TickNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let prev_time = ctx.prev_time();
+            let time = ctx
+                .time_update_fwd()?
+                .partial_update_basic(prev_time)
+                .unwrap_or(prev_time);
+            ctx.set_time(time);
+
+            let pose = Pose {
+                timestamp: time,
+                ..Default::default()
+            };
+            let tick = SampledEvent {
+                event: AnimationEvent::StringId("tick".into()),
+                percentage: time,
+                ..Default::default()
+            };
+            ctx.set_data_fwd(DEFAULT_OUTPUT_POSE, pose);
+            ctx.set_data_fwd("events", EventQueue::with_events([tick]));
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_output_time()
+                .add_output_data(DEFAULT_OUTPUT_POSE, DataSpec::Pose)
+                .add_output_data("events", DataSpec::EventQueue);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Tick".into()
+        }
+    }
+
+    fn tick_graph() -> AnimationGraph {
+        let mut graph = AnimationGraph::new();
+        let node = AnimationNode::new("tick", TickNode);
+        let id = node.id;
+        graph.add_node(node);
+        graph.add_output_data(DEFAULT_OUTPUT_POSE.into(), DataSpec::Pose);
+        graph.add_output_data("events".into(), DataSpec::EventQueue);
+        graph.add_output_time();
+        graph.add_output_data_edge(id, DEFAULT_OUTPUT_POSE, DEFAULT_OUTPUT_POSE);
+        graph.add_output_data_edge(id, "events", "events");
+        graph.add_output_pose_edge(id);
+        graph
+    }
+
+    #[test]
+    fn test_headless_evaluation_follows_time_updates() {
+        let mut assets = GraphAssets::default();
+        let graph = assets.graphs.add(tick_graph());
+        let skeleton = assets.skeletons.add(Skeleton::default());
+        let mut evaluator = HeadlessEvaluator::new(assets, graph, skeleton);
+
+        let steps = evaluator
+            .run([
+                TimeUpdate::Absolute(1.),
+                TimeUpdate::Delta(0.5),
+                TimeUpdate::Delta(0.25),
+            ])
+            .unwrap();
+
+        let times: Vec<_> = steps.iter().map(|step| step.time).collect();
+        assert_eq!(times, [1., 1.5, 1.75]);
+        for step in &steps {
+            assert_eq!(step.pose().unwrap().timestamp, step.time);
+            let events = step.events();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].0, "events");
+            assert_eq!(events[0].1.percentage, step.time);
+        }
+    }
+
+    #[test]
+    fn test_headless_evaluation_requires_the_graph_asset() {
+        let mut evaluator =
+            HeadlessEvaluator::new(GraphAssets::default(), Handle::default(), Handle::default());
+        assert!(matches!(
+            evaluator.step(TimeUpdate::Delta(0.1)),
+            Err(GraphError::GraphAssetMissing)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::{
+    math::{Quat, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+use serde::{Deserialize, Serialize};
+
+/// Limits how far an aim direction turns away from a reference direction, e.g. how far a head
+/// turns away from where the animation has it look. Yaw is the angle around the Y axis, and
+/// pitch is the angle above or below the horizontal plane.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub struct AimLimits {
+    /// Largest yaw in radians to either side of the reference direction
+    pub max_yaw: f32,
+    /// Largest pitch in radians above or below the reference direction
+    pub max_pitch: f32,
+}
+
+impl Default for AimLimits {
+    fn default() -> Self {
+        Self {
+            max_yaw: PI,
+            max_pitch: PI,
+        }
+    }
+}
+
+impl AimLimits {
+    /// The direction within the limits around `reference` that is closest to `direction`. Both
+    /// directions must be normalized.
+    pub fn apply(&self, reference: Vec3, direction: Vec3) -> Vec3 {
+        let (reference_yaw, reference_pitch) = yaw_pitch(reference);
+        let (yaw, pitch) = yaw_pitch(direction);
+
+        let yaw_offset = (yaw - reference_yaw + PI).rem_euclid(2. * PI) - PI;
+        let yaw = reference_yaw + yaw_offset.clamp(-self.max_yaw, self.max_yaw);
+        let pitch = (reference_pitch
+            + (pitch - reference_pitch).clamp(-self.max_pitch, self.max_pitch))
+        .clamp(-FRAC_PI_2, FRAC_PI_2);
+
+        Vec3::new(
+            pitch.cos() * yaw.sin(),
+            pitch.sin(),
+            pitch.cos() * yaw.cos(),
+        )
+    }
+}
+
+/// Turns `from` towards `to` by the given fraction of the angle between them
+pub fn turn_towards(from: Vec3, to: Vec3, fraction: f32) -> Vec3 {
+    Quat::IDENTITY.slerp(Quat::from_rotation_arc(from, to), fraction) * from
+}
+
+/// Fraction of the way to a target that smoothing covers in `delta_time` seconds, for a
+/// `half_life` in seconds. A half-life of 0 or less disables smoothing.
+pub fn smoothing_factor(delta_time: f32, half_life: f32) -> f32 {
+    if half_life > 0. {
+        1. - 0.5_f32.powf(delta_time / half_life)
+    } else {
+        1.
+    }
+}
+
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    (
+        direction.x.atan2(direction.z),
+        direction.y.clamp(-1., 1.).asin(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+
+    #[test]
+    fn test_directions_within_the_limits_are_kept() {
+        let limits = AimLimits {
+            max_yaw: FRAC_PI_2,
+            max_pitch: FRAC_PI_4,
+        };
+        let direction = Vec3::new(1., 0.5, 1.).normalize();
+        assert!(
+            limits
+                .apply(Vec3::Z, direction)
+                .abs_diff_eq(direction, 1e-5)
+        );
+    }
+
+    #[test]
+    fn test_yaw_and_pitch_are_clamped() {
+        let limits = AimLimits {
+            max_yaw: FRAC_PI_4,
+            max_pitch: 0.,
+        };
+        // Behind and above the reference, to its left
+        let limited = limits.apply(Vec3::Z, Vec3::new(1., 1., -1.).normalize());
+        assert!(limited.abs_diff_eq(Vec3::new(1., 0., 1.).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_yaw_wraps_around_behind_the_reference() {
+        let limits = AimLimits {
+            max_yaw: FRAC_PI_4,
+            max_pitch: PI,
+        };
+        // The reference is just left of straight back, the direction just right of it
+        let reference = Vec3::new(0.1, 0., -1.).normalize();
+        let direction = Vec3::new(-0.1, 0., -1.).normalize();
+        assert!(
+            limits
+                .apply(reference, direction)
+                .abs_diff_eq(direction, 1e-5)
+        );
+    }
+
+    #[test]
+    fn test_turn_towards() {
+        let turned = turn_towards(Vec3::Z, Vec3::X, 0.5);
+        assert!(turned.abs_diff_eq(Vec3::new(1., 0., 1.).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_smoothing_factor() {
+        assert_eq!(smoothing_factor(0.2, 0.2), 0.5);
+        assert_eq!(smoothing_factor(0.4, 0.2), 0.75);
+        assert_eq!(smoothing_factor(0., 0.2), 0.);
+        assert_eq!(smoothing_factor(0.1, 0.), 1.);
+    }
+}
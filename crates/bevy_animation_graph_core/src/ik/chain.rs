@@ -0,0 +1,275 @@
+use bevy::{
+    math::{Quat, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use super::joint_limit::JointLimit;
+
+/// Algorithm that [`IKChain::solve`] uses
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Default)]
+pub enum ChainIKSolver {
+    /// Forward and backward reaching inverse kinematics. Moves the joints towards the target and
+    /// back to the start of the chain, which spreads the bend over the whole chain.
+    #[default]
+    Fabrik,
+    /// Cyclic coordinate descent. Rotates one joint at a time from the end of the chain, which
+    /// bends the joints close to the end the most.
+    Ccd,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub struct ChainIKSettings {
+    pub solver: ChainIKSolver,
+    /// Largest number of iterations of the solver
+    pub iterations: usize,
+    /// Distance between the end of the chain and the target at which the chain is solved
+    pub tolerance: f32,
+}
+
+impl Default for ChainIKSettings {
+    fn default() -> Self {
+        Self {
+            solver: ChainIKSolver::Fabrik,
+            iterations: 10,
+            tolerance: 0.001,
+        }
+    }
+}
+
+/// A joint of an [`IKChain`]
+#[derive(Clone, Copy, Debug)]
+pub struct IKJoint {
+    /// Transform relative to the previous joint, or to the base for the first joint
+    pub local: Transform,
+    /// Local rotation in the rest pose, which the limit is relative to
+    pub rest_rotation: Quat,
+    pub limit: Option<JointLimit>,
+}
+
+/// Chain of joints whose end is moved to a target by rotating the joints. The last joint is the
+/// end of the chain, and it is only rotated by [`IKChain::set_end_rotation`].
+#[derive(Clone, Debug, Default)]
+pub struct IKChain {
+    /// Transform of the parent of the first joint. Targets are in the same space as the base.
+    pub base: Transform,
+    pub joints: Vec<IKJoint>,
+}
+
+impl IKChain {
+    /// Transforms of the joints in the space of the base
+    pub fn transforms(&self) -> Vec<Transform> {
+        self.joints
+            .iter()
+            .scan(self.base, |parent, joint| {
+                *parent = *parent * joint.local;
+                Some(*parent)
+            })
+            .collect()
+    }
+
+    /// Rotates the joints so that the end of the chain reaches the target, or gets as close as
+    /// the chain and the joint limits allow. Returns the distance left to the target.
+    pub fn solve(&mut self, target: Vec3, settings: &ChainIKSettings) -> f32 {
+        for _ in 0..settings.iterations {
+            if self.joints.len() < 2 || self.distance_to(target) <= settings.tolerance {
+                break;
+            }
+            match settings.solver {
+                ChainIKSolver::Fabrik => self.fabrik_iteration(target),
+                ChainIKSolver::Ccd => self.ccd_iteration(target),
+            }
+        }
+
+        self.distance_to(target)
+    }
+
+    /// Sets the rotation of the end joint, in the space of the base
+    pub fn set_end_rotation(&mut self, rotation: Quat) {
+        let transforms = self.transforms();
+        let Some(end) = transforms.len().checked_sub(1) else {
+            return;
+        };
+        let end_rotation = transforms[end].rotation;
+        self.rotate_joint(end, rotation * end_rotation.inverse(), &transforms);
+    }
+
+    fn distance_to(&self, target: Vec3) -> f32 {
+        self.transforms()
+            .last()
+            .map_or(0., |end| end.translation.distance(target))
+    }
+
+    /// Rotates a joint by `rotation`, given in the space of the base
+    fn rotate_joint(&mut self, index: usize, rotation: Quat, transforms: &[Transform]) {
+        let parent_rotation = match index {
+            0 => self.base.rotation,
+            _ => transforms[index - 1].rotation,
+        };
+        let joint = &mut self.joints[index];
+        let local_rotation =
+            (parent_rotation.inverse() * rotation * transforms[index].rotation).normalize();
+        joint.local.rotation = match joint.limit {
+            Some(limit) => limit.apply(joint.rest_rotation, local_rotation),
+            None => local_rotation,
+        };
+    }
+
+    /// Rotates a joint so that the direction to its child points from the joint to `position`
+    fn aim_joint(&mut self, index: usize, position: Vec3) {
+        let transforms = self.transforms();
+        let joint = transforms[index].translation;
+        if let (Some(from), Some(to)) = (
+            (transforms[index + 1].translation - joint).try_normalize(),
+            (position - joint).try_normalize(),
+        ) {
+            self.rotate_joint(index, Quat::from_rotation_arc(from, to), &transforms);
+        }
+    }
+
+    fn fabrik_iteration(&mut self, target: Vec3) {
+        let mut positions: Vec<Vec3> = self
+            .transforms()
+            .iter()
+            .map(|transform| transform.translation)
+            .collect();
+        let lengths: Vec<f32> = positions
+            .windows(2)
+            .map(|bone| bone[0].distance(bone[1]))
+            .collect();
+        let end = positions.len() - 1;
+        let start = positions[0];
+
+        // Backward pass, from the target to the start of the chain
+        positions[end] = target;
+        for i in (0..end).rev() {
+            let direction = (positions[i] - positions[i + 1]).normalize_or_zero();
+            positions[i] = positions[i + 1] + direction * lengths[i];
+        }
+
+        // Forward pass, from the start of the chain back to its end
+        positions[0] = start;
+        for i in 0..end {
+            let direction = (positions[i + 1] - positions[i]).normalize_or_zero();
+            positions[i + 1] = positions[i] + direction * lengths[i];
+        }
+
+        // The joints are rotated towards the new positions, which also applies their limits
+        for (i, position) in positions.into_iter().enumerate().skip(1) {
+            self.aim_joint(i - 1, position);
+        }
+    }
+
+    fn ccd_iteration(&mut self, target: Vec3) {
+        for i in (0..self.joints.len() - 1).rev() {
+            let transforms = self.transforms();
+            let joint = transforms[i].translation;
+            let end = transforms[transforms.len() - 1].translation;
+            if let (Some(from), Some(to)) = (
+                (end - joint).try_normalize(),
+                (target - joint).try_normalize(),
+            ) {
+                self.rotate_joint(i, Quat::from_rotation_arc(from, to), &transforms);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+
+    /// Four joints one unit apart along the Y axis
+    fn straight_chain() -> IKChain {
+        let joint = |translation| IKJoint {
+            local: Transform::from_translation(translation),
+            rest_rotation: Quat::IDENTITY,
+            limit: None,
+        };
+        IKChain {
+            base: Transform::IDENTITY,
+            joints: vec![
+                joint(Vec3::ZERO),
+                joint(Vec3::Y),
+                joint(Vec3::Y),
+                joint(Vec3::Y),
+            ],
+        }
+    }
+
+    fn bone_lengths(chain: &IKChain) -> Vec<f32> {
+        chain
+            .transforms()
+            .windows(2)
+            .map(|bone| bone[0].translation.distance(bone[1].translation))
+            .collect()
+    }
+
+    #[test]
+    fn test_solvers_reach_the_target() {
+        let target = Vec3::new(1.5, 1.5, 0.5);
+        for solver in [ChainIKSolver::Fabrik, ChainIKSolver::Ccd] {
+            let settings = ChainIKSettings {
+                solver,
+                iterations: 50,
+                tolerance: 1e-3,
+            };
+            let mut chain = straight_chain();
+            assert!(chain.solve(target, &settings) <= 1e-3, "{solver:?}");
+            for length in bone_lengths(&chain) {
+                assert!((length - 1.).abs() < 1e-4, "{solver:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unreachable_targets_stretch_the_chain() {
+        let mut chain = straight_chain();
+        let distance = chain.solve(Vec3::new(10., 0., 0.), &ChainIKSettings::default());
+        assert!((distance - 7.).abs() < 1e-2);
+        let end = chain.transforms()[3].translation;
+        assert!(end.abs_diff_eq(Vec3::new(3., 0., 0.), 1e-2));
+    }
+
+    #[test]
+    fn test_joint_limits_are_respected() {
+        let mut chain = straight_chain();
+        for joint in &mut chain.joints {
+            joint.limit = Some(JointLimit {
+                max_angle: FRAC_PI_4,
+                hinge_axis: Some(Vec3::Z),
+            });
+        }
+
+        for solver in [ChainIKSolver::Fabrik, ChainIKSolver::Ccd] {
+            let settings = ChainIKSettings {
+                solver,
+                ..Default::default()
+            };
+            let mut chain = chain.clone();
+            chain.solve(Vec3::new(2., -1., 1.), &settings);
+            for joint in &chain.joints {
+                let (axis, angle) = joint.local.rotation.to_axis_angle();
+                assert!(angle <= FRAC_PI_4 + 1e-4, "{solver:?}");
+                assert!(
+                    angle < 1e-4 || axis.abs().abs_diff_eq(Vec3::Z, 1e-4),
+                    "{solver:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_end_rotation() {
+        let mut chain = straight_chain();
+        chain.solve(Vec3::new(1.5, 1.5, 0.), &ChainIKSettings::default());
+        let rotation = Quat::from_rotation_x(1.);
+        chain.set_end_rotation(rotation);
+        assert!(chain.transforms()[3].rotation.abs_diff_eq(rotation, 1e-5));
+    }
+}
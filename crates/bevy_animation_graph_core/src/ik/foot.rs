@@ -0,0 +1,227 @@
+use bevy::{
+    ecs::{entity::Entity, resource::Resource},
+    math::{Dir3, Vec3},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+use serde::{Deserialize, Serialize};
+
+/// Point where a ray hit the ground, in global space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroundHit {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+/// Finds the ground under the feet of a character for foot IK.
+///
+/// With the `physics_avian` feature it is implemented for avian's `SpatialQueryPipeline`, which
+/// is used unless a [`CustomGroundQuery`] is inserted.
+pub trait GroundQuery: Send + Sync {
+    /// Closest ground hit along a ray, in global space. The `excluded` entities belong to the
+    /// character itself and are never ground.
+    fn ground_hit(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        excluded: &[Entity],
+    ) -> Option<GroundHit>;
+}
+
+/// Ground query used by foot IK instead of the physics engine, e.g. to test without physics or
+/// to only consider some of the colliders
+#[derive(Resource)]
+pub struct CustomGroundQuery(pub Box<dyn GroundQuery>);
+
+#[cfg(feature = "physics_avian")]
+impl GroundQuery for avian3d::prelude::SpatialQueryPipeline {
+    fn ground_hit(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        excluded: &[Entity],
+    ) -> Option<GroundHit> {
+        self.cast_ray(
+            origin,
+            direction,
+            max_distance,
+            true,
+            &avian3d::prelude::SpatialQueryFilter::from_excluded_entities(excluded.iter().copied()),
+        )
+        .map(|hit| GroundHit {
+            point: origin + direction * hit.distance,
+            normal: hit.normal,
+        })
+    }
+}
+
+/// How the ground under a foot is searched for. Rays are cast straight down in global space.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub struct FootRaySettings {
+    /// Height above the foot that rays start from, which is how much higher than in the
+    /// animation the ground can be
+    pub ray_height: f32,
+    /// How much lower than in the animation the ground can be
+    pub ray_depth: f32,
+}
+
+impl Default for FootRaySettings {
+    fn default() -> Self {
+        Self {
+            ray_height: 0.5,
+            ray_depth: 0.5,
+        }
+    }
+}
+
+/// Where a foot is moved to stay on the ground
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct FootPlacement {
+    /// Vertical offset of the foot, in global space
+    pub offset: f32,
+    /// Normal of the ground under the foot, in global space
+    pub normal: Vec3,
+}
+
+impl Default for FootPlacement {
+    fn default() -> Self {
+        Self {
+            offset: 0.,
+            normal: Vec3::Y,
+        }
+    }
+}
+
+impl FootRaySettings {
+    /// Placement of a foot at the global position `foot`, which the animation has at `height`
+    /// above the ground. `None` if there is no ground under the foot. Colliders of the
+    /// `excluded` entities are not ground, see [`GroundQuery::ground_hit`].
+    pub fn place_foot(
+        &self,
+        query: &dyn GroundQuery,
+        foot: Vec3,
+        height: f32,
+        excluded: &[Entity],
+    ) -> Option<FootPlacement> {
+        let hit = query.ground_hit(
+            foot + Vec3::Y * self.ray_height,
+            Dir3::NEG_Y,
+            self.ray_height + height.max(0.) + self.ray_depth,
+            excluded,
+        )?;
+        Some(FootPlacement {
+            offset: hit.point.y + height - foot.y,
+            normal: hit.normal.normalize_or(Vec3::Y),
+        })
+    }
+}
+
+/// Vertical offset of the pelvis that lets the legs reach all feet placements: the offset of the
+/// lowest foot, clamped to `max_offset` in either direction.
+pub fn pelvis_offset(placements: &[FootPlacement], max_offset: f32) -> f32 {
+    placements
+        .iter()
+        .map(|placement| placement.offset)
+        .reduce(f32::min)
+        .map_or(0., |offset| {
+            offset.clamp(-max_offset.abs(), max_offset.abs())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::Assets,
+        ecs::{system::SystemState, world::World},
+    };
+
+    use super::*;
+    use crate::{
+        animation_clip::GraphClip, animation_graph::AnimationGraph,
+        context::system_resources::SystemResources, skeleton::Skeleton,
+        state_machine::high_level::StateMachine,
+    };
+
+    /// Ground at y = 0 for x < 0, and a step up to y = 0.3 for x >= 0
+    struct Step;
+
+    impl GroundQuery for Step {
+        fn ground_hit(
+            &self,
+            origin: Vec3,
+            direction: Dir3,
+            max_distance: f32,
+            _excluded: &[Entity],
+        ) -> Option<GroundHit> {
+            let height = if origin.x < 0. { 0. } else { 0.3 };
+            let distance = (origin.y - height) / -direction.y;
+            (0. ..=max_distance)
+                .contains(&distance)
+                .then_some(GroundHit {
+                    point: origin + direction * distance,
+                    normal: Vec3::Y,
+                })
+        }
+    }
+
+    #[test]
+    fn test_feet_are_placed_on_the_ground() {
+        let settings = FootRaySettings::default();
+        // A foot 0.1 above the ground in the animation stays 0.1 above it
+        let low = settings
+            .place_foot(&Step, Vec3::new(-1., 0.1, 0.), 0.1, &[])
+            .unwrap();
+        assert!(low.offset.abs() < 1e-5);
+        let high = settings
+            .place_foot(&Step, Vec3::new(1., 0.1, 0.), 0.1, &[])
+            .unwrap();
+        assert!((high.offset - 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_no_ground_out_of_range() {
+        let settings = FootRaySettings {
+            ray_height: 0.5,
+            ray_depth: 0.2,
+        };
+        assert!(
+            settings
+                .place_foot(&Step, Vec3::new(-1., 1., 0.), 0., &[])
+                .is_none()
+        );
+        assert!(
+            settings
+                .place_foot(&Step, Vec3::new(1., -0.3, 0.), 0., &[])
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_pelvis_follows_the_lowest_foot() {
+        let placement = |offset| FootPlacement {
+            offset,
+            normal: Vec3::Y,
+        };
+        assert_eq!(pelvis_offset(&[placement(0.2), placement(-0.1)], 0.5), -0.1);
+        assert_eq!(pelvis_offset(&[placement(-0.8), placement(0.)], 0.5), -0.5);
+        assert_eq!(pelvis_offset(&[], 0.5), 0.);
+    }
+
+    #[test]
+    fn test_custom_ground_query_is_used() {
+        let mut world = World::new();
+        world.init_resource::<Assets<GraphClip>>();
+        world.init_resource::<Assets<AnimationGraph>>();
+        world.init_resource::<Assets<StateMachine>>();
+        world.init_resource::<Assets<Skeleton>>();
+        world.insert_resource(CustomGroundQuery(Box::new(Step)));
+        let mut system_state = SystemState::<SystemResources>::new(&mut world);
+        let resources = system_state.get_mut(&mut world);
+
+        let ground = resources.ground_query().unwrap();
+        let hit = ground.ground_hit(Vec3::new(1., 1., 0.), Dir3::NEG_Y, 2., &[]);
+        assert_eq!(hit.map(|hit| hit.point.y), Some(0.3));
+    }
+}
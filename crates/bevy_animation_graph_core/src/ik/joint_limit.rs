@@ -0,0 +1,96 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    math::{Quat, Vec3, Vec4},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+use serde::{Deserialize, Serialize};
+
+/// Limits how far a joint rotates away from its rest pose
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub struct JointLimit {
+    /// Largest angle in radians between the rest pose and the rotation of the joint
+    pub max_angle: f32,
+    /// If set, the joint only rotates around this axis, like a knee or the hinge of a mechanical
+    /// arm. The axis is in the space of the joint in its rest pose.
+    pub hinge_axis: Option<Vec3>,
+}
+
+impl Default for JointLimit {
+    fn default() -> Self {
+        Self {
+            max_angle: PI,
+            hinge_axis: None,
+        }
+    }
+}
+
+impl JointLimit {
+    /// The rotation within the limit that is closest to `rotation`. Both `rotation` and `rest`
+    /// are local rotations of the joint.
+    pub fn apply(&self, rest: Quat, rotation: Quat) -> Quat {
+        let mut offset = rest.inverse() * rotation;
+        if offset.w < 0. {
+            offset = -offset;
+        }
+
+        if let Some(axis) = self.hinge_axis.and_then(Vec3::try_normalize) {
+            // Only the twist around the axis is kept, the swing away from it is dropped
+            let twist = axis * offset.xyz().dot(axis);
+            let twist = Vec4::new(twist.x, twist.y, twist.z, offset.w);
+            offset = if twist.length_squared() > f32::EPSILON {
+                Quat::from_vec4(twist.normalize())
+            } else {
+                Quat::IDENTITY
+            };
+        }
+
+        let (axis, angle) = offset.to_axis_angle();
+        if angle > self.max_angle {
+            offset = Quat::from_axis_angle(axis, self.max_angle.max(0.));
+        }
+
+        (rest * offset).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotations_within_the_limit_are_kept() {
+        let limit = JointLimit {
+            max_angle: 1.,
+            hinge_axis: None,
+        };
+        let rest = Quat::from_rotation_y(0.5);
+        let rotation = rest * Quat::from_rotation_x(0.75);
+        assert!(limit.apply(rest, rotation).abs_diff_eq(rotation, 1e-5));
+    }
+
+    #[test]
+    fn test_rotations_are_clamped_to_the_max_angle() {
+        let limit = JointLimit {
+            max_angle: 0.5,
+            hinge_axis: None,
+        };
+        let rest = Quat::from_rotation_y(0.5);
+        let limited = limit.apply(rest, rest * Quat::from_rotation_x(2.));
+        assert!(limited.abs_diff_eq(rest * Quat::from_rotation_x(0.5), 1e-5));
+    }
+
+    #[test]
+    fn test_hinges_only_rotate_around_their_axis() {
+        let limit = JointLimit {
+            max_angle: PI,
+            hinge_axis: Some(Vec3::Z),
+        };
+        let rotation = Quat::from_rotation_z(0.8) * Quat::from_rotation_x(0.3);
+        let limited = limit.apply(Quat::IDENTITY, rotation);
+        let (axis, angle) = limited.to_axis_angle();
+        assert!(axis.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!((angle - 0.8).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod aim;
+pub mod chain;
+pub mod foot;
+pub mod joint_limit;
+pub mod two_bone;
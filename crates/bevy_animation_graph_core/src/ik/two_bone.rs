@@ -0,0 +1,182 @@
+use bevy::{
+    math::{Quat, Vec3},
+    transform::components::Transform,
+};
+
+/// How far short of full extension the chain is always kept, so that the middle joint never
+/// lines up exactly with the other two
+const MAX_LEN_OFFSET: f32 = 0.01;
+
+/// Rotates the root and middle joints of a two-bone chain (e.g. hip, knee and ankle) so that the
+/// end joint reaches `target_pos`. All transforms and positions are in the same space, and the
+/// returned transforms are in the order of the arguments.
+///
+/// The middle joint bends towards `pole` when given. Otherwise, it keeps bending in the direction
+/// it bent in before.
+///
+/// `softness` is the fraction of the length of the chain over which it straightens out more and
+/// more slowly as the target moves away. This keeps the middle joint from snapping straight when
+/// the target gets out of reach. A softness of 0 straightens the chain all the way.
+///
+/// Adapted from <https://blog.littlepolygon.com/posts/twobone/>
+pub fn two_bone_ik(
+    bone: Transform,
+    parent: Transform,
+    grandparent: Transform,
+    target_pos: Vec3,
+    pole: Option<Vec3>,
+    softness: f32,
+) -> (Transform, Transform, Transform) {
+    // compute joint positions
+    let in_end_loc = bone.translation;
+    let in_mid_loc = parent.translation;
+    let in_root_loc = grandparent.translation;
+
+    // compute bone lengths
+    let upper_len = in_root_loc.distance(in_mid_loc);
+    let lower_len = in_mid_loc.distance(in_end_loc);
+    let max_len = upper_len + lower_len - MAX_LEN_OFFSET;
+
+    // compute input planar basis vectors
+    let to_end = (in_end_loc - in_root_loc).normalize();
+    let in_pole_vec = (in_mid_loc - in_root_loc)
+        .reject_from(to_end)
+        .try_normalize()
+        .unwrap_or_else(|| to_end.any_orthonormal_vector());
+
+    // compute final planar basis vectors
+    let to_target_offset = target_pos - in_root_loc;
+    let to_target_offset = to_target_offset
+        .clamp_length_max(soft_distance(
+            to_target_offset.length(),
+            upper_len + lower_len,
+            softness,
+        ))
+        .clamp_length_max(max_len);
+    let to_target_dist = to_target_offset.length();
+    let to_target = to_target_offset / to_target_dist;
+
+    let to_target_swing = Quat::from_rotation_arc(to_end, to_target);
+    let out_pole_vec = pole
+        .and_then(|pole| (pole - in_root_loc).reject_from(to_target).try_normalize())
+        .unwrap_or(to_target_swing * in_pole_vec);
+
+    // apply law of cosines to get middle joint angle
+    let denom = 2. * upper_len * to_target_dist;
+    let mut cos_angle = 0.;
+    if denom > f32::EPSILON {
+        cos_angle = (to_target_dist * to_target_dist + upper_len * upper_len
+            - lower_len * lower_len)
+            / denom;
+    }
+    let angle = cos_angle.acos();
+
+    // compute final joint positions
+    let pole_dist = upper_len * angle.sin();
+    let eff_dist = upper_len * cos_angle;
+    let out_end_loc = in_root_loc + to_target_offset;
+    let out_mid_loc = in_root_loc + eff_dist * to_target + pole_dist * out_pole_vec;
+
+    // compute final rotations
+    let in_to_mid = in_mid_loc - in_root_loc;
+    let out_to_mid = out_mid_loc - in_root_loc;
+    let root_swing = Quat::from_rotation_arc(in_to_mid.normalize(), out_to_mid.normalize());
+    let in_end_loc_with_root_swing = in_root_loc + root_swing * (in_end_loc - in_root_loc);
+    let to_in_end = in_end_loc_with_root_swing - out_mid_loc;
+    let to_out_end = out_end_loc - out_mid_loc;
+    let mid_swing =
+        Quat::from_rotation_arc(to_in_end.normalize(), to_out_end.normalize()) * root_swing;
+
+    // set up output transforms
+    let out_grandparent = Transform {
+        rotation: root_swing * grandparent.rotation,
+        ..grandparent
+    };
+
+    let out_parent = Transform {
+        translation: out_mid_loc,
+        rotation: mid_swing * parent.rotation,
+        ..parent
+    };
+    let out_bone = Transform {
+        translation: out_end_loc,
+        rotation: mid_swing * bone.rotation,
+        ..bone
+    };
+
+    (out_bone, out_parent, out_grandparent)
+}
+
+/// Distance from the root joint to the end joint when the target is `distance` away. Past the
+/// start of the soft range the end joint approaches the full `reach` of the chain exponentially,
+/// instead of reaching it at once.
+fn soft_distance(distance: f32, reach: f32, softness: f32) -> f32 {
+    let soft = softness.clamp(0., 1.) * reach;
+    let hard = reach - soft;
+    if soft <= f32::EPSILON || distance <= hard {
+        distance
+    } else {
+        hard + soft * (1. - (-(distance - hard) / soft).exp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chain with two bones of length ~1, bent slightly towards +Z
+    fn bent_chain() -> (Transform, Transform, Transform) {
+        (
+            Transform::from_xyz(0., 2., 0.),
+            Transform::from_xyz(0., 1., 0.2),
+            Transform::IDENTITY,
+        )
+    }
+
+    fn solve(target: Vec3, pole: Option<Vec3>, softness: f32) -> (Vec3, Vec3, Vec3) {
+        let (bone, parent, grandparent) = bent_chain();
+        let (bone, parent, grandparent) =
+            two_bone_ik(bone, parent, grandparent, target, pole, softness);
+        (
+            bone.translation,
+            parent.translation,
+            grandparent.translation,
+        )
+    }
+
+    #[test]
+    fn test_reaches_the_target() {
+        let target = Vec3::new(1., 1., 0.);
+        let (end, mid, root) = solve(target, None, 0.);
+        let (in_end, in_mid, in_root) = bent_chain();
+        assert!(end.abs_diff_eq(target, 1e-4));
+        assert!(
+            (mid.distance(root) - in_mid.translation.distance(in_root.translation)).abs() < 1e-4
+        );
+        assert!((end.distance(mid) - in_end.translation.distance(in_mid.translation)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_middle_joint_bends_towards_the_pole() {
+        let target = Vec3::new(1., 1., 0.);
+        for pole_side in [1., -1.] {
+            let (_, mid, root) = solve(target, Some(Vec3::new(0., 0., 5. * pole_side)), 0.);
+            let bend = (mid - root).reject_from(target - root);
+            assert!(bend.dot(Vec3::Z) * pole_side > 0.1);
+        }
+    }
+
+    #[test]
+    fn test_soft_limit() {
+        let reach = 2. * Vec3::new(0., 1., 0.2).length();
+        let softness = 0.1;
+        let distance = |target: f32| solve(Vec3::Y * target, None, softness).0.length();
+
+        // Inside the hard range the target is reached
+        assert!((distance(1.5) - 1.5).abs() < 1e-4);
+        // The chain keeps straightening past the point where the target is out of reach
+        assert!(distance(reach) < reach - MAX_LEN_OFFSET);
+        assert!(distance(reach) < distance(reach + 0.5));
+        assert!(distance(reach + 0.5) <= reach - MAX_LEN_OFFSET + 1e-4);
+    }
+}
@@ -9,20 +9,13 @@ pub struct DifferenceInterpolator {
 
 impl DifferenceInterpolator {
     pub fn interpolate_pose(&self, base: &mut Pose, overlay: &Pose) {
-        for (bone_id, bone_index) in overlay.paths.iter() {
-            if self.bone_mask.bone_weight(bone_id) == 0. {
-                continue;
+        base.zip_bones_mut(overlay, |base, index, overlay_index, bone_id| {
+            if self.bone_mask.bone_weight(&bone_id) == 0. {
+                return;
             }
 
-            let overlay_bone_pose = &overlay.bones[*bone_index];
-
-            if let Some(base_index) = base.paths.get(bone_id) {
-                let base_bone_pose = &mut base.bones[*base_index];
-                base_bone_pose.difference_mut(overlay_bone_pose);
-            } else {
-                base.add_bone(overlay_bone_pose.clone(), *bone_id);
-            }
-        }
+            base.difference_bone(index, overlay, overlay_index);
+        });
 
         // Compute difference of root motion deltas independently of bone mask
         base.root_motion = match (&base.root_motion, &overlay.root_motion) {
@@ -0,0 +1,418 @@
+//! Inertialization, as described in "Inertialization: High-Performance Animation Transitions in
+//! Gears of War" (David Bollo, GDC 2018).
+//!
+//! Instead of evaluating both the source and the target animation for the whole transition, we
+//! switch to the target right away and add the offset between the last source pose and the target
+//! pose on top of it. The offset then decays to zero over the duration of the transition, starting
+//! with the velocity the source animation had.
+
+use std::sync::Arc;
+
+use bevy::{
+    math::{Quat, Vec3},
+    reflect::Reflect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::pose::{BoneId, Pose, PoseLayout};
+
+/// Offsets smaller than this are not worth inertializing
+const MIN_OFFSET: f32 = 1e-5;
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InertializationFalloff {
+    /// Quintic polynomial that reaches zero offset, velocity and acceleration exactly at the end
+    /// of the transition.
+    #[default]
+    Polynomial,
+    /// Critically damped spring. The offset decays exponentially and whatever is left at the end
+    /// of the transition (a fraction of a percent) is dropped.
+    Spring,
+}
+
+impl InertializationFalloff {
+    /// Value at time `t` of a one-dimensional offset starting at `x0` with velocity `v0`, decaying
+    /// to zero at `duration`.
+    pub fn sample(&self, x0: f32, v0: f32, t: f32, duration: f32) -> f32 {
+        if t >= duration || duration <= 0. {
+            return 0.;
+        }
+
+        match self {
+            InertializationFalloff::Polynomial => {
+                // Moving away from the target would make the polynomial overshoot
+                let v0 = v0.min(0.);
+                // Shorten the transition if the initial velocity would otherwise make the
+                // offset cross zero
+                let t1 = if v0 < 0. {
+                    duration.min(-5. * x0 / v0)
+                } else {
+                    duration
+                };
+                if t >= t1 {
+                    return 0.;
+                }
+
+                let a0 = ((-8. * v0 * t1 - 20. * x0) / t1.powi(2)).max(0.);
+                let a = -(a0 * t1.powi(2) + 6. * v0 * t1 + 12. * x0) / (2. * t1.powi(5));
+                let b = (3. * a0 * t1.powi(2) + 16. * v0 * t1 + 30. * x0) / (2. * t1.powi(4));
+                let c = -(3. * a0 * t1.powi(2) + 12. * v0 * t1 + 20. * x0) / (2. * t1.powi(3));
+
+                a * t.powi(5) + b * t.powi(4) + c * t.powi(3) + 0.5 * a0 * t.powi(2) + v0 * t + x0
+            }
+            InertializationFalloff::Spring => {
+                // Half-life of a sixth of the duration leaves ~0.2% of the offset at the end
+                let y = 12. * std::f32::consts::LN_2 / duration;
+                (x0 + (v0 + x0 * y) * t) * (-y * t).exp()
+            }
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InertializationSettings {
+    /// Time it takes for the offset to fully decay, in seconds
+    pub duration: f32,
+    pub falloff: InertializationFalloff,
+}
+
+impl Default for InertializationSettings {
+    fn default() -> Self {
+        Self {
+            duration: 0.2,
+            falloff: InertializationFalloff::default(),
+        }
+    }
+}
+
+/// Vector offset that decays along its own direction.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+struct DecayingOffset {
+    direction: Vec3,
+    x0: f32,
+    v0: f32,
+}
+
+impl DecayingOffset {
+    fn new(offset: Vec3, velocity: Vec3) -> Option<Self> {
+        let x0 = offset.length();
+        if x0 < MIN_OFFSET {
+            return None;
+        }
+        let direction = offset / x0;
+
+        Some(Self {
+            direction,
+            x0,
+            // Only the velocity component along the offset matters for the decay
+            v0: velocity.dot(direction),
+        })
+    }
+
+    fn sample(&self, settings: &InertializationSettings, t: f32) -> Vec3 {
+        self.direction
+            * settings
+                .falloff
+                .sample(self.x0, self.v0, t, settings.duration)
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+struct BoneOffset {
+    translation: Option<DecayingOffset>,
+    /// Rotation offset as a scaled axis
+    rotation: Option<DecayingOffset>,
+}
+
+/// An inertialized transition in progress.
+///
+/// Only translation and rotation are inertialized, scale and morph weights snap to the target.
+#[derive(Reflect, Clone, Debug, Default)]
+pub struct PoseInertialization {
+    /// Layout of the target pose, which the offset bone indices refer to
+    layout: Arc<PoseLayout>,
+    offsets: Vec<(usize, BoneOffset)>,
+    settings: InertializationSettings,
+    elapsed: f32,
+}
+
+impl PoseInertialization {
+    /// Captures the offsets between `source`, the last pose output before the transition, and
+    /// `target`, the first pose of the new animation.
+    ///
+    /// `prev_source` is the pose output before `source`, `dt` seconds earlier. It is used to
+    /// estimate bone velocities; without it the offsets decay starting from rest.
+    pub fn new(
+        prev_source: Option<&Pose>,
+        source: &Pose,
+        target: &Pose,
+        dt: f32,
+        settings: InertializationSettings,
+    ) -> Self {
+        let mut offsets = Vec::new();
+
+        for (target_index, bone_id) in target.iter_bones() {
+            let Some(source_index) = matching_index(source, target, target_index, bone_id) else {
+                continue;
+            };
+            let prev = prev_source.filter(|_| dt > 0.).and_then(|prev| {
+                matching_index(prev, target, target_index, bone_id).map(|index| (prev, index))
+            });
+
+            let translation = source
+                .translation(source_index)
+                .zip(target.translation(target_index))
+                .and_then(|(source_t, target_t)| {
+                    let velocity = prev
+                        .and_then(|(prev, index)| prev.translation(index))
+                        .map_or(Vec3::ZERO, |prev_t| (source_t - prev_t) / dt);
+                    DecayingOffset::new(source_t - target_t, velocity)
+                });
+
+            let rotation = source
+                .rotation(source_index)
+                .zip(target.rotation(target_index))
+                .and_then(|(source_r, target_r)| {
+                    let velocity = prev
+                        .and_then(|(prev, index)| prev.rotation(index))
+                        .map_or(Vec3::ZERO, |prev_r| {
+                            shortest(source_r * prev_r.inverse()).to_scaled_axis() / dt
+                        });
+                    DecayingOffset::new(
+                        shortest(source_r * target_r.inverse()).to_scaled_axis(),
+                        velocity,
+                    )
+                });
+
+            if translation.is_some() || rotation.is_some() {
+                offsets.push((
+                    target_index,
+                    BoneOffset {
+                        translation,
+                        rotation,
+                    },
+                ));
+            }
+        }
+
+        Self {
+            layout: target.layout().clone(),
+            offsets,
+            settings,
+            elapsed: 0.,
+        }
+    }
+
+    /// Adds the current (decayed) offsets on top of the given pose.
+    pub fn apply(&self, pose: &mut Pose) {
+        let same_layout = Arc::ptr_eq(&self.layout, pose.layout());
+
+        for (index, offset) in self.offsets.iter() {
+            let index = if same_layout {
+                *index
+            } else {
+                let Some(index) = self
+                    .layout
+                    .bone_id(*index)
+                    .and_then(|bone_id| pose.bone_index(&bone_id))
+                else {
+                    continue;
+                };
+                index
+            };
+
+            if let Some(translation) = pose.translation(index)
+                && let Some(offset) = &offset.translation
+            {
+                pose.set_translation(
+                    index,
+                    translation + offset.sample(&self.settings, self.elapsed),
+                );
+            }
+
+            if let Some(rotation) = pose.rotation(index)
+                && let Some(offset) = &offset.rotation
+            {
+                pose.set_rotation(
+                    index,
+                    (Quat::from_scaled_axis(offset.sample(&self.settings, self.elapsed))
+                        * rotation)
+                        .normalize(),
+                );
+            }
+        }
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.elapsed += dt.max(0.);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.settings.duration || self.offsets.is_empty()
+    }
+}
+
+/// The last pose outputs, kept around so that an inertialized transition can be started at any
+/// time.
+#[derive(Reflect, Clone, Debug, Default)]
+pub struct PoseHistory {
+    pub last: Option<Pose>,
+    pub previous: Option<Pose>,
+    /// Time elapsed between `previous` and `last`
+    pub delta: f32,
+}
+
+impl PoseHistory {
+    /// Records a pose, returning the one that no longer needs to be kept so that its buffers can
+    /// be reused
+    pub fn push(&mut self, pose: Pose, delta: f32) -> Option<Pose> {
+        let dropped = std::mem::replace(&mut self.previous, self.last.replace(pose));
+        self.delta = delta;
+        dropped
+    }
+
+    /// The recorded poses, to reuse their buffers once the history is no longer needed
+    pub fn into_poses(self) -> impl Iterator<Item = Pose> {
+        self.last.into_iter().chain(self.previous)
+    }
+
+    /// Starts an inertialized transition from the last recorded pose to `target`, if there is a
+    /// recorded pose.
+    pub fn inertialize(
+        &self,
+        target: &Pose,
+        settings: InertializationSettings,
+    ) -> Option<PoseInertialization> {
+        let last = self.last.as_ref()?;
+        Some(PoseInertialization::new(
+            self.previous.as_ref(),
+            last,
+            target,
+            self.delta,
+            settings,
+        ))
+    }
+}
+
+/// Index in `pose` of the bone at `target_index` in `target`
+fn matching_index(
+    pose: &Pose,
+    target: &Pose,
+    target_index: usize,
+    bone_id: BoneId,
+) -> Option<usize> {
+    if Arc::ptr_eq(pose.layout(), target.layout()) {
+        Some(target_index)
+    } else {
+        pose.bone_index(&bone_id)
+    }
+}
+
+fn shortest(quat: Quat) -> Quat {
+    if quat.w < 0. { -quat } else { quat }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pose::BonePose;
+
+    const SETTINGS: InertializationSettings = InertializationSettings {
+        duration: 0.5,
+        falloff: InertializationFalloff::Polynomial,
+    };
+
+    fn make_pose(translation: Vec3, rotation: Quat) -> Pose {
+        let mut pose = Pose::default();
+        pose.add_bone(
+            BonePose {
+                translation: Some(translation),
+                rotation: Some(rotation),
+                ..Default::default()
+            },
+            BoneId::default(),
+        );
+        pose
+    }
+
+    fn bone(pose: &Pose) -> BonePose {
+        pose.get_bone(BoneId::default()).unwrap()
+    }
+
+    #[test]
+    fn test_falloffs_start_at_offset_and_end_at_zero() {
+        for falloff in [
+            InertializationFalloff::Polynomial,
+            InertializationFalloff::Spring,
+        ] {
+            assert!((falloff.sample(2., 0., 0., 1.) - 2.).abs() < 1e-5);
+            assert!(falloff.sample(2., 0., 0.999, 1.).abs() < 1e-2);
+            assert_eq!(falloff.sample(2., 0., 1., 1.), 0.);
+        }
+    }
+
+    #[test]
+    fn test_polynomial_matches_initial_velocity() {
+        let falloff = InertializationFalloff::Polynomial;
+        let h = 1e-3;
+        let velocity = (falloff.sample(1., -2., h, 1.) - falloff.sample(1., -2., 0., 1.)) / h;
+        assert!((velocity + 2.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_polynomial_does_not_overshoot() {
+        let falloff = InertializationFalloff::Polynomial;
+        for i in 0..=100 {
+            let t = i as f32 / 100.;
+            assert!(falloff.sample(1., -20., t, 1.) >= -1e-5);
+        }
+    }
+
+    #[test]
+    fn test_inertialization_starts_at_source_and_ends_at_target() {
+        let source = make_pose(Vec3::X, Quat::from_rotation_y(1.));
+        let target = make_pose(Vec3::ZERO, Quat::IDENTITY);
+
+        let mut inertialization = PoseInertialization::new(None, &source, &target, 0., SETTINGS);
+
+        let mut pose = target.clone();
+        inertialization.apply(&mut pose);
+        assert!(bone(&pose).translation.unwrap().abs_diff_eq(Vec3::X, 1e-5));
+        assert!(
+            bone(&pose)
+                .rotation
+                .unwrap()
+                .abs_diff_eq(Quat::from_rotation_y(1.), 1e-5)
+        );
+
+        inertialization.advance(0.5);
+        assert!(inertialization.is_finished());
+
+        let mut pose = target.clone();
+        inertialization.apply(&mut pose);
+        assert!(
+            bone(&pose)
+                .translation
+                .unwrap()
+                .abs_diff_eq(Vec3::ZERO, 1e-5)
+        );
+        assert!(
+            bone(&pose)
+                .rotation
+                .unwrap()
+                .abs_diff_eq(Quat::IDENTITY, 1e-5)
+        );
+    }
+
+    #[test]
+    fn test_pose_history_requires_a_recorded_pose() {
+        let target = make_pose(Vec3::ZERO, Quat::IDENTITY);
+        let mut history = PoseHistory::default();
+        assert!(history.inertialize(&target, SETTINGS).is_none());
+
+        history.push(make_pose(Vec3::X, Quat::IDENTITY), 0.1);
+        history.push(make_pose(Vec3::Y, Quat::IDENTITY), 0.1);
+        assert!(history.previous.is_some());
+        assert!(history.inertialize(&target, SETTINGS).is_some());
+    }
+}
@@ -22,22 +22,15 @@ pub struct LinearInterpolator {
 
 impl LinearInterpolator {
     pub fn interpolate_pose(&self, base: &mut Pose, overlay: &Pose, f: f32) {
-        for (bone_id, bone_index) in overlay.paths.iter() {
-            let bone_weight = self.bone_mask.bone_weight(bone_id);
+        base.zip_bones_mut(overlay, |base, index, overlay_index, bone_id| {
+            let bone_weight = self.bone_mask.bone_weight(&bone_id);
             if bone_weight == 0. {
-                continue;
+                return;
             }
 
             let scaled_f = f * bone_weight;
-            let overlay_bone_pose = &overlay.bones[*bone_index];
-
-            if let Some(base_index) = base.paths.get(bone_id) {
-                let base_bone_pose = &mut base.bones[*base_index];
-                base_bone_pose.linear_blend_mut(overlay_bone_pose, scaled_f);
-            } else {
-                base.add_bone(overlay_bone_pose.clone(), *bone_id);
-            }
-        }
+            base.linear_blend_bone(index, overlay, overlay_index, scaled_f);
+        });
 
         // Blend root motion independently of bone mask
         base.root_motion = match (&base.root_motion, &overlay.root_motion) {
@@ -1,4 +1,5 @@
 pub mod additive;
 pub mod difference;
+pub mod inertialization;
 pub mod linear;
 pub mod step;
@@ -83,28 +83,14 @@ impl InterpolateStep for BonePose {
 
 impl InterpolateStep for Pose {
     fn interpolate_step(&self, other: &Self, f: f32) -> Self {
-        let mut result = Pose::default();
-
-        for (path, bone_id) in self.paths.iter() {
-            if let Some(other_bone_id) = other.paths.get(path) {
-                result.add_bone(
-                    self.bones[*bone_id].interpolate_step(&other.bones[*other_bone_id], f),
-                    *path,
-                );
-            } else {
-                result.add_bone(self.bones[*bone_id].clone(), *path);
-            }
-        }
+        let mut result = self.clone();
 
-        for (path, bone_id) in other.paths.iter() {
-            if self.paths.contains_key(path) {
-                continue;
-            }
-            result.add_bone(other.bones[*bone_id].clone(), *path);
-        }
+        // Channels present in both poses keep their value, like in the `Vec3` and `Quat`
+        // implementations. Channels missing from `self` are taken from `other`.
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.mix_bone(index, other, other_index, |a, _| a, |a, _| a, |a, _| a);
+        });
 
-        result.timestamp = self.timestamp;
-        result.skeleton = self.skeleton.clone();
         // Step interpolation: pick one side's root motion based on threshold
         result.root_motion = if f < 0.5 {
             self.root_motion.clone()
@@ -3,21 +3,30 @@ pub mod animation_clip;
 pub mod animation_graph;
 pub mod animation_graph_player;
 pub mod animation_node;
+pub mod bake;
+pub mod bvh;
 pub mod context;
+pub mod deterministic;
 pub mod duration_data;
 pub mod edge_data;
 pub mod errors;
 pub mod event_track;
+pub mod headless;
 pub mod id;
+pub mod ik;
 pub mod interpolation;
+pub mod lod;
 #[cfg(feature = "physics_avian")]
 pub mod physics_systems_avian;
 pub mod pin_map;
 pub mod plugin;
 pub mod pose;
 pub mod ragdoll;
+pub mod shared_evaluation;
 pub mod skeleton;
+pub mod snapshot;
 pub mod space_conversion;
+pub mod spring;
 pub mod state_machine;
 pub mod symmetry;
 pub mod systems;
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use bevy::{
+    camera::Camera, ecs::prelude::*, reflect::prelude::*, transform::components::GlobalTransform,
+};
+
+use crate::{
+    edge_data::bone_mask::BoneMask,
+    interpolation::linear::LinearInterpolator,
+    pose::{BoneId, Pose},
+    skeleton::Skeleton,
+};
+
+/// Which bones of the skeleton are animated at a given LOD level. Bones that are left out keep
+/// the transform they had when they were last animated.
+#[derive(Reflect, Clone, Debug, Default, PartialEq)]
+pub enum LodBones {
+    #[default]
+    All,
+    /// Only bones at most this many levels below the skeleton root
+    MaxDepth(usize),
+    /// Only bones with a non-zero weight in the mask
+    Mask(BoneMask),
+}
+
+impl LodBones {
+    fn retain(&self, pose: &mut Pose, skeleton: &Skeleton) {
+        match self {
+            LodBones::All => {}
+            LodBones::MaxDepth(max_depth) => {
+                pose.retain_bones(|bone_id| bone_depth(skeleton, bone_id) <= *max_depth)
+            }
+            LodBones::Mask(mask) => pose.retain_bones(|bone_id| mask.bone_weight(&bone_id) > 0.),
+        }
+    }
+}
+
+fn bone_depth(skeleton: &Skeleton, mut bone_id: BoneId) -> usize {
+    let mut depth = 0;
+    while let Some(parent) = skeleton.parent(&bone_id) {
+        bone_id = parent;
+        depth += 1;
+    }
+    depth
+}
+
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct AnimationLodLevel {
+    /// The graph is evaluated once every this many frames. Time elapsed during skipped frames is
+    /// accumulated and applied on the next evaluation.
+    pub update_interval: u32,
+    /// If enabled, skipped frames show a pose interpolated between the last two evaluated poses
+    /// instead of holding the last one. This delays the animation by `update_interval` frames.
+    pub interpolate: bool,
+    pub bones: LodBones,
+}
+
+impl Default for AnimationLodLevel {
+    fn default() -> Self {
+        Self {
+            update_interval: 1,
+            interpolate: false,
+            bones: LodBones::All,
+        }
+    }
+}
+
+/// Input for [`AnimationLodSelector::Custom`]
+pub struct AnimationLodQuery {
+    /// Entity with the [`AnimationGraphPlayer`](crate::animation_graph_player::AnimationGraphPlayer)
+    pub entity: Entity,
+    /// Distance from the player to the closest active camera, if there is one
+    pub camera_distance: Option<f32>,
+}
+
+#[derive(Clone)]
+pub enum AnimationLodSelector {
+    /// Level `i` is used for players up to `distances[i]` away from the closest active camera.
+    /// Players further away than all distances use the next level.
+    CameraDistance(Vec<f32>),
+    /// Returns the level to use for a player.
+    Custom(Arc<dyn Fn(&AnimationLodQuery) -> usize + Send + Sync>),
+    /// Levels are set manually through [`AnimationLod::level`].
+    Manual,
+}
+
+/// Global configuration of animation LOD. Only applies to players with an [`AnimationLod`]
+/// component.
+#[derive(Resource, Clone)]
+pub struct AnimationLodSettings {
+    pub levels: Vec<AnimationLodLevel>,
+    pub selector: AnimationLodSelector,
+}
+
+impl Default for AnimationLodSettings {
+    fn default() -> Self {
+        Self {
+            levels: vec![
+                AnimationLodLevel::default(),
+                AnimationLodLevel {
+                    update_interval: 2,
+                    interpolate: true,
+                    bones: LodBones::All,
+                },
+                AnimationLodLevel {
+                    update_interval: 4,
+                    interpolate: true,
+                    bones: LodBones::MaxDepth(4),
+                },
+            ],
+            selector: AnimationLodSelector::CameraDistance(vec![15., 40.]),
+        }
+    }
+}
+
+impl AnimationLodSettings {
+    pub fn level(&self, index: usize) -> Option<&AnimationLodLevel> {
+        self.levels
+            .get(index.min(self.levels.len().saturating_sub(1)))
+    }
+}
+
+/// Enables level of detail for the [`AnimationGraphPlayer`] on the same entity, as configured in
+/// the [`AnimationLodSettings`] resource.
+///
+/// [`AnimationGraphPlayer`]: crate::animation_graph_player::AnimationGraphPlayer
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct AnimationLod {
+    /// Index of the level in [`AnimationLodSettings::levels`]. Updated every frame unless the
+    /// selector is [`AnimationLodSelector::Manual`].
+    pub level: usize,
+    /// `None` until the graph is evaluated for the first time
+    frames_since_update: Option<u32>,
+    /// Last two evaluated poses, used for interpolation
+    poses: Option<(Pose, Pose)>,
+}
+
+impl AnimationLod {
+    pub fn new(level: usize) -> Self {
+        Self {
+            level,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the graph should be evaluated this frame.
+    pub(crate) fn should_update(&mut self, level: &AnimationLodLevel) -> bool {
+        match &mut self.frames_since_update {
+            Some(frames) if *frames + 1 < level.update_interval => {
+                *frames += 1;
+                false
+            }
+            _ => {
+                self.frames_since_update = Some(0);
+                true
+            }
+        }
+    }
+
+    /// Pose to show in a frame in which the graph is not evaluated, if it should differ from the
+    /// last output.
+    pub(crate) fn interpolated_pose(&self, level: &AnimationLodLevel) -> Option<Pose> {
+        if !level.interpolate {
+            return None;
+        }
+        let (from, to) = self.poses.as_ref()?;
+        let frames = self.frames_since_update?;
+
+        let mut pose = from.clone();
+        LinearInterpolator {
+            bone_mask: BoneMask::all(),
+        }
+        .interpolate_pose(
+            &mut pose,
+            to,
+            frames as f32 / level.update_interval.max(1) as f32,
+        );
+        Some(pose)
+    }
+
+    /// Forgets the evaluated poses, so that interpolation starts over from the next one.
+    pub(crate) fn reset_interpolation(&mut self) {
+        self.poses = None;
+    }
+
+    /// Post-processes a freshly evaluated pose according to the current level.
+    pub(crate) fn process_evaluated(
+        &mut self,
+        pose: &mut Pose,
+        level: &AnimationLodLevel,
+        skeleton: Option<&Skeleton>,
+    ) {
+        if let Some(skeleton) = skeleton {
+            level.bones.retain(pose, skeleton);
+        }
+
+        if !level.interpolate {
+            self.poses = None;
+            return;
+        }
+
+        // Root motion is not interpolated, it is reported as soon as it is evaluated
+        let root_motion = pose.root_motion.take();
+        let from = match self.poses.take() {
+            Some((_, to)) => to,
+            None => pose.clone(),
+        };
+        self.poses = Some((from.clone(), pose.clone()));
+        *pose = from;
+        pose.root_motion = root_motion;
+    }
+}
+
+/// System that chooses the LOD level of every player with an [`AnimationLod`] component,
+/// according to the [`AnimationLodSettings`] resource.
+pub fn select_animation_lod(
+    settings: Res<AnimationLodSettings>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut players: Query<(Entity, &GlobalTransform, &mut AnimationLod)>,
+) {
+    if matches!(settings.selector, AnimationLodSelector::Manual) {
+        return;
+    }
+
+    for (entity, transform, mut lod) in &mut players {
+        let camera_distance = cameras
+            .iter()
+            .filter(|(camera, _)| camera.is_active)
+            .map(|(_, camera_transform)| {
+                camera_transform
+                    .translation()
+                    .distance(transform.translation())
+            })
+            .min_by(f32::total_cmp);
+
+        let level = match &settings.selector {
+            AnimationLodSelector::CameraDistance(distances) => camera_distance
+                .map_or(0, |distance| {
+                    distances.iter().filter(|max| distance > **max).count()
+                }),
+            AnimationLodSelector::Custom(selector) => selector(&AnimationLodQuery {
+                entity,
+                camera_distance,
+            }),
+            AnimationLodSelector::Manual => unreachable!(),
+        };
+
+        // Avoid triggering change detection every frame
+        if lod.level != level {
+            lod.level = level;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::pose::BonePose;
+
+    fn pose_at(x: f32) -> Pose {
+        let mut pose = Pose::default();
+        pose.add_bone(
+            BonePose {
+                translation: Some(Vec3::new(x, 0., 0.)),
+                ..Default::default()
+            },
+            BoneId::default(),
+        );
+        pose
+    }
+
+    fn translation(pose: &Pose) -> f32 {
+        pose.get_bone(BoneId::default())
+            .unwrap()
+            .translation
+            .unwrap()
+            .x
+    }
+
+    #[test]
+    fn test_lod_updates_every_interval() {
+        let level = AnimationLodLevel {
+            update_interval: 3,
+            ..Default::default()
+        };
+        let mut lod = AnimationLod::default();
+
+        let updates: Vec<bool> = (0..7).map(|_| lod.should_update(&level)).collect();
+        assert_eq!(updates, [true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_lod_interpolates_between_evaluated_poses() {
+        let level = AnimationLodLevel {
+            update_interval: 2,
+            interpolate: true,
+            ..Default::default()
+        };
+        let mut lod = AnimationLod::default();
+
+        assert!(lod.should_update(&level));
+        let mut pose = pose_at(0.);
+        lod.process_evaluated(&mut pose, &level, None);
+        assert_eq!(translation(&pose), 0.);
+
+        assert!(!lod.should_update(&level));
+        assert!(lod.should_update(&level));
+        let mut pose = pose_at(2.);
+        lod.process_evaluated(&mut pose, &level, None);
+        // The evaluated pose is shown one interval later
+        assert_eq!(translation(&pose), 0.);
+
+        assert!(!lod.should_update(&level));
+        let pose = lod.interpolated_pose(&level).unwrap();
+        assert_eq!(translation(&pose), 1.);
+    }
+}
@@ -7,7 +7,8 @@ use bevy::{
         intern::Interned,
         schedule::{IntoScheduleConfigs, ScheduleLabel, SystemSet},
     },
-    reflect::prelude::ReflectDefault,
+    platform::sync::Arc,
+    reflect::{ReflectDeserialize, ReflectSerialize, prelude::ReflectDefault},
     transform::TransformSystems,
 };
 
@@ -19,29 +20,39 @@ use crate::physics_systems_avian::{
 };
 use crate::{
     animated_scene::{
-        AnimatedScene, loader::AnimatedSceneLoader, locate_animated_scene_player,
-        spawn_animated_scenes,
+        AnimatedScene, AnimationVisibilityCulling, loader::AnimatedSceneLoader,
+        locate_animated_scene_player, spawn_animated_scenes, update_animation_visibility_culling,
     },
     animation_clip::{EntityPath, GraphClip, Interpolation, loader::GraphClipLoader},
     animation_graph::{AnimationGraph, loader::AnimationGraphLoader},
     animation_graph_player::AnimationGraphPlayer,
     animation_node::AnimationNode,
+    bvh::loader::BvhLoader,
+    deterministic::{DeterministicEvaluation, check_deterministic_schedule, not_deterministic},
     edge_data::{
         DataSpec, DataValue,
         bone_mask::BoneMask,
-        events::{AnimationEvent, EventQueue, SampledEvent},
+        events::{AnimationEvent, EventPayload, EventQueue, SampledEvent},
     },
-    pose::{Pose, RootMotionDelta, RootMotionMode},
+    lod::{AnimationLod, AnimationLodLevel, AnimationLodSettings, LodBones, select_animation_lod},
+    pose::{Pose, PoseLayout, RootMotionDelta, RootMotionMode},
     ragdoll::{
         bone_mapping::RagdollBoneMap, bone_mapping_loader::RagdollBoneMapLoader,
         definition::Ragdoll, definition_loader::RagdollLoader,
     },
+    shared_evaluation::{
+        SharedEvaluation, SharedEvaluationSettings, SharedEvaluations, update_shared_evaluations,
+    },
     skeleton::{Skeleton, loader::SkeletonLoader},
-    state_machine::high_level::{StateMachine, loader::StateMachineLoader},
+    state_machine::{
+        high_level::{StateMachine, loader::StateMachineLoader},
+        low_level::FsmState,
+    },
     symmetry::{config::SymmetryConfig, serial::SymmetryConfigSerial},
     systems::{
-        RootMotionOutput, animation_player, animation_player_deferred_gizmos,
-        apply_animation_to_targets, extract_root_motion,
+        AnimationEventPhase, AnimationGraphEvent, ForwardAnimationEvents, RootMotionOutput,
+        animation_player, animation_player_deferred_gizmos, apply_animation_to_targets,
+        extract_root_motion, forward_animation_events,
     },
 };
 
@@ -49,6 +60,9 @@ use crate::{
 pub struct AnimationGraphCorePlugin {
     pub physics_schedule: Interned<dyn ScheduleLabel>,
     pub final_schedule: Interned<dyn ScheduleLabel>,
+    /// Settings of the deterministic evaluation mode, if enabled. See
+    /// [`AnimationGraphCorePlugin::with_deterministic_evaluation`].
+    pub deterministic: Option<DeterministicEvaluation>,
 }
 
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, SystemSet)]
@@ -96,6 +110,18 @@ impl Plugin for AnimationGraphCorePlugin {
             self.register_physics_types(app);
         }
 
+        app.add_message::<AnimationGraphEvent>();
+        app.init_resource::<AnimationLodSettings>();
+        app.init_resource::<SharedEvaluationSettings>();
+        app.init_resource::<SharedEvaluations>();
+        if let Some(deterministic) = &self.deterministic {
+            app.insert_resource(deterministic.clone());
+            app.add_systems(
+                self.physics_schedule,
+                check_deterministic_schedule.in_set(AnimationGraphSet::PrePhysics),
+            );
+        }
+
         app.add_systems(PreUpdate, spawn_animated_scenes);
 
         app.add_systems(
@@ -103,7 +129,11 @@ impl Plugin for AnimationGraphCorePlugin {
             (
                 #[cfg(feature = "physics_avian")]
                 spawn_missing_ragdolls_avian,
+                update_animation_visibility_culling.run_if(not_deterministic),
+                select_animation_lod.run_if(not_deterministic),
+                update_shared_evaluations,
                 animation_player,
+                forward_animation_events,
                 extract_root_motion,
                 #[cfg(feature = "physics_avian")]
                 update_ragdoll_rigidbodies,
@@ -138,10 +168,30 @@ impl Plugin for AnimationGraphCorePlugin {
 }
 
 impl AnimationGraphCorePlugin {
+    pub fn new(physics_schedule: impl ScheduleLabel, final_schedule: impl ScheduleLabel) -> Self {
+        Self {
+            physics_schedule: physics_schedule.intern(),
+            final_schedule: final_schedule.intern(),
+            deterministic: None,
+        }
+    }
+
+    /// Enables the deterministic evaluation mode, for lockstep and rollback networking. See
+    /// [`DeterministicEvaluation`] for what it guarantees.
+    ///
+    /// The physics schedule should be a fixed timestep schedule, and the timestep of
+    /// [`Time<Fixed>`](bevy::time::Fixed) should match the one of the settings. A warning is
+    /// logged otherwise.
+    pub fn with_deterministic_evaluation(mut self, settings: DeterministicEvaluation) -> Self {
+        self.deterministic = Some(settings);
+        self
+    }
+
     /// Registers asset types and their loaders
     fn register_assets(&self, app: &mut App) {
         app.init_asset::<GraphClip>()
             .init_asset_loader::<GraphClipLoader>()
+            .init_asset_loader::<BvhLoader>()
             .register_asset_reflect::<GraphClip>();
         app.init_asset::<AnimationGraph>()
             .init_asset_loader::<AnimationGraphLoader>()
@@ -171,9 +221,14 @@ impl AnimationGraphCorePlugin {
             .register_type::<EntityPath>()
             .register_type::<BoneMask>()
             .register_type::<Pose>()
+            // Layouts are serialized along with poses in snapshots
+            .register_type_data::<Arc<PoseLayout>, ReflectSerialize>()
+            .register_type_data::<Arc<PoseLayout>, ReflectDeserialize>()
+            .register_type::<FsmState>()
             .register_type::<AnimationEvent>()
             .register_type::<SampledEvent>()
             .register_type::<EventQueue>()
+            .register_type::<EventPayload>()
             .register_type::<AnimationEvent>()
             .register_type::<SampledEvent>()
             .register_type::<DataValue>()
@@ -184,6 +239,15 @@ impl AnimationGraphCorePlugin {
             .register_type::<RootMotionDelta>()
             .register_type::<RootMotionMode>()
             .register_type::<RootMotionOutput>()
+            .register_type::<ForwardAnimationEvents>()
+            .register_type::<AnimationGraphEvent>()
+            .register_type::<AnimationEventPhase>()
+            .register_type::<AnimationVisibilityCulling>()
+            .register_type::<AnimationLod>()
+            .register_type::<AnimationLodLevel>()
+            .register_type::<LodBones>()
+            .register_type::<SharedEvaluation>()
+            .register_type::<DeterministicEvaluation>()
             .register_type::<()>()
             .register_type_data::<(), ReflectDefault>();
     }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bevy::{
     asset::prelude::*, math::prelude::*, platform::collections::HashMap, reflect::prelude::*,
     transform::prelude::*,
@@ -224,14 +226,136 @@ impl BonePose {
     }
 }
 
+/// Order of the bones in a [`Pose`], usually the bone order of its [`Skeleton`].
+///
+/// Poses that share a layout can be combined bone by bone without looking up bone ids.
+#[derive(TypePath, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<BoneId>", into = "Vec<BoneId>")]
+pub struct PoseLayout {
+    bones: Vec<BoneId>,
+    indices: HashMap<BoneId, usize>,
+}
+
+impl From<Vec<BoneId>> for PoseLayout {
+    fn from(bones: Vec<BoneId>) -> Self {
+        let mut layout = Self::default();
+        for bone_id in bones {
+            layout.push(bone_id);
+        }
+        layout
+    }
+}
+
+impl From<PoseLayout> for Vec<BoneId> {
+    fn from(layout: PoseLayout) -> Self {
+        layout.bones
+    }
+}
+
+impl PoseLayout {
+    pub fn len(&self) -> usize {
+        self.bones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bones.is_empty()
+    }
+
+    pub fn index(&self, bone_id: &BoneId) -> Option<usize> {
+        self.indices.get(bone_id).copied()
+    }
+
+    pub fn bone_id(&self, index: usize) -> Option<BoneId> {
+        self.bones.get(index).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BoneId> + '_ {
+        self.bones.iter().copied()
+    }
+
+    /// Appends a bone to the layout and returns its index. If the bone is already in the layout
+    /// its current index is returned.
+    pub fn push(&mut self, bone_id: BoneId) -> usize {
+        *self.indices.entry(bone_id).or_insert_with(|| {
+            self.bones.push(bone_id);
+            self.bones.len() - 1
+        })
+    }
+}
+
+/// Set of bone indices in a [`PoseLayout`]
+#[derive(Reflect, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoneSet {
+    blocks: Vec<u64>,
+}
+
+impl BoneSet {
+    pub fn contains(&self, index: usize) -> bool {
+        self.blocks
+            .get(index / 64)
+            .is_some_and(|block| block & (1 << (index % 64)) != 0)
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        let block = index / 64;
+        if block >= self.blocks.len() {
+            self.blocks.resize(block + 1, 0);
+        }
+        self.blocks[block] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if let Some(block) = self.blocks.get_mut(index / 64) {
+            *block &= !(1 << (index % 64));
+        }
+    }
+
+    /// Removes all indices, keeping the allocated blocks
+    pub fn clear(&mut self) {
+        self.blocks.fill(0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|block| *block == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(block_index, block)| {
+                let mut block = *block;
+                std::iter::from_fn(move || {
+                    if block == 0 {
+                        return None;
+                    }
+                    let bit = block.trailing_zeros() as usize;
+                    block &= block - 1;
+                    Some(block_index * 64 + bit)
+                })
+            })
+    }
+}
+
 /// Vertical slice of an [`GraphClip`]
 ///
+/// Bone channels are stored in dense arrays indexed by the bone order of the pose's
+/// [`PoseLayout`], together with a [`BoneSet`] per channel that tracks which bones have a value
+/// for it. Bones that are not in the layout yet are appended to it when they are set.
+///
 /// [`GraphClip`]: crate::prelude::GraphClip
-#[derive(Asset, Reflect, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
-#[reflect(Default)]
+#[derive(Asset, Reflect, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[reflect(Default, Clone)]
 pub struct Pose {
-    pub bones: Vec<BonePose>,
-    pub paths: HashMap<BoneId, usize>,
+    pub(crate) layout: Arc<PoseLayout>,
+    pub(crate) translations: Vec<Vec3>,
+    pub(crate) rotations: Vec<Quat>,
+    pub(crate) scales: Vec<Vec3>,
+    pub(crate) weights: Vec<Vec<f32>>,
+    pub(crate) has_translation: BoneSet,
+    pub(crate) has_rotation: BoneSet,
+    pub(crate) has_scale: BoneSet,
+    pub(crate) has_weights: BoneSet,
     pub timestamp: f32,
     #[serde(skip)]
     pub skeleton: Handle<Skeleton>,
@@ -241,23 +365,364 @@ pub struct Pose {
     pub root_motion: Option<RootMotionDelta>,
 }
 
+impl Clone for Pose {
+    fn clone(&self) -> Self {
+        let mut pose = Pose::default();
+        pose.clone_from(self);
+        pose
+    }
+
+    /// Reuses the buffers of `self`, so that cloning into a pose from a [`PosePool`] does not
+    /// allocate.
+    fn clone_from(&mut self, source: &Self) {
+        self.layout.clone_from(&source.layout);
+        self.translations.clone_from(&source.translations);
+        self.rotations.clone_from(&source.rotations);
+        self.scales.clone_from(&source.scales);
+        self.weights.clone_from(&source.weights);
+        self.has_translation.clone_from(&source.has_translation);
+        self.has_rotation.clone_from(&source.has_rotation);
+        self.has_scale.clone_from(&source.has_scale);
+        self.has_weights.clone_from(&source.has_weights);
+        self.timestamp = source.timestamp;
+        self.skeleton.clone_from(&source.skeleton);
+        self.root_motion.clone_from(&source.root_motion);
+    }
+}
+
 impl Pose {
+    /// Creates an empty pose with the given layout
+    pub fn with_layout(layout: Arc<PoseLayout>) -> Self {
+        let mut pose = Pose::default();
+        pose.reset(&layout);
+        pose
+    }
+
+    /// Creates an empty pose laid out in the bone order of the given skeleton
+    pub fn for_skeleton(handle: Handle<Skeleton>, skeleton: &Skeleton) -> Self {
+        Self {
+            skeleton: handle,
+            ..Self::with_layout(skeleton.pose_layout().clone())
+        }
+    }
+
+    /// Removes all bones and root motion and switches to the given layout, keeping the allocated
+    /// buffers.
+    pub fn reset(&mut self, layout: &Arc<PoseLayout>) {
+        let len = layout.len();
+        self.layout.clone_from(layout);
+
+        self.translations.clear();
+        self.translations.resize(len, Vec3::ZERO);
+        self.rotations.clear();
+        self.rotations.resize(len, Quat::IDENTITY);
+        self.scales.clear();
+        self.scales.resize(len, Vec3::ONE);
+        self.weights.truncate(len);
+        for weights in &mut self.weights {
+            weights.clear();
+        }
+        self.weights.resize_with(len, Vec::new);
+
+        self.has_translation.clear();
+        self.has_rotation.clear();
+        self.has_scale.clear();
+        self.has_weights.clear();
+
+        self.timestamp = 0.;
+        self.root_motion = None;
+    }
+
+    pub fn layout(&self) -> &Arc<PoseLayout> {
+        &self.layout
+    }
+
+    /// Index of the bone in this pose's layout. The bone may not have any values set.
+    pub fn bone_index(&self, bone_id: &BoneId) -> Option<usize> {
+        self.layout.index(bone_id)
+    }
+
+    /// Index of the bone in this pose's layout, appending it to the layout if needed.
+    pub fn insert_bone(&mut self, bone_id: BoneId) -> usize {
+        if let Some(index) = self.layout.index(&bone_id) {
+            return index;
+        }
+
+        let index = Arc::make_mut(&mut self.layout).push(bone_id);
+        self.translations.push(Vec3::ZERO);
+        self.rotations.push(Quat::IDENTITY);
+        self.scales.push(Vec3::ONE);
+        self.weights.push(Vec::new());
+        index
+    }
+
+    /// Whether the bone at the given index has a value for any channel
+    pub fn contains_bone(&self, index: usize) -> bool {
+        self.has_translation.contains(index)
+            || self.has_rotation.contains(index)
+            || self.has_scale.contains(index)
+            || self.has_weights.contains(index)
+    }
+
+    /// Iterates over the index and id of all bones that have a value for any channel
+    pub fn iter_bones(&self) -> impl Iterator<Item = (usize, BoneId)> + '_ {
+        self.layout
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.contains_bone(*index))
+    }
+
+    /// Number of bones that have a value for any channel
+    pub fn bone_count(&self) -> usize {
+        self.iter_bones().count()
+    }
+
+    pub fn translation(&self, index: usize) -> Option<Vec3> {
+        self.has_translation
+            .contains(index)
+            .then(|| self.translations[index])
+    }
+
+    pub fn set_translation(&mut self, index: usize, translation: Vec3) {
+        self.translations[index] = translation;
+        self.has_translation.insert(index);
+    }
+
+    pub fn rotation(&self, index: usize) -> Option<Quat> {
+        self.has_rotation
+            .contains(index)
+            .then(|| self.rotations[index])
+    }
+
+    pub fn set_rotation(&mut self, index: usize, rotation: Quat) {
+        self.rotations[index] = rotation;
+        self.has_rotation.insert(index);
+    }
+
+    pub fn scale(&self, index: usize) -> Option<Vec3> {
+        self.has_scale.contains(index).then(|| self.scales[index])
+    }
+
+    pub fn set_scale(&mut self, index: usize, scale: Vec3) {
+        self.scales[index] = scale;
+        self.has_scale.insert(index);
+    }
+
+    pub fn weights(&self, index: usize) -> Option<&[f32]> {
+        self.has_weights
+            .contains(index)
+            .then(|| self.weights[index].as_slice())
+    }
+
+    pub fn set_weights(&mut self, index: usize, weights: &[f32]) {
+        self.weights[index].clear();
+        self.weights[index].extend_from_slice(weights);
+        self.has_weights.insert(index);
+    }
+
+    /// Transform of the bone at the given index, with missing channels taken from `base`
+    pub fn transform_with_base(&self, index: usize, mut base: Transform) -> Transform {
+        if let Some(translation) = self.translation(index) {
+            base.translation = translation;
+        }
+        if let Some(rotation) = self.rotation(index) {
+            base.rotation = rotation;
+        }
+        if let Some(scale) = self.scale(index) {
+            base.scale = scale;
+        }
+        base
+    }
+
+    /// Clears all channels of the bone at the given index
+    pub fn remove_bone(&mut self, index: usize) {
+        self.translations[index] = Vec3::ZERO;
+        self.rotations[index] = Quat::IDENTITY;
+        self.scales[index] = Vec3::ONE;
+        self.weights[index].clear();
+        self.has_translation.remove(index);
+        self.has_rotation.remove(index);
+        self.has_scale.remove(index);
+        self.has_weights.remove(index);
+    }
+
+    /// Returns a copy of the channels of the bone at the given index, if it has any.
+    pub fn bone(&self, index: usize) -> Option<BonePose> {
+        self.contains_bone(index).then(|| BonePose {
+            translation: self.translation(index),
+            rotation: self.rotation(index),
+            scale: self.scale(index),
+            weights: self.weights(index).map(|weights| weights.to_vec()),
+        })
+    }
+
+    /// Replaces the channels of the bone at the given index
+    pub fn set_bone(&mut self, index: usize, pose: &BonePose) {
+        self.remove_bone(index);
+        if let Some(translation) = pose.translation {
+            self.set_translation(index, translation);
+        }
+        if let Some(rotation) = pose.rotation {
+            self.set_rotation(index, rotation);
+        }
+        if let Some(scale) = pose.scale {
+            self.set_scale(index, scale);
+        }
+        if let Some(weights) = &pose.weights {
+            self.set_weights(index, weights);
+        }
+    }
+
+    pub fn get_bone(&self, bone_id: BoneId) -> Option<BonePose> {
+        self.bone_index(&bone_id).and_then(|index| self.bone(index))
+    }
+
+    /// Sets the channels of the given bone, replacing any previous values
     pub fn add_bone(&mut self, pose: BonePose, bone_id: BoneId) {
-        let id = self.bones.len();
-        self.bones.insert(id, pose);
-        self.paths.insert(bone_id, id);
+        let index = self.insert_bone(bone_id);
+        self.set_bone(index, &pose);
+    }
+
+    /// Removes all bones for which the predicate returns `false`.
+    pub fn retain_bones(&mut self, keep: impl Fn(BoneId) -> bool) {
+        for index in 0..self.layout.len() {
+            if self.contains_bone(index) && !keep(self.layout.bones[index]) {
+                self.remove_bone(index);
+            }
+        }
+    }
+
+    /// Calls `func` with the index in this pose and in `other` of every bone that has values in
+    /// `other`, appending missing bones to this pose's layout.
+    ///
+    /// Poses that share a layout are walked by index, otherwise bones are matched by id.
+    pub fn zip_bones_mut(
+        &mut self,
+        other: &Pose,
+        mut func: impl FnMut(&mut Pose, usize, usize, BoneId),
+    ) {
+        let same_layout =
+            Arc::ptr_eq(&self.layout, &other.layout) || self.layout.bones == other.layout.bones;
+
+        for (other_index, bone_id) in other.iter_bones() {
+            let index = if same_layout {
+                other_index
+            } else {
+                self.insert_bone(bone_id)
+            };
+            func(self, index, other_index, bone_id);
+        }
+    }
+
+    /// Mixes every channel of a bone in `other` into the same channel of a bone in this pose.
+    /// Channels missing from this pose are copied from `other`.
+    pub fn mix_bone(
+        &mut self,
+        index: usize,
+        other: &Pose,
+        other_index: usize,
+        mix_vec: impl Fn(Vec3, Vec3) -> Vec3,
+        mix_quat: impl Fn(Quat, Quat) -> Quat,
+        mix_weight: impl Fn(f32, f32) -> f32,
+    ) {
+        if let Some(b) = other.translation(other_index) {
+            let value = self.translation(index).map_or(b, |a| mix_vec(a, b));
+            self.set_translation(index, value);
+        }
+        if let Some(b) = other.rotation(other_index) {
+            let value = self.rotation(index).map_or(b, |a| mix_quat(a, b));
+            self.set_rotation(index, value);
+        }
+        if let Some(b) = other.scale(other_index) {
+            let value = self.scale(index).map_or(b, |a| mix_vec(a, b));
+            self.set_scale(index, value);
+        }
+        if let Some(b) = other.weights(other_index) {
+            if self.has_weights.contains(index) {
+                let weights = &mut self.weights[index];
+                weights.truncate(b.len());
+                for (a, b) in weights.iter_mut().zip(b) {
+                    *a = mix_weight(*a, *b);
+                }
+            } else {
+                self.set_weights(index, b);
+            }
+        }
+    }
+
+    pub fn linear_blend_bone(
+        &mut self,
+        index: usize,
+        other: &Pose,
+        other_index: usize,
+        alpha: f32,
+    ) {
+        self.mix_bone(
+            index,
+            other,
+            other_index,
+            |a, b| a.lerp(b, alpha),
+            |a, b| a.slerp(b, alpha),
+            |a, b| a + (b - a) * alpha,
+        );
+    }
+
+    pub fn additive_blend_bone(
+        &mut self,
+        index: usize,
+        other: &Pose,
+        other_index: usize,
+        alpha: f32,
+    ) {
+        self.mix_bone(
+            index,
+            other,
+            other_index,
+            |a, b| a + alpha * b,
+            |a, b| additive_blend_quat(a, b, alpha),
+            |a, b| a + alpha * b,
+        );
+    }
+
+    pub fn difference_bone(&mut self, index: usize, other: &Pose, other_index: usize) {
+        self.mix_bone(
+            index,
+            other,
+            other_index,
+            |a, b| b - a,
+            |a, b| b * a.inverse(),
+            |a, b| b - a,
+        );
     }
 
     pub fn additive_blend(&self, other: &Pose, alpha: f32) -> Self {
-        self.combine(other, |ba, bb| ba.additive_blend(bb, alpha))
+        let mut result = self.clone();
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.additive_blend_bone(index, other, other_index, alpha);
+        });
+        result
     }
 
     pub fn difference(&self, other: &Pose) -> Self {
-        self.combine(other, |l, r| l.difference(r))
+        let mut result = self.clone();
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.difference_bone(index, other, other_index);
+        });
+        result
     }
 
     pub fn linear_add(&self, other: &Pose) -> Self {
-        let mut result = self.combine(other, |l, r| l.linear_add(r));
+        let mut result = self.clone();
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.mix_bone(
+                index,
+                other,
+                other_index,
+                |a, b| a + b,
+                linear_add_quaternion,
+                |a, b| a + b,
+            );
+        });
         result.root_motion = match (&self.root_motion, &other.root_motion) {
             (Some(a), Some(b)) => Some(a.linear_add(b)),
             (Some(a), None) => Some(a.clone()),
@@ -268,13 +733,30 @@ impl Pose {
     }
 
     pub fn scalar_mult(&self, alpha: f32) -> Self {
-        let mut result = self.map_bones(|bone| bone.scalar_mult(alpha));
+        let mut result = self.clone();
+        for index in result.has_translation.iter() {
+            result.translations[index] *= alpha;
+        }
+        for index in result.has_rotation.iter() {
+            result.rotations[index] *= alpha;
+        }
+        for index in result.has_scale.iter() {
+            result.scales[index] *= alpha;
+        }
+        for index in result.has_weights.iter() {
+            for weight in &mut result.weights[index] {
+                *weight *= alpha;
+            }
+        }
         result.root_motion = self.root_motion.as_ref().map(|rm| rm.scale(alpha));
         result
     }
 
     pub fn normalize_quat(&self) -> Self {
-        let mut result = self.map_bones(|bone| bone.normalize_quat());
+        let mut result = self.clone();
+        for index in result.has_rotation.iter() {
+            result.rotations[index] = result.rotations[index].normalize();
+        }
         result.root_motion = self.root_motion.as_ref().map(|rm| RootMotionDelta {
             translation: rm.translation,
             rotation: rm.rotation.normalize(),
@@ -283,55 +765,84 @@ impl Pose {
     }
 
     pub fn overlay(&self, other: &Pose) -> Self {
-        self.combine(other, |l, r| l.overlay(r))
+        let mut result = self.clone();
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.mix_bone(index, other, other_index, |_, b| b, |_, b| b, |_, b| b);
+        });
+        result
     }
 
-    pub fn combine(&self, other: &Self, func: impl Fn(&BonePose, &BonePose) -> BonePose) -> Self {
-        let mut result = Pose::default();
+    /// Combines the channels present in both poses with the given functions, channel by channel.
+    /// Channels present in only one of the poses are copied as they are.
+    pub fn combine(
+        &self,
+        other: &Self,
+        mix_vec: impl Fn(Vec3, Vec3) -> Vec3,
+        mix_quat: impl Fn(Quat, Quat) -> Quat,
+        mix_weight: impl Fn(f32, f32) -> f32,
+    ) -> Self {
+        let mut result = self.clone();
+        result.zip_bones_mut(other, |result, index, other_index, _| {
+            result.mix_bone(index, other, other_index, &mix_vec, &mix_quat, &mix_weight);
+        });
+        result
+    }
 
-        for (path, bone_id) in self.paths.iter() {
-            if let Some(other_bone_id) = other.paths.get(path) {
-                result.add_bone(
-                    func(&self.bones[*bone_id], &other.bones[*other_bone_id]),
-                    *path,
-                );
-            } else {
-                result.add_bone(self.bones[*bone_id].clone(), *path);
-            }
+    /// Maps every channel of every bone with the given functions
+    pub fn map_bones(
+        &self,
+        map_vec: impl Fn(Vec3) -> Vec3,
+        map_quat: impl Fn(Quat) -> Quat,
+        map_weight: impl Fn(f32) -> f32,
+    ) -> Self {
+        let mut result = self.clone();
+        for index in result.has_translation.iter() {
+            result.translations[index] = map_vec(result.translations[index]);
         }
-
-        for (path, bone_id) in other.paths.iter() {
-            if self.paths.contains_key(path) {
-                continue;
+        for index in result.has_rotation.iter() {
+            result.rotations[index] = map_quat(result.rotations[index]);
+        }
+        for index in result.has_scale.iter() {
+            result.scales[index] = map_vec(result.scales[index]);
+        }
+        for index in result.has_weights.iter() {
+            for weight in &mut result.weights[index] {
+                *weight = map_weight(*weight);
             }
-            result.add_bone(other.bones[*bone_id].clone(), *path);
         }
-
-        result.timestamp = self.timestamp;
-        result.skeleton = self.skeleton.clone();
-        result.root_motion = self.root_motion.clone();
-
         result
     }
+}
 
-    pub fn map_bones(&self, func: impl Fn(&BonePose) -> BonePose) -> Self {
-        let mut result = Pose::default();
+/// Buffers of poses that are no longer needed, reused to create new poses without allocating.
+#[derive(Debug, Default)]
+pub struct PosePool {
+    free: Vec<Pose>,
+}
 
-        for (path, bone_index) in self.paths.iter() {
-            result.add_bone(func(&self.bones[*bone_index]), *path);
-        }
+impl PosePool {
+    /// Poses beyond this many are dropped instead of being kept around
+    const MAX_FREE: usize = 64;
 
-        result.timestamp = self.timestamp;
-        result.skeleton = self.skeleton.clone();
-        result.root_motion = self.root_motion.clone();
+    /// Returns an empty pose with the given layout
+    pub fn take(&mut self, layout: &Arc<PoseLayout>) -> Pose {
+        let mut pose = self.free.pop().unwrap_or_default();
+        pose.reset(layout);
+        pose.skeleton = Handle::default();
+        pose
+    }
 
-        result
+    /// Returns a copy of the given pose
+    pub fn clone_pose(&mut self, source: &Pose) -> Pose {
+        let mut pose = self.free.pop().unwrap_or_default();
+        pose.clone_from(source);
+        pose
     }
 
-    pub fn get_bone(&self, bone_id: BoneId) -> Option<&BonePose> {
-        self.paths
-            .get(&bone_id)
-            .and_then(|idx| self.bones.get(*idx))
+    pub fn recycle(&mut self, pose: Pose) {
+        if self.free.len() < Self::MAX_FREE {
+            self.free.push(pose);
+        }
     }
 }
 
@@ -519,7 +1030,7 @@ mod tests {
         let a = make_pose_with_root_motion(Vec3::new(1.0, 0.0, 0.0), Quat::IDENTITY);
         let b = Pose::default(); // no root motion
 
-        let result = a.combine(&b, |l, _r| l.clone());
+        let result = a.combine(&b, |l, _| l, |l, _| l, |l, _| l);
         assert!(result.root_motion.is_some());
         assert!(approx_eq_vec3(
             result.root_motion.unwrap().translation,
@@ -531,7 +1042,7 @@ mod tests {
     #[test]
     fn test_pose_map_bones_preserves_root_motion() {
         let p = make_pose_with_root_motion(Vec3::new(1.0, 2.0, 3.0), Quat::IDENTITY);
-        let result = p.map_bones(|b| b.clone());
+        let result = p.map_bones(|v| v, |q| q, |w| w);
         assert!(result.root_motion.is_some());
         assert!(approx_eq_vec3(
             result.root_motion.unwrap().translation,
@@ -597,4 +1108,120 @@ mod tests {
             1e-5
         ));
     }
+
+    // --- Pose layout tests ---
+
+    fn bone_id(name: &str) -> BoneId {
+        crate::animation_clip::EntityPath::default()
+            .child(name)
+            .id()
+    }
+
+    fn translated(x: f32) -> BonePose {
+        BonePose {
+            translation: Some(Vec3::new(x, 0.0, 0.0)),
+            ..BonePose::default()
+        }
+    }
+
+    #[test]
+    fn test_bone_set_iterates_in_order() {
+        let mut set = BoneSet::default();
+        for index in [130, 3, 64, 0] {
+            set.insert(index);
+        }
+        set.remove(64);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [0, 3, 130]);
+        assert!(set.contains(130));
+        assert!(!set.contains(64));
+    }
+
+    #[test]
+    fn test_pose_blend_matches_bones_across_layouts() {
+        let mut base = Pose::default();
+        base.add_bone(translated(0.0), bone_id("a"));
+        base.add_bone(translated(0.0), bone_id("b"));
+
+        let mut overlay = Pose::default();
+        overlay.add_bone(translated(2.0), bone_id("c"));
+        overlay.add_bone(translated(2.0), bone_id("b"));
+
+        base.zip_bones_mut(&overlay, |base, index, overlay_index, _| {
+            base.linear_blend_bone(index, &overlay, overlay_index, 0.5);
+        });
+
+        let x = |id| base.get_bone(bone_id(id)).unwrap().translation.unwrap().x;
+        assert_eq!(x("a"), 0.0);
+        assert_eq!(x("b"), 1.0);
+        assert_eq!(x("c"), 2.0);
+        assert_eq!(base.bone_count(), 3);
+    }
+
+    #[test]
+    fn test_pose_combine_mixes_channel_by_channel() {
+        let mut a = Pose::default();
+        a.add_bone(translated(1.0), bone_id("a"));
+
+        let mut b = Pose::default();
+        let rotation = Quat::from_rotation_y(1.0);
+        b.add_bone(
+            BonePose {
+                rotation: Some(rotation),
+                ..translated(2.0)
+            },
+            bone_id("a"),
+        );
+        b.add_bone(translated(4.0), bone_id("b"));
+
+        let result = a.combine(&b, |l, r| l + r, |l, _| l, |l, _| l);
+        let bone = result.get_bone(bone_id("a")).unwrap();
+        assert_eq!(bone.translation, Some(Vec3::new(3.0, 0.0, 0.0)));
+        assert_eq!(bone.rotation, Some(rotation));
+        assert_eq!(result.get_bone(bone_id("b")), Some(translated(4.0)));
+
+        let doubled = result.map_bones(|v| v * 2.0, |q| q, |w| w);
+        let x = |id| {
+            doubled
+                .get_bone(bone_id(id))
+                .unwrap()
+                .translation
+                .unwrap()
+                .x
+        };
+        assert_eq!(x("a"), 6.0);
+        assert_eq!(x("b"), 8.0);
+    }
+
+    #[test]
+    fn test_pose_retain_bones_clears_channels() {
+        let mut pose = Pose::default();
+        pose.add_bone(translated(1.0), bone_id("a"));
+        pose.add_bone(translated(1.0), bone_id("b"));
+
+        pose.retain_bones(|id| id == bone_id("a"));
+
+        assert!(pose.get_bone(bone_id("a")).is_some());
+        assert!(pose.get_bone(bone_id("b")).is_none());
+        // The layout is kept, so that the pose can still be blended by index
+        assert_eq!(pose.layout().len(), 2);
+    }
+
+    #[test]
+    fn test_pose_pool_reuses_buffers() {
+        let mut source = Pose::default();
+        for i in 0..8 {
+            source.add_bone(translated(i as f32), bone_id(&i.to_string()));
+        }
+
+        let mut pool = PosePool::default();
+        let copy = pool.clone_pose(&source);
+        assert_eq!(copy, source);
+
+        let buffer = copy.translations.as_ptr();
+        pool.recycle(copy);
+        let pose = pool.take(source.layout());
+        assert_eq!(pose.translations.as_ptr(), buffer);
+        assert_eq!(pose.bone_count(), 0);
+        assert_eq!(pose.layout().len(), 8);
+    }
 }
@@ -0,0 +1,701 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use bevy::{
+    asset::AssetId,
+    ecs::prelude::*,
+    log::info_span,
+    platform::{
+        collections::{HashMap, HashSet},
+        hash::FixedHasher,
+    },
+    reflect::prelude::*,
+    tasks::{ComputeTaskPool, ParallelSliceMut},
+    time::Time,
+};
+
+use crate::{
+    animated_scene::AnimatedSceneInstance,
+    animation_graph::{AnimationGraph, GraphInputPin, PinId, TimeUpdate},
+    animation_graph_player::{AnimationGraphPlayer, AnimationSource, PlaybackState},
+    context::system_resources::SystemResources,
+    deterministic::{DeterministicEvaluation, frame_delta},
+    edge_data::{DataValue, events::SampledEvent},
+    skeleton::Skeleton,
+    systems::build_entity_map,
+};
+
+/// Makes an [`AnimationGraphPlayer`] reuse the outputs of a graph evaluation that is shared with
+/// other players, instead of evaluating the graph itself.
+///
+/// Players share an evaluation when they play the same graph on the same skeleton with the same
+/// input data and seed, and their time offsets fall into the same bucket (see
+/// [`SharedEvaluationSettings::time_bucket`]). This is meant for crowds of background characters
+/// that would otherwise evaluate the same graph over and over.
+///
+/// Add it either to the entity with the player or to the entity with the
+/// [`AnimatedSceneHandle`](crate::animated_scene::AnimatedSceneHandle).
+///
+/// While shared, the state of the player's own graph is not advanced, and time updates queued on
+/// it are ignored. A player that is sent events with [`AnimationGraphPlayer::send_event`] moves to
+/// an evaluation of its own, carrying over the state of the one it shared, so that the events
+/// only affect it. It keeps evaluating apart from then on, as its graph state may have diverged,
+/// unless other players are sent the same events in the same frame.
+/// [`AnimationLod`](crate::lod::AnimationLod) does not apply to shared players.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct SharedEvaluation {
+    /// Time offset of this player in seconds. It is only used to pick a time bucket: a new
+    /// shared evaluation starts at the beginning of the bucket the offset falls into, and players
+    /// in the same bucket play in sync. The remainder of the offset within its bucket is not
+    /// applied.
+    pub time_offset: f32,
+    /// Key of the evaluation shared in the last frame
+    #[reflect(ignore)]
+    key: Option<SharedEvaluationKey>,
+    /// Identifies the events sent to this player so far, see [`SharedEvaluationKey::branch`]
+    #[reflect(ignore)]
+    branch: u64,
+}
+
+impl SharedEvaluation {
+    pub fn new(time_offset: f32) -> Self {
+        Self {
+            time_offset,
+            key: None,
+            branch: 0,
+        }
+    }
+}
+
+/// Global configuration of [`SharedEvaluation`]
+#[derive(Resource, Clone, Debug)]
+pub struct SharedEvaluationSettings {
+    /// Length in seconds of the time buckets. Players whose time offsets fall into the same
+    /// bucket share an evaluation, so larger buckets mean fewer evaluations but less variation
+    /// between players.
+    pub time_bucket: f32,
+}
+
+impl Default for SharedEvaluationSettings {
+    fn default() -> Self {
+        Self { time_bucket: 0.25 }
+    }
+}
+
+/// Everything that must match for two players to share an evaluation
+#[derive(Clone, Debug)]
+struct SharedEvaluationKey {
+    graph: AssetId<AnimationGraph>,
+    skeleton: AssetId<Skeleton>,
+    /// Sorted by pin
+    inputs: Vec<(GraphInputPin, DataValue)>,
+    /// Sorted by pin
+    global_inputs: Vec<(PinId, DataValue)>,
+    paused: bool,
+    seed: u64,
+    time_bucket: i64,
+    /// Hash of all events sent to the player, so that players that were sent different events do
+    /// not share an evaluation
+    branch: u64,
+}
+
+impl SharedEvaluationKey {
+    fn new(
+        player: &AnimationGraphPlayer,
+        time_offset: f32,
+        time_bucket: f32,
+        branch: u64,
+    ) -> Option<Self> {
+        let AnimationSource::Graph(graph) = &player.animation else {
+            return None;
+        };
+
+        // User events are sent to the shared evaluation instead
+        let user_events = GraphInputPin::Passthrough(AnimationGraphPlayer::USER_EVENTS.into());
+        let mut inputs: Vec<_> = player
+            .io_overrides
+            .data
+            .iter()
+            .filter(|(pin, _)| **pin != user_events)
+            .map(|(pin, value)| (pin.clone(), value.clone()))
+            .collect();
+        inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut global_inputs: Vec<_> = player
+            .global_input_data
+            .iter()
+            .map(|(pin, value)| (pin.clone(), value.clone()))
+            .collect();
+        global_inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Some(Self {
+            graph: graph.id(),
+            skeleton: player.skeleton.id(),
+            inputs,
+            global_inputs,
+            paused: player.is_paused(),
+            seed: player.seed(),
+            time_bucket: (time_offset / time_bucket.max(f32::EPSILON)).floor() as i64,
+            branch,
+        })
+    }
+}
+
+/// Branch of a player after it is sent the given events in the current frame
+fn next_branch(branch: u64, events: &[SampledEvent]) -> u64 {
+    let mut hasher = FixedHasher.build_hasher();
+    branch.hash(&mut hasher);
+    for event in events {
+        event.event.hash(&mut hasher);
+        event.weight.to_bits().hash(&mut hasher);
+        event.percentage.to_bits().hash(&mut hasher);
+        event.track.hash(&mut hasher);
+        event.payload.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Floats of the input values that are hashed, or `None` for values that are only compared
+fn scalar_floats(value: &DataValue) -> Option<Vec<f32>> {
+    match value {
+        DataValue::F32(value) => Some(vec![*value]),
+        DataValue::Vec2(value) => Some(value.to_array().to_vec()),
+        DataValue::Vec3(value) => Some(value.to_array().to_vec()),
+        DataValue::Quat(value) => Some(value.to_array().to_vec()),
+        DataValue::Bool(value) => Some(vec![f32::from(*value)]),
+        _ => None,
+    }
+}
+
+/// Like `==`, but NaN floats are equal to each other. Otherwise a player with a NaN input would
+/// never find its evaluation again and start a new one every frame.
+fn same_inputs<P: PartialEq>(a: &[(P, DataValue)], b: &[(P, DataValue)]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|((pin_a, a), (pin_b, b))| {
+            pin_a == pin_b
+                && match (scalar_floats(a), scalar_floats(b)) {
+                    (Some(floats_a), Some(floats_b)) => {
+                        std::mem::discriminant(a) == std::mem::discriminant(b)
+                            && floats_a
+                                .iter()
+                                .zip(&floats_b)
+                                .all(|(a, b)| a == b || (a.is_nan() && b.is_nan()))
+                    }
+                    _ => a == b,
+                }
+        })
+}
+
+impl PartialEq for SharedEvaluationKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.graph == other.graph
+            && self.skeleton == other.skeleton
+            && same_inputs(&self.inputs, &other.inputs)
+            && same_inputs(&self.global_inputs, &other.global_inputs)
+            && self.paused == other.paused
+            && self.seed == other.seed
+            && self.time_bucket == other.time_bucket
+            && self.branch == other.branch
+    }
+}
+
+/// Only the type of pose, event queue and other large input values is hashed. Keys that differ in
+/// those alone get the same hash and are told apart by comparing them.
+impl Hash for SharedEvaluationKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        fn hash_value<H: Hasher>(value: &DataValue, state: &mut H) {
+            std::mem::discriminant(value).hash(state);
+            for float in scalar_floats(value).unwrap_or_default() {
+                // 0. and -0. are equal, and so are all NaNs, so they must hash the same
+                let bits = if float == 0. {
+                    0
+                } else if float.is_nan() {
+                    f32::NAN.to_bits()
+                } else {
+                    float.to_bits()
+                };
+                state.write_u32(bits);
+            }
+        }
+
+        self.graph.hash(state);
+        self.skeleton.hash(state);
+        for (pin, value) in &self.inputs {
+            pin.hash(state);
+            hash_value(value, state);
+        }
+        for (pin, value) in &self.global_inputs {
+            pin.hash(state);
+            hash_value(value, state);
+        }
+        self.paused.hash(state);
+        self.seed.hash(state);
+        self.time_bucket.hash(state);
+        self.branch.hash(state);
+    }
+}
+
+struct SharedEvaluationGroup {
+    key: SharedEvaluationKey,
+    player: AnimationGraphPlayer,
+    /// Player entities sharing the evaluation in the current frame
+    members: Vec<Entity>,
+    /// Whether all members are culled
+    culled: bool,
+}
+
+impl SharedEvaluationGroup {
+    /// Creates the evaluation for a player. If the player shared a different evaluation in the
+    /// last frame, the graph state is carried over so that the animation continues smoothly.
+    fn new(
+        key: SharedEvaluationKey,
+        member: &AnimationGraphPlayer,
+        previous: Option<&AnimationGraphPlayer>,
+        time_bucket: f32,
+    ) -> Self {
+        let AnimationSource::Graph(graph) = &member.animation else {
+            unreachable!("Only players with a graph can share an evaluation");
+        };
+
+        let mut player =
+            AnimationGraphPlayer::new(member.skeleton.clone()).with_graph(graph.clone());
+        player.io_overrides = member.io_overrides.clone();
+        player.global_input_data = member.global_input_data.clone();
+
+        match previous.and_then(|previous| Some((previous, previous.context_arena.as_ref()?))) {
+            Some((previous, context_arena)) => {
+                player.context_arena = Some(context_arena.clone_states());
+                player.elapsed = previous.elapsed;
+                // New players start with a jump to the beginning of the graph
+                player.pending_update = TimeUpdate::Delta(0.);
+            }
+            None => {
+                player.queue_time_update(TimeUpdate::Absolute(key.time_bucket as f32 * time_bucket))
+            }
+        }
+
+        if key.paused {
+            player.pause();
+        }
+
+        Self {
+            key,
+            player,
+            members: Vec::new(),
+            culled: true,
+        }
+    }
+
+    fn update(&mut self, delta: f32, system_resources: &SystemResources) {
+        let Some(&root) = self.members.first() else {
+            return;
+        };
+
+        // Nodes that read the scene (e.g. for IK) see the first member
+        self.player.entity_map = build_entity_map(root, system_resources);
+        self.player.set_culled(self.culled);
+
+        if !self.player.is_paused() {
+            self.player.queue_time_update(TimeUpdate::Delta(delta));
+        }
+
+        let _update_span = info_span!("shared_player_update").entered();
+        self.player.update(system_resources, root);
+    }
+}
+
+/// Evaluations shared between players with a [`SharedEvaluation`] component
+#[derive(Resource, Default)]
+pub struct SharedEvaluations {
+    groups: Vec<SharedEvaluationGroup>,
+    /// Indices of the groups by the hash of their key
+    group_indices: HashMap<u64, Vec<usize>>,
+    /// Player entities sharing an evaluation in the current frame
+    members: HashSet<Entity>,
+}
+
+impl SharedEvaluations {
+    /// Whether the player on the given entity shares an evaluation in the current frame
+    pub fn is_shared(&self, player_entity: Entity) -> bool {
+        self.members.contains(&player_entity)
+    }
+
+    /// Number of shared evaluations in the current frame
+    pub fn evaluation_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    fn group_index(&self, key: &SharedEvaluationKey) -> Option<usize> {
+        self.group_indices
+            .get(&FixedHasher.hash_one(key))?
+            .iter()
+            .copied()
+            .find(|index| self.groups[*index].key == *key)
+    }
+
+    fn add_group(&mut self, group: SharedEvaluationGroup) -> usize {
+        let index = self.groups.len();
+        self.group_indices
+            .entry(FixedHasher.hash_one(&group.key))
+            .or_default()
+            .push(index);
+        self.groups.push(group);
+        index
+    }
+
+    /// Drops the groups that no player shares anymore
+    fn remove_unused_groups(&mut self) {
+        let groups = std::mem::take(&mut self.groups);
+        self.group_indices.clear();
+        for group in groups.into_iter().filter(|group| !group.members.is_empty()) {
+            self.add_group(group);
+        }
+    }
+}
+
+/// System that groups players with a [`SharedEvaluation`] component, evaluates the graph once
+/// per group and hands the outputs to every player in it.
+pub fn update_shared_evaluations(
+    time: Res<Time>,
+    deterministic: Option<Res<DeterministicEvaluation>>,
+    settings: Res<SharedEvaluationSettings>,
+    mut shared: ResMut<SharedEvaluations>,
+    mut sharing: Query<(
+        Entity,
+        &mut SharedEvaluation,
+        Option<&AnimatedSceneInstance>,
+    )>,
+    mut players: Query<&mut AnimationGraphPlayer>,
+    system_resources: SystemResources,
+) {
+    let shared = &mut *shared;
+    shared.members.clear();
+    for group in &mut shared.groups {
+        group.members.clear();
+        group.culled = true;
+    }
+
+    for (entity, mut shared_evaluation, instance) in &mut sharing {
+        let player_entity = instance.map_or(entity, |instance| instance.player_entity());
+        let Ok(mut player) = players.get_mut(player_entity) else {
+            continue;
+        };
+        let branch = if player.queued_events.events.is_empty() {
+            shared_evaluation.branch
+        } else {
+            next_branch(shared_evaluation.branch, &player.queued_events.events)
+        };
+        let Some(key) = SharedEvaluationKey::new(
+            &player,
+            shared_evaluation.time_offset,
+            settings.time_bucket,
+            branch,
+        ) else {
+            continue;
+        };
+        shared_evaluation.branch = branch;
+
+        let index = match shared.group_index(&key) {
+            Some(index) => index,
+            None => {
+                let previous = shared_evaluation
+                    .key
+                    .as_ref()
+                    .and_then(|key| shared.group_index(key))
+                    .map(|index| &shared.groups[index].player);
+                let group = SharedEvaluationGroup::new(
+                    key.clone(),
+                    &player,
+                    previous,
+                    settings.time_bucket,
+                );
+                shared.add_group(group)
+            }
+        };
+
+        let group = &mut shared.groups[index];
+        // Members of the group were all sent the same events this frame, as the branch is part of
+        // the key, so the events are only sent to the evaluation once
+        if group.members.is_empty() {
+            group
+                .player
+                .queued_events
+                .events
+                .append(&mut player.queued_events.events);
+        } else {
+            player.queued_events.events.clear();
+        }
+        group.members.push(player_entity);
+        group.culled &= player.is_culled();
+        shared.members.insert(player_entity);
+
+        if shared_evaluation.key.as_ref() != Some(&key) {
+            shared_evaluation.key = Some(key);
+        }
+    }
+
+    shared.remove_unused_groups();
+
+    let delta = frame_delta(&time, deterministic.as_deref());
+    shared
+        .groups
+        .par_splat_map_mut(ComputeTaskPool::get(), None, |_, groups| {
+            for group in groups {
+                group.update(delta, &system_resources);
+            }
+        });
+
+    for group in &shared.groups {
+        for member in &group.members {
+            let Ok(mut player) = players.get_mut(*member) else {
+                continue;
+            };
+            player.entity_map = build_entity_map(*member, &system_resources);
+            player.share_outputs(&group.player);
+
+            if matches!(player.playback_state(), PlaybackState::PlayOneFrame) {
+                player.pause();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        asset::{Assets, Handle},
+        ecs::{name::Name, system::RunSystemOnce},
+        tasks::TaskPool,
+    };
+
+    use super::*;
+    use crate::{
+        animation_clip::GraphClip,
+        animation_graph::DEFAULT_OUTPUT_POSE,
+        animation_node::{AnimationNode, NodeLike},
+        context::{new_context::NodeContext, spec_context::SpecContext},
+        edge_data::{DataSpec, events::AnimationEvent},
+        errors::GraphError,
+        pose::Pose,
+        state_machine::high_level::StateMachine,
+    };
+
+    fn player() -> AnimationGraphPlayer {
+        AnimationGraphPlayer::new(Handle::default()).with_graph(Handle::default())
+    }
+
+    #[test]
+    fn test_equal_shared_evaluation_keys_hash_the_same() {
+        let mut a = player();
+        let mut b = player();
+        a.set_input_data("speed", DataValue::F32(0.));
+        b.set_input_data("speed", DataValue::F32(-0.));
+        let key =
+            |player: &AnimationGraphPlayer| SharedEvaluationKey::new(player, 0., 0.25, 0).unwrap();
+        assert_eq!(key(&a), key(&b));
+        assert_eq!(FixedHasher.hash_one(key(&a)), FixedHasher.hash_one(key(&b)));
+    }
+
+    #[test]
+    fn test_shared_evaluation_keys_with_nan_inputs_are_equal() {
+        let mut a = player();
+        let mut b = player();
+        a.set_input_data("speed", DataValue::F32(f32::NAN));
+        b.set_input_data("speed", DataValue::F32(-f32::NAN));
+        let key =
+            |player: &AnimationGraphPlayer| SharedEvaluationKey::new(player, 0., 0.25, 0).unwrap();
+        assert_eq!(key(&a), key(&b));
+        assert_eq!(FixedHasher.hash_one(key(&a)), FixedHasher.hash_one(key(&b)));
+    }
+
+    /// Outputs an empty pose at the current time
+    #[derive(Reflect, Clone, Debug)]
+    struct ClockNode;
+
+    impl NodeLike for ClockNode {
+        fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+            let prev_time = ctx.prev_time();
+            let time = ctx
+                .time_update_fwd()?
+                .partial_update_basic(prev_time)
+                .unwrap_or(prev_time);
+            ctx.set_time(time);
+            ctx.set_data_fwd(
+                DEFAULT_OUTPUT_POSE,
+                Pose {
+                    timestamp: time,
+                    ..Default::default()
+                },
+            );
+            Ok(())
+        }
+
+        fn spec(&self, mut ctx: SpecContext) -> Result<(), GraphError> {
+            ctx.add_output_time()
+                .add_output_data(DEFAULT_OUTPUT_POSE, DataSpec::Pose);
+            Ok(())
+        }
+
+        fn display_name(&self) -> String {
+            "Clock".into()
+        }
+    }
+
+    /// World with the resources needed by [`update_shared_evaluations`], and a player of a graph
+    /// made of a [`ClockNode`]
+    fn shared_world() -> (World, impl Fn() -> AnimationGraphPlayer) {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+
+        let mut graph = AnimationGraph::new();
+        let node = AnimationNode::new("clock", ClockNode);
+        let id = node.id;
+        graph.add_node(node);
+        graph.add_output_data(DEFAULT_OUTPUT_POSE.into(), DataSpec::Pose);
+        graph.add_output_time();
+        graph.add_output_data_edge(id, DEFAULT_OUTPUT_POSE, DEFAULT_OUTPUT_POSE);
+        graph.add_output_pose_edge(id);
+
+        let mut world = World::new();
+        world.init_resource::<Assets<GraphClip>>();
+        world.init_resource::<Assets<AnimationGraph>>();
+        world.init_resource::<Assets<StateMachine>>();
+        world.init_resource::<Assets<Skeleton>>();
+        world.init_resource::<Time>();
+        world.init_resource::<SharedEvaluationSettings>();
+        world.init_resource::<SharedEvaluations>();
+        let graph = world.resource_mut::<Assets<AnimationGraph>>().add(graph);
+        let skeleton = world
+            .resource_mut::<Assets<Skeleton>>()
+            .add(Skeleton::default());
+
+        let player = move || AnimationGraphPlayer::new(skeleton.clone()).with_graph(graph.clone());
+        (world, player)
+    }
+
+    fn spawn_player(world: &mut World, player: AnimationGraphPlayer, time_offset: f32) -> Entity {
+        world
+            .spawn((
+                Name::new("character"),
+                player,
+                SharedEvaluation::new(time_offset),
+            ))
+            .with_child(Name::new("bone"))
+            .id()
+    }
+
+    fn run_frame(world: &mut World) {
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(0.1));
+        world.run_system_once(update_shared_evaluations).unwrap();
+    }
+
+    fn output_pose(world: &World, entity: Entity) -> Pose {
+        let player = world.get::<AnimationGraphPlayer>(entity).unwrap();
+        match &player.get_outputs()[DEFAULT_OUTPUT_POSE] {
+            DataValue::Pose(pose) => pose.clone(),
+            other => panic!("Expected a pose, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_players_in_the_same_bucket_share_the_output_pose() {
+        let (mut world, player) = shared_world();
+        let a = spawn_player(&mut world, player(), 0.);
+        let b = spawn_player(&mut world, player(), 0.1);
+        let c = spawn_player(&mut world, player(), 0.3);
+
+        for _ in 0..3 {
+            run_frame(&mut world);
+        }
+
+        let shared = world.resource::<SharedEvaluations>();
+        assert_eq!(shared.evaluation_count(), 2);
+        assert!([a, b, c].into_iter().all(|entity| shared.is_shared(entity)));
+        assert_eq!(output_pose(&world, a), output_pose(&world, b));
+        assert!((output_pose(&world, a).timestamp - 0.3).abs() < 1e-5);
+        // The next bucket starts 0.25 seconds later
+        assert!((output_pose(&world, c).timestamp - 0.55).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_evaluation_carries_over_when_changing_buckets() {
+        let (mut world, player) = shared_world();
+        let a = spawn_player(&mut world, player(), 0.);
+        let b = spawn_player(&mut world, player(), 0.);
+
+        for _ in 0..3 {
+            run_frame(&mut world);
+        }
+        world.get_mut::<SharedEvaluation>(a).unwrap().time_offset = 0.6;
+        run_frame(&mut world);
+
+        assert_eq!(world.resource::<SharedEvaluations>().evaluation_count(), 2);
+        // Starting over in the new bucket would put it at 0.6
+        assert!((output_pose(&world, a).timestamp - 0.4).abs() < 1e-5);
+        assert_eq!(output_pose(&world, a), output_pose(&world, b));
+    }
+
+    #[test]
+    fn test_events_only_reach_the_players_they_were_sent_to() {
+        let (mut world, player) = shared_world();
+        let a = spawn_player(&mut world, player(), 0.);
+        let b = spawn_player(&mut world, player(), 0.);
+        let c = spawn_player(&mut world, player(), 0.);
+
+        run_frame(&mut world);
+        assert_eq!(world.resource::<SharedEvaluations>().evaluation_count(), 1);
+
+        for entity in [a, b] {
+            world
+                .get_mut::<AnimationGraphPlayer>(entity)
+                .unwrap()
+                .send_event(AnimationEvent::StringId("jump".into()));
+        }
+        run_frame(&mut world);
+
+        assert_eq!(world.resource::<SharedEvaluations>().evaluation_count(), 2);
+        let key = |entity| world.get::<SharedEvaluation>(entity).unwrap().key.clone();
+        assert_eq!(key(a), key(b));
+        assert_ne!(key(a), key(c));
+        // The players that were sent the jump keep evaluating apart, and in time with the others
+        run_frame(&mut world);
+        assert_eq!(world.resource::<SharedEvaluations>().evaluation_count(), 2);
+        assert_eq!(output_pose(&world, a), output_pose(&world, b));
+        assert_eq!(output_pose(&world, a), output_pose(&world, c));
+    }
+
+    #[test]
+    fn test_shared_evaluation_key_buckets_time_offsets() {
+        let player = player();
+        let key = |offset| SharedEvaluationKey::new(&player, offset, 0.25, 0).unwrap();
+
+        assert_eq!(key(0.), key(0.2));
+        assert_ne!(key(0.2), key(0.3));
+        assert_eq!(key(0.3).time_bucket, 1);
+    }
+
+    #[test]
+    fn test_shared_evaluation_key_depends_on_inputs() {
+        let mut a = player();
+        let mut b = player();
+        a.set_input_data("speed", DataValue::F32(1.));
+        b.set_input_data("speed", DataValue::F32(1.));
+        let key = |player: &AnimationGraphPlayer| SharedEvaluationKey::new(player, 0., 0.25, 0);
+        assert_eq!(key(&a), key(&b));
+
+        b.set_input_data("speed", DataValue::F32(2.));
+        assert_ne!(key(&a), key(&b));
+
+        // Queued user events do not prevent sharing
+        b.set_input_data("speed", DataValue::F32(1.));
+        b.send_event(AnimationEvent::StringId("jump".into()));
+        a.io_overrides.data.insert(
+            GraphInputPin::Passthrough(AnimationGraphPlayer::USER_EVENTS.into()),
+            DataValue::F32(0.),
+        );
+        assert_eq!(key(&a), key(&b));
+    }
+}
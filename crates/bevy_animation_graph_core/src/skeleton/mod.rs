@@ -1,14 +1,14 @@
 pub mod loader;
 pub mod serial;
 
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use bevy::{
     asset::Asset, platform::collections::HashMap, reflect::Reflect,
     transform::components::Transform,
 };
 
-use crate::{animation_clip::EntityPath, id::BoneId};
+use crate::{animation_clip::EntityPath, id::BoneId, pose::PoseLayout};
 
 #[derive(Debug, Clone, Reflect, Default)]
 pub struct DefaultBoneTransform {
@@ -25,6 +25,9 @@ pub struct Skeleton {
     id_to_path: HashMap<BoneId, EntityPath>,
     children_map: HashMap<BoneId, Vec<BoneId>>,
     parent_map: HashMap<BoneId, BoneId>,
+    /// Bones in the order they were added, shared by all poses of this skeleton
+    #[reflect(ignore)]
+    pose_layout: Arc<PoseLayout>,
 }
 
 impl Skeleton {
@@ -44,6 +47,7 @@ impl Skeleton {
         let id = path.id();
 
         self.id_to_path.insert(id, path);
+        Arc::make_mut(&mut self.pose_layout).push(id);
         self.default_transforms.insert(
             id,
             DefaultBoneTransform {
@@ -105,6 +109,18 @@ impl Skeleton {
         self.id_to_path.get(&id).cloned()
     }
 
+    /// Bone order used by poses of this skeleton, see [`Pose::for_skeleton`].
+    ///
+    /// [`Pose::for_skeleton`]: crate::pose::Pose::for_skeleton
+    pub fn pose_layout(&self) -> &Arc<PoseLayout> {
+        &self.pose_layout
+    }
+
+    /// Index of the bone in [`Self::pose_layout`]
+    pub fn bone_index(&self, id: &BoneId) -> Option<usize> {
+        self.pose_layout.index(id)
+    }
+
     pub fn has_id(&self, id: &BoneId) -> bool {
         self.id_to_path.contains_key(id)
     }
@@ -0,0 +1,325 @@
+use bevy::{
+    asset::{AssetServer, ReflectHandle, UntypedHandle},
+    platform::collections::HashMap,
+    reflect::{
+        PartialReflect, ReflectFromReflect, TypeRegistration, TypeRegistry,
+        serde::{
+            ReflectDeserializerProcessor, ReflectSerializerProcessor, TypedReflectDeserializer,
+            TypedReflectSerializer,
+        },
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeSeed};
+use uuid::Uuid;
+
+use crate::{
+    animation_graph::NodeId,
+    animation_graph_player::PlaybackState,
+    context::{
+        graph_context_arena::SubContextKey,
+        node_states::{GraphStateType, NodeState, ReflectGraphStateType},
+    },
+    errors::SnapshotError,
+};
+
+/// Serializable copy of the state of an
+/// [`AnimationGraphPlayer`](crate::animation_graph_player::AnimationGraphPlayer), created with
+/// [`AnimationGraphPlayer::snapshot`] and applied with [`AnimationGraphPlayer::restore`].
+///
+/// Node states are keyed by node id and by the path of their graph context from the top level
+/// graph, which do not change between runs. A snapshot can therefore be saved to disk and restored
+/// after a restart, as long as the graph has not been edited in the meantime. States of nodes that
+/// no longer exist are ignored, and nodes missing from the snapshot start from scratch.
+///
+/// [`AnimationGraphPlayer::snapshot`]: crate::animation_graph_player::AnimationGraphPlayer::snapshot
+/// [`AnimationGraphPlayer::restore`]: crate::animation_graph_player::AnimationGraphPlayer::restore
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PlayerSnapshot {
+    pub playback_state: PlaybackState,
+    pub elapsed: f32,
+    pub graph_states: Vec<GraphStateSnapshot>,
+}
+
+/// Node states of a graph context
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GraphStateSnapshot {
+    /// Path of the context from the top level graph, empty for the top level graph itself
+    pub path: Vec<SubContextKey>,
+    /// Sorted by node id
+    pub node_states: Vec<NodeStateSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeStateSnapshot {
+    pub node_id: NodeId,
+    pub time: f32,
+    pub state: Option<ReflectedState>,
+}
+
+/// A node state in its RON representation. Its type must be registered with
+/// `#[reflect(GraphStateType)]` to be restored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReflectedState {
+    pub type_path: String,
+    pub ron: String,
+}
+
+impl GraphStateSnapshot {
+    pub(crate) fn new<'a>(
+        path: Vec<SubContextKey>,
+        node_states: impl IntoIterator<Item = (NodeId, &'a NodeState)>,
+        registry: &TypeRegistry,
+    ) -> Result<Self, SnapshotError> {
+        let mut node_states = node_states
+            .into_iter()
+            .map(|(node_id, node_state)| {
+                Ok(NodeStateSnapshot {
+                    node_id,
+                    time: node_state.current_time(),
+                    state: node_state
+                        .current_state()
+                        .map(|state| ReflectedState::new(state, registry))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, SnapshotError>>()?;
+        node_states.sort_by_key(|node_state| node_state.node_id.uuid());
+
+        Ok(Self { path, node_states })
+    }
+
+    pub(crate) fn node_states(
+        &self,
+        registry: &TypeRegistry,
+        asset_server: &AssetServer,
+    ) -> Result<HashMap<NodeId, NodeState>, SnapshotError> {
+        self.node_states
+            .iter()
+            .map(|node_state| {
+                let state = node_state
+                    .state
+                    .as_ref()
+                    .map(|state| state.restore(registry, asset_server))
+                    .transpose()?;
+                Ok((
+                    node_state.node_id,
+                    NodeState::restored(node_state.time, state),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl ReflectedState {
+    fn new(state: &dyn GraphStateType, registry: &TypeRegistry) -> Result<Self, SnapshotError> {
+        let type_path = state.reflect_type_path().to_string();
+        if registry.get_with_type_path(&type_path).is_none() {
+            return Err(SnapshotError::UnregisteredStateType(type_path));
+        }
+        let serializer = TypedReflectSerializer::with_processor(
+            state.as_partial_reflect(),
+            registry,
+            &HandleSerializer,
+        );
+        let ron = ron::to_string(&serializer)
+            .map_err(|err| SnapshotError::Serialization(type_path.clone(), err.to_string()))?;
+
+        Ok(Self { type_path, ron })
+    }
+
+    fn restore(
+        &self,
+        registry: &TypeRegistry,
+        asset_server: &AssetServer,
+    ) -> Result<Box<dyn GraphStateType>, SnapshotError> {
+        let type_path = &self.type_path;
+        let registration = registry
+            .get_with_type_path(type_path)
+            .ok_or_else(|| SnapshotError::UnregisteredStateType(type_path.clone()))?;
+        let deserialization_error =
+            |err: String| SnapshotError::Deserialization(type_path.clone(), err);
+
+        let mut deserializer = ron::Deserializer::from_str(&self.ron)
+            .map_err(|err| deserialization_error(err.to_string()))?;
+        let mut processor = HandleDeserializer { asset_server };
+        let value =
+            TypedReflectDeserializer::with_processor(registration, registry, &mut processor)
+                .deserialize(&mut deserializer)
+                .map_err(|err| deserialization_error(err.to_string()))?;
+
+        let not_a_state = || SnapshotError::NotAGraphStateType(type_path.clone());
+        let value = registration
+            .data::<ReflectFromReflect>()
+            .and_then(|from_reflect| from_reflect.from_reflect(value.as_ref()))
+            .ok_or_else(not_a_state)?;
+        registration
+            .data::<ReflectGraphStateType>()
+            .ok_or_else(not_a_state)?
+            .get_boxed(value)
+            .map_err(|_| not_a_state())
+    }
+}
+
+/// Asset handles in node states (e.g. the skeleton of a pose), stored by asset path
+#[derive(Serialize, Deserialize)]
+enum SerializedHandle {
+    Path(String),
+    Uuid(Uuid),
+}
+
+struct HandleSerializer;
+
+impl ReflectSerializerProcessor for HandleSerializer {
+    fn try_serialize<S>(
+        &self,
+        value: &dyn PartialReflect,
+        registry: &TypeRegistry,
+        serializer: S,
+    ) -> Result<Result<S::Ok, S>, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Some(untyped_handle) = value.try_as_reflect().and_then(|value| {
+            registry
+                .get_type_data::<ReflectHandle>(value.reflect_type_info().type_id())?
+                .downcast_handle_untyped(value.as_any())
+        }) else {
+            return Ok(Err(serializer));
+        };
+
+        let handle = match &untyped_handle {
+            UntypedHandle::Uuid { uuid, .. } => SerializedHandle::Uuid(*uuid),
+            UntypedHandle::Strong(_) => {
+                let path = untyped_handle.path().ok_or_else(|| {
+                    serde::ser::Error::custom("asset handle does not have a path")
+                })?;
+                SerializedHandle::Path(path.to_string())
+            }
+        };
+
+        handle.serialize(serializer).map(Ok)
+    }
+}
+
+struct HandleDeserializer<'a> {
+    asset_server: &'a AssetServer,
+}
+
+impl ReflectDeserializerProcessor for HandleDeserializer<'_> {
+    fn try_deserialize<'de, D>(
+        &mut self,
+        registration: &TypeRegistration,
+        _registry: &TypeRegistry,
+        deserializer: D,
+    ) -> Result<Result<Box<dyn PartialReflect>, D>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let Some(handle_info) = registration.data::<ReflectHandle>() else {
+            return Ok(Err(deserializer));
+        };
+        let type_id = handle_info.asset_type_id();
+
+        let untyped_handle = match SerializedHandle::deserialize(deserializer)? {
+            SerializedHandle::Uuid(uuid) => UntypedHandle::Uuid { type_id, uuid },
+            // Assets referenced by node states are already loaded by the graph
+            SerializedHandle::Path(path) => self
+                .asset_server
+                .get_path_ids(path.clone())
+                .into_iter()
+                .find(|id| id.type_id() == type_id)
+                .and_then(|id| self.asset_server.get_id_handle_untyped(id))
+                .ok_or_else(|| serde::de::Error::custom(format!("asset {path} is not loaded")))?,
+        };
+
+        Ok(Ok(handle_info.typed(untyped_handle).into_partial_reflect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::Any, sync::Arc};
+
+    use bevy::{
+        app::{App, TaskPoolPlugin},
+        asset::{AssetApp, AssetId, AssetPlugin},
+        ecs::reflect::AppTypeRegistry,
+        math::Vec3,
+        reflect::{ReflectDeserialize, ReflectSerialize},
+    };
+
+    use super::*;
+    use crate::{
+        context::graph_context_arena::GraphContextArena,
+        id::BoneId,
+        interpolation::inertialization::PoseHistory,
+        pose::{Pose, PoseLayout},
+        skeleton::Skeleton,
+        state_machine::{
+            high_level::StateId,
+            low_level::{FsmState, LowLevelStateId},
+        },
+    };
+
+    #[test]
+    fn test_snapshot_round_trips_node_states() {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<Skeleton>()
+            .register_asset_reflect::<Skeleton>()
+            .register_type::<FsmState>()
+            .register_type_data::<Arc<PoseLayout>, ReflectSerialize>()
+            .register_type_data::<Arc<PoseLayout>, ReflectDeserialize>();
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+        let asset_server = app.world().resource::<AssetServer>();
+
+        let mut pose = Pose::default();
+        let bone = pose.insert_bone(BoneId::from_uuid(Uuid::from_u128(1)));
+        pose.set_translation(bone, Vec3::X);
+        let state_id = LowLevelStateId::HlState(StateId::from(Uuid::from_u128(2)));
+        let fsm_state = FsmState {
+            state: state_id.clone(),
+            state_entered_time: 0.5,
+            source_entered_time: 0.,
+            pending_inertialization: None,
+            inertializations: HashMap::default(),
+            pose_history: [(
+                "pose".to_string(),
+                PoseHistory {
+                    last: Some(pose.clone()),
+                    previous: None,
+                    delta: 0.1,
+                },
+            )]
+            .into(),
+        };
+        let node_id = NodeId::from(Uuid::from_u128(3));
+
+        let mut arena = GraphContextArena::new(AssetId::default());
+        arena
+            .get_toplevel_mut()
+            .node_states
+            .restore([(node_id, NodeState::restored(1.5, Some(Box::new(fsm_state))))].into());
+
+        let snapshot = arena.snapshot(&registry).unwrap();
+        let ron = ron::to_string(&snapshot).unwrap();
+        let snapshot: Vec<GraphStateSnapshot> = ron::from_str(&ron).unwrap();
+
+        let mut restored = GraphContextArena::new(AssetId::default());
+        restored
+            .restore(&snapshot, &registry, asset_server)
+            .unwrap();
+
+        let node_states: Vec<_> = restored.get_toplevel().node_states.iter_by_id().collect();
+        assert_eq!(node_states.len(), 1);
+        let (restored_node_id, node_state) = node_states[0];
+        assert_eq!(restored_node_id, node_id);
+        assert_eq!(node_state.current_time(), 1.5);
+
+        let fsm_state: &dyn Any = node_state.current_state().unwrap();
+        let fsm_state = fsm_state.downcast_ref::<FsmState>().unwrap();
+        assert_eq!(fsm_state.state, state_id);
+        assert_eq!(fsm_state.state_entered_time, 0.5);
+        assert_eq!(fsm_state.pose_history["pose"].last, Some(pose));
+    }
+}
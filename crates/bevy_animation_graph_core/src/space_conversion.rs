@@ -1,7 +1,7 @@
 use bevy::transform::components::Transform;
 
 use super::{
-    pose::{BoneId, BonePose, Pose},
+    pose::{BoneId, Pose},
     skeleton::Skeleton,
 };
 use crate::context::pose_fallback::PoseFallbackContext;
@@ -25,14 +25,11 @@ impl SpaceConversionContext<'_> {
         let mut curr_transform = Transform::IDENTITY;
 
         while curr_bone_id != source {
-            let bone_frame = if data.paths.contains_key(&curr_bone_id) {
-                let bone_id = data.paths.get(&curr_bone_id).unwrap();
-                data.bones[*bone_id].clone()
-            } else {
-                BonePose::default()
-            };
             let curr_local_transform = self.pose_fallback.local_transform(curr_bone_id).unwrap();
-            let merged_local_transform = bone_frame.to_transform_with_base(curr_local_transform);
+            let merged_local_transform = match data.bone_index(&curr_bone_id) {
+                Some(index) => data.transform_with_base(index, curr_local_transform),
+                None => curr_local_transform,
+            };
 
             curr_transform = merged_local_transform * curr_transform;
             curr_bone_id = skeleton.parent(&curr_bone_id).unwrap();
@@ -53,14 +50,11 @@ impl SpaceConversionContext<'_> {
         let mut curr_transform = Transform::IDENTITY;
 
         while curr_bone_id != target {
-            let bone_pose: BonePose = if data.paths.contains_key(&curr_bone_id) {
-                let bone_id = data.paths.get(&curr_bone_id).unwrap();
-                data.bones[*bone_id].clone()
-            } else {
-                BonePose::default()
-            };
             let curr_local_transform = self.pose_fallback.local_transform(curr_bone_id).unwrap();
-            let merged_local_transform = bone_pose.to_transform_with_base(curr_local_transform);
+            let merged_local_transform = match data.bone_index(&curr_bone_id) {
+                Some(index) => data.transform_with_base(index, curr_local_transform),
+                None => curr_local_transform,
+            };
 
             curr_transform = merged_local_transform * curr_transform;
             curr_bone_id = skeleton.parent(&curr_bone_id).unwrap();
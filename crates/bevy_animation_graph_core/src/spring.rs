@@ -0,0 +1,264 @@
+use bevy::{
+    math::Vec3,
+    reflect::{Reflect, std_traits::ReflectDefault},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+/// Longest time step of the simulation. Longer frames are split into several steps.
+const MAX_STEP: f32 = 1. / 60.;
+/// Most steps simulated in one frame. Frames longer than this many steps are slowed down.
+const MAX_STEPS: usize = 8;
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub struct SpringSettings {
+    /// How strongly the bones are pulled back towards the animated pose
+    pub stiffness: f32,
+    /// How quickly the bones stop swinging. The velocity of a bone decays exponentially at this
+    /// rate per second.
+    pub damping: f32,
+    /// Acceleration applied to the bones, in global space
+    pub gravity: Vec3,
+    /// Radius of the bones when colliding
+    pub radius: f32,
+}
+
+impl Default for SpringSettings {
+    fn default() -> Self {
+        Self {
+            stiffness: 100.,
+            damping: 5.,
+            gravity: Vec3::new(0., -9.81, 0.),
+            radius: 0.02,
+        }
+    }
+}
+
+/// Shape of a collider, in the space of the bone it is attached to
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Default)]
+pub enum SpringColliderShape {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// Capsule between two points
+    Capsule {
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    },
+}
+
+impl Default for SpringColliderShape {
+    fn default() -> Self {
+        Self::Sphere {
+            center: Vec3::ZERO,
+            radius: 0.1,
+        }
+    }
+}
+
+impl SpringColliderShape {
+    /// The collider placed at the given transform of its bone
+    pub fn placed(&self, transform: Transform) -> PlacedSpringCollider {
+        let (start, end, radius) = match *self {
+            Self::Sphere { center, radius } => (center, center, radius),
+            Self::Capsule { start, end, radius } => (start, end, radius),
+        };
+        PlacedSpringCollider {
+            start: transform.transform_point(start),
+            end: transform.transform_point(end),
+            radius: radius * transform.scale.max_element(),
+        }
+    }
+}
+
+/// Collider in global space. Spheres are capsules with the same start and end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedSpringCollider {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub radius: f32,
+}
+
+impl PlacedSpringCollider {
+    /// Moves a sphere out of the collider, if it is inside it
+    fn push_out(&self, position: Vec3, radius: f32) -> Vec3 {
+        let segment = self.end - self.start;
+        let length_squared = segment.length_squared();
+        let t = if length_squared > 0. {
+            ((position - self.start).dot(segment) / length_squared).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let closest = self.start + segment * t;
+        let min_distance = self.radius + radius;
+        let offset = position - closest;
+        if offset.length_squared() >= min_distance * min_distance {
+            return position;
+        }
+        closest + offset.normalize_or(Vec3::Y) * min_distance
+    }
+}
+
+/// Simulated positions of the joints of a chain, in global space. The first joint stays at its
+/// animated position.
+#[derive(Reflect, Clone, Debug, Default, PartialEq)]
+pub struct SpringChainState {
+    pub positions: Vec<Vec3>,
+    pub velocities: Vec<Vec3>,
+}
+
+impl SpringChainState {
+    /// Advances the simulation by `delta_time` towards the `animated` positions of the joints.
+    /// Starts over from the animated positions if the number of joints changed.
+    pub fn step(
+        &mut self,
+        animated: &[Vec3],
+        settings: &SpringSettings,
+        colliders: &[PlacedSpringCollider],
+        delta_time: f32,
+    ) {
+        if self.positions.len() != animated.len() {
+            self.positions = animated.to_vec();
+            self.velocities = vec![Vec3::ZERO; animated.len()];
+        }
+        let Some(&root) = animated.first() else {
+            return;
+        };
+        self.positions[0] = root;
+
+        let steps = ((delta_time / MAX_STEP).ceil() as usize).clamp(1, MAX_STEPS);
+        let dt = (delta_time / steps as f32).min(MAX_STEP);
+        if dt <= 0. {
+            return;
+        }
+
+        for _ in 0..steps {
+            for i in 1..animated.len() {
+                let previous = self.positions[i];
+                let acceleration = (animated[i] - previous) * settings.stiffness + settings.gravity;
+                let velocity =
+                    (self.velocities[i] + acceleration * dt) * (-settings.damping * dt).exp();
+                let mut position = previous + velocity * dt;
+
+                for collider in colliders {
+                    position = collider.push_out(position, settings.radius);
+                }
+
+                // Bones keep their animated length
+                let parent = self.positions[i - 1];
+                let length = animated[i].distance(animated[i - 1]);
+                let direction = (position - parent)
+                    .try_normalize()
+                    .unwrap_or_else(|| (animated[i] - animated[i - 1]).normalize_or_zero());
+                position = parent + direction * length;
+
+                self.positions[i] = position;
+                self.velocities[i] = (position - previous) / dt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vertical chain hanging down from the origin, 0.5 units per bone
+    fn hanging_chain() -> Vec<Vec3> {
+        (0..4).map(|i| Vec3::NEG_Y * 0.5 * i as f32).collect()
+    }
+
+    fn simulate(
+        state: &mut SpringChainState,
+        animated: &[Vec3],
+        settings: &SpringSettings,
+        colliders: &[PlacedSpringCollider],
+        seconds: f32,
+    ) {
+        for _ in 0..(seconds * 60.) as usize {
+            state.step(animated, settings, colliders, 1. / 60.);
+        }
+    }
+
+    #[test]
+    fn test_chain_at_rest_stays_animated() {
+        let animated = hanging_chain();
+        let settings = SpringSettings {
+            gravity: Vec3::ZERO,
+            ..Default::default()
+        };
+        let mut state = SpringChainState::default();
+        simulate(&mut state, &animated, &settings, &[], 1.);
+        for (position, animated) in state.positions.iter().zip(&animated) {
+            assert!(position.abs_diff_eq(*animated, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_chain_lags_behind_and_settles() {
+        let animated = hanging_chain();
+        let settings = SpringSettings {
+            gravity: Vec3::ZERO,
+            ..Default::default()
+        };
+        let mut state = SpringChainState::default();
+        state.step(&animated, &settings, &[], 0.);
+
+        // The whole chain moves sideways at once
+        let moved: Vec<Vec3> = animated.iter().map(|p| *p + Vec3::X).collect();
+        state.step(&moved, &settings, &[], 1. / 60.);
+        assert!(state.positions[0].abs_diff_eq(moved[0], 1e-5));
+        assert!(state.positions[3].x < 0.5);
+
+        simulate(&mut state, &moved, &settings, &[], 5.);
+        assert!(state.positions[3].abs_diff_eq(moved[3], 1e-3));
+        for bone in state.positions.windows(2) {
+            assert!((bone[0].distance(bone[1]) - 0.5).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_colliders_push_the_chain_out() {
+        // A single bone sticking out sideways falls down onto a sphere
+        let animated = [Vec3::ZERO, Vec3::X];
+        let settings = SpringSettings {
+            stiffness: 0.,
+            ..Default::default()
+        };
+        let center = Vec3::NEG_Y;
+        let sphere = SpringColliderShape::Sphere {
+            center,
+            radius: 0.3,
+        }
+        .placed(Transform::IDENTITY);
+
+        let mut state = SpringChainState::default();
+        simulate(&mut state, &animated, &settings, &[sphere], 2.);
+        let tip = state.positions[1];
+        assert!(tip.distance(center) >= 0.3 + settings.radius - 1e-2);
+        assert!(tip.y > -0.99);
+
+        // Without the sphere the bone hangs straight down
+        let mut free = SpringChainState::default();
+        simulate(&mut free, &animated, &settings, &[], 2.);
+        assert!(free.positions[1].abs_diff_eq(Vec3::NEG_Y, 1e-2));
+    }
+
+    #[test]
+    fn test_capsule_push_out() {
+        let capsule = SpringColliderShape::Capsule {
+            start: Vec3::ZERO,
+            end: Vec3::Y,
+            radius: 0.5,
+        }
+        .placed(Transform::from_xyz(1., 0., 0.));
+        let pushed = capsule.push_out(Vec3::new(1.2, 0.5, 0.), 0.);
+        assert!(pushed.abs_diff_eq(Vec3::new(1.5, 0.5, 0.), 1e-5));
+        let outside = Vec3::new(2., 0.5, 0.);
+        assert_eq!(capsule.push_out(outside, 0.), outside);
+    }
+}
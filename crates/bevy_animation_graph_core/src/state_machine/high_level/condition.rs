@@ -0,0 +1,181 @@
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::{animation_graph::PinId, edge_data::DataValue, errors::GraphError};
+
+#[derive(Reflect, Clone, Copy, Default, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum CompareOp {
+    Less,
+    LessEqual,
+    More,
+    MoreEqual,
+    #[default]
+    Equal,
+}
+
+impl CompareOp {
+    pub fn compare(&self, a: f32, b: f32) -> bool {
+        match self {
+            CompareOp::Less => a < b,
+            CompareOp::LessEqual => a <= b,
+            CompareOp::More => a > b,
+            CompareOp::MoreEqual => a >= b,
+            CompareOp::Equal => a == b,
+        }
+    }
+}
+
+/// A check over one of the state machine's input data pins (see [`StateMachine::node_spec`]).
+///
+/// A transition with conditions is triggered automatically by the state machine as soon as all of
+/// its conditions hold, without the need for an event.
+///
+/// [`StateMachine::node_spec`]: super::StateMachine::node_spec
+#[derive(Reflect, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TransitionCondition {
+    /// Holds when `input <op> value`, where `input` is an `F32` pin.
+    F32 {
+        input: PinId,
+        op: CompareOp,
+        value: f32,
+    },
+    /// Holds when the `Bool` pin `input` equals `value`.
+    Bool { input: PinId, value: bool },
+}
+
+impl Default for TransitionCondition {
+    fn default() -> Self {
+        Self::Bool {
+            input: PinId::default(),
+            value: true,
+        }
+    }
+}
+
+impl TransitionCondition {
+    /// Evaluates the condition, using `read_input` to fetch the value of the input pin.
+    pub fn evaluate(
+        &self,
+        read_input: impl FnOnce(&PinId) -> Result<DataValue, GraphError>,
+    ) -> Result<bool, GraphError> {
+        Ok(match self {
+            TransitionCondition::F32 { input, op, value } => {
+                op.compare(read_input(input)?.as_f32()?, *value)
+            }
+            TransitionCondition::Bool { input, value } => read_input(input)?.as_bool()? == *value,
+        })
+    }
+
+    /// Evaluates a conjunction of conditions, short-circuiting on the first one that doesn't hold.
+    ///
+    /// An empty list of conditions never holds, as transitions without conditions should only be
+    /// triggered by events.
+    pub fn all_hold(
+        conditions: &[TransitionCondition],
+        mut read_input: impl FnMut(&PinId) -> Result<DataValue, GraphError>,
+    ) -> Result<bool, GraphError> {
+        if conditions.is_empty() {
+            return Ok(false);
+        }
+
+        for condition in conditions {
+            if !condition.evaluate(&mut read_input)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+
+    use super::*;
+
+    fn inputs() -> HashMap<PinId, DataValue> {
+        [
+            ("speed".to_string(), DataValue::F32(3.5)),
+            ("grounded".to_string(), DataValue::Bool(true)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read(
+        inputs: &HashMap<PinId, DataValue>,
+    ) -> impl FnMut(&PinId) -> Result<DataValue, GraphError> {
+        |pin| {
+            inputs
+                .get(pin)
+                .cloned()
+                .ok_or(GraphError::GlobalInputDataMissing(pin.clone()))
+        }
+    }
+
+    fn speed(op: CompareOp, value: f32) -> TransitionCondition {
+        TransitionCondition::F32 {
+            input: "speed".into(),
+            op,
+            value,
+        }
+    }
+
+    fn grounded(value: bool) -> TransitionCondition {
+        TransitionCondition::Bool {
+            input: "grounded".into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_f32_condition() {
+        let inputs = inputs();
+        assert!(speed(CompareOp::More, 3.).evaluate(read(&inputs)).unwrap());
+        assert!(!speed(CompareOp::Less, 3.).evaluate(read(&inputs)).unwrap());
+        assert!(
+            speed(CompareOp::MoreEqual, 3.5)
+                .evaluate(read(&inputs))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bool_condition() {
+        let inputs = inputs();
+        assert!(grounded(true).evaluate(read(&inputs)).unwrap());
+        assert!(!grounded(false).evaluate(read(&inputs)).unwrap());
+    }
+
+    #[test]
+    fn test_all_hold() {
+        let inputs = inputs();
+        assert!(
+            TransitionCondition::all_hold(
+                &[speed(CompareOp::More, 0.5), grounded(true)],
+                read(&inputs)
+            )
+            .unwrap()
+        );
+        assert!(
+            !TransitionCondition::all_hold(
+                &[speed(CompareOp::More, 5.), grounded(true)],
+                read(&inputs)
+            )
+            .unwrap()
+        );
+        assert!(!TransitionCondition::all_hold(&[], read(&inputs)).unwrap());
+    }
+
+    #[test]
+    fn test_mismatched_input_type() {
+        let inputs = inputs();
+        let condition = TransitionCondition::F32 {
+            input: "grounded".into(),
+            op: CompareOp::Equal,
+            value: 1.,
+        };
+        assert!(condition.evaluate(read(&inputs)).is_err());
+    }
+}
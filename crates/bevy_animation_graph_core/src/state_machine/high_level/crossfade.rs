@@ -0,0 +1,217 @@
+use bevy::{
+    math::curve::{Curve, EaseFunction},
+    platform::collections::HashMap,
+    reflect::Reflect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation_graph::PinId,
+    edge_data::{
+        DataValue,
+        bone_mask::BoneMask,
+        events::{EventQueue, SampledEvent},
+    },
+    interpolation::linear::LinearInterpolator,
+};
+
+/// Easing applied to the blend factor of a crossfade transition.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CrossfadeCurve {
+    #[default]
+    Linear,
+    SmoothStep,
+    SmootherStep,
+    QuadraticIn,
+    QuadraticOut,
+    QuadraticInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+}
+
+impl CrossfadeCurve {
+    pub const ALL: [CrossfadeCurve; 12] = [
+        CrossfadeCurve::Linear,
+        CrossfadeCurve::SmoothStep,
+        CrossfadeCurve::SmootherStep,
+        CrossfadeCurve::QuadraticIn,
+        CrossfadeCurve::QuadraticOut,
+        CrossfadeCurve::QuadraticInOut,
+        CrossfadeCurve::CubicIn,
+        CrossfadeCurve::CubicOut,
+        CrossfadeCurve::CubicInOut,
+        CrossfadeCurve::SineIn,
+        CrossfadeCurve::SineOut,
+        CrossfadeCurve::SineInOut,
+    ];
+
+    /// Maps the fraction of the transition that has elapsed (in `[0, 1]`) to the weight of the
+    /// target state.
+    pub fn sample(&self, t: f32) -> f32 {
+        EaseFunction::from(*self).sample_clamped(t)
+    }
+}
+
+impl From<CrossfadeCurve> for EaseFunction {
+    fn from(value: CrossfadeCurve) -> Self {
+        match value {
+            CrossfadeCurve::Linear => EaseFunction::Linear,
+            CrossfadeCurve::SmoothStep => EaseFunction::SmoothStep,
+            CrossfadeCurve::SmootherStep => EaseFunction::SmootherStep,
+            CrossfadeCurve::QuadraticIn => EaseFunction::QuadraticIn,
+            CrossfadeCurve::QuadraticOut => EaseFunction::QuadraticOut,
+            CrossfadeCurve::QuadraticInOut => EaseFunction::QuadraticInOut,
+            CrossfadeCurve::CubicIn => EaseFunction::CubicIn,
+            CrossfadeCurve::CubicOut => EaseFunction::CubicOut,
+            CrossfadeCurve::CubicInOut => EaseFunction::CubicInOut,
+            CrossfadeCurve::SineIn => EaseFunction::SineIn,
+            CrossfadeCurve::SineOut => EaseFunction::SineOut,
+            CrossfadeCurve::SineInOut => EaseFunction::SineInOut,
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CrossfadeSync {
+    /// Source and target states keep playing independently, both driven by the FSM time.
+    #[default]
+    NoSync,
+    /// The target state is kept at the same normalized time (i.e. percent through its duration)
+    /// as the source state for the whole transition.
+    NormalizedTime,
+}
+
+/// Blends the outputs of the source and target states of a crossfade.
+///
+/// Poses are linearly interpolated, numeric values are lerped and events from both states are
+/// kept with their weights scaled accordingly. Values that cannot be blended are taken from
+/// whichever state has the highest weight.
+pub(crate) fn blend_outputs(
+    mut source: HashMap<PinId, DataValue>,
+    target: HashMap<PinId, DataValue>,
+    weight: f32,
+) -> HashMap<PinId, DataValue> {
+    let mut outputs = HashMap::new();
+
+    for (pin_id, target_value) in target {
+        let value = match source.remove(&pin_id) {
+            Some(source_value) => blend_values(source_value, target_value, weight),
+            None => target_value,
+        };
+        outputs.insert(pin_id, value);
+    }
+
+    // Outputs only produced by the source state are kept until the end of the transition
+    outputs.extend(source);
+
+    outputs
+}
+
+fn blend_values(source: DataValue, target: DataValue, weight: f32) -> DataValue {
+    match (source, target) {
+        (DataValue::F32(a), DataValue::F32(b)) => DataValue::F32(a + (b - a) * weight),
+        (DataValue::Vec2(a), DataValue::Vec2(b)) => DataValue::Vec2(a.lerp(b, weight)),
+        (DataValue::Vec3(a), DataValue::Vec3(b)) => DataValue::Vec3(a.lerp(b, weight)),
+        (DataValue::Quat(a), DataValue::Quat(b)) => DataValue::Quat(a.slerp(b, weight)),
+        (DataValue::Pose(mut a), DataValue::Pose(b)) => {
+            LinearInterpolator {
+                bone_mask: BoneMask::all(),
+            }
+            .interpolate_pose(&mut a, &b, weight);
+            DataValue::Pose(a)
+        }
+        (DataValue::EventQueue(a), DataValue::EventQueue(b)) => {
+            let scale = |queue: EventQueue, factor: f32| {
+                queue.events.into_iter().map(move |event| SampledEvent {
+                    weight: event.weight * factor,
+                    ..event
+                })
+            };
+            DataValue::EventQueue(EventQueue::with_events(
+                scale(a, 1. - weight)
+                    .chain(scale(b, weight))
+                    .collect::<Vec<_>>(),
+            ))
+        }
+        (source, target) => {
+            if weight < 0.5 {
+                source
+            } else {
+                target
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::edge_data::events::AnimationEvent;
+
+    #[test]
+    fn test_curves_span_unit_interval() {
+        for curve in CrossfadeCurve::ALL {
+            assert!(
+                curve.sample(0.).abs() < 1e-5,
+                "{curve:?} does not start at 0"
+            );
+            assert!(
+                (curve.sample(1.) - 1.).abs() < 1e-5,
+                "{curve:?} does not end at 1"
+            );
+        }
+    }
+
+    #[test]
+    fn test_blend_numeric_values() {
+        let blended = blend_values(DataValue::F32(1.), DataValue::F32(3.), 0.25);
+        assert_eq!(blended, DataValue::F32(1.5));
+
+        let blended = blend_values(DataValue::Vec3(Vec3::ZERO), DataValue::Vec3(Vec3::ONE), 0.5);
+        assert_eq!(blended, DataValue::Vec3(Vec3::splat(0.5)));
+    }
+
+    #[test]
+    fn test_blend_non_numeric_values_picks_highest_weight() {
+        assert_eq!(
+            blend_values(DataValue::Bool(false), DataValue::Bool(true), 0.4),
+            DataValue::Bool(false)
+        );
+        assert_eq!(
+            blend_values(DataValue::Bool(false), DataValue::Bool(true), 0.6),
+            DataValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_blend_event_queues_scales_weights() {
+        let event = |name: &str| SampledEvent::instant(AnimationEvent::StringId(name.into()));
+        let blended = blend_values(
+            DataValue::EventQueue(EventQueue::with_events([event("source")])),
+            DataValue::EventQueue(EventQueue::with_events([event("target")])),
+            0.75,
+        )
+        .into_event_queue()
+        .unwrap();
+
+        assert_eq!(blended.events.len(), 2);
+        assert_eq!(blended.events[0].weight, 0.25);
+        assert_eq!(blended.events[1].weight, 0.75);
+    }
+
+    #[test]
+    fn test_blend_outputs_keeps_source_only_pins() {
+        let source = HashMap::from_iter([("a".to_string(), DataValue::F32(0.))]);
+        let target = HashMap::from_iter([("b".to_string(), DataValue::F32(1.))]);
+        let outputs = blend_outputs(source, target, 0.5);
+
+        assert_eq!(outputs.get("a"), Some(&DataValue::F32(0.)));
+        assert_eq!(outputs.get("b"), Some(&DataValue::F32(1.)));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod condition;
+pub mod crossfade;
 pub mod loader;
 pub mod serial;
 
@@ -16,7 +18,14 @@ use super::low_level::{
     LowLevelTransitionId, LowLevelTransitionType,
 };
 use crate::{
-    animation_graph::AnimationGraph, context::spec_context::NodeSpec, errors::GraphValidationError,
+    animation_graph::AnimationGraph,
+    context::spec_context::NodeSpec,
+    errors::GraphValidationError,
+    interpolation::inertialization::{InertializationFalloff, InertializationSettings},
+    state_machine::high_level::{
+        condition::TransitionCondition,
+        crossfade::{CrossfadeCurve, CrossfadeSync},
+    },
 };
 
 /// Unique within a high-level FSM
@@ -80,6 +89,13 @@ pub struct TransitionData {
     /// Whether the target state should be "cleared" when this transition is triggered
     /// Note that this will happen when the transition starts, not when it ends.
     pub reset_target_state: bool,
+    /// If not empty, the transition will be triggered automatically as soon as all conditions
+    /// hold while the FSM is in the source state.
+    pub conditions: Vec<TransitionCondition>,
+    /// Used to break ties when the conditions of several transitions hold at once. Direct
+    /// transitions always take precedence over state transitions; within each group, the
+    /// transition with the highest priority wins.
+    pub priority: i32,
 }
 
 #[derive(Reflect, Debug, Clone, Default)]
@@ -91,6 +107,60 @@ pub enum TransitionKind {
         /// If set, will automatically end the transition after the given time has elapsed
         timed: Option<f32>,
     },
+    /// Blends from the source state into the target state over `duration` seconds, without the
+    /// need for a transition graph.
+    Crossfade {
+        duration: f32,
+        curve: CrossfadeCurve,
+        sync: CrossfadeSync,
+    },
+    /// Switches to the target state right away, and decays the offset between the last pose of
+    /// the source state and the target state over `duration` seconds. Cheaper than a crossfade,
+    /// as only the target state is evaluated.
+    Inertialize {
+        duration: f32,
+        falloff: InertializationFalloff,
+    },
+}
+
+/// Graph, duration and crossfade settings of the intermediate low-level state of a transition
+type TransitionState = (
+    Option<Handle<AnimationGraph>>,
+    Option<f32>,
+    Option<low_level::LlCrossfadeData>,
+);
+
+impl TransitionKind {
+    /// Returns the intermediate low-level state of this transition, or `None` if the transition
+    /// is immediate. Crossfades have no graph, as they are evaluated by the state machine itself.
+    fn transition_state(&self) -> Option<TransitionState> {
+        match self {
+            TransitionKind::Immediate | TransitionKind::Inertialize { .. } => None,
+            TransitionKind::Graph { graph, timed } => Some((Some(graph.clone()), *timed, None)),
+            TransitionKind::Crossfade {
+                duration,
+                curve,
+                sync,
+            } => Some((
+                None,
+                Some(*duration),
+                Some(low_level::LlCrossfadeData {
+                    curve: *curve,
+                    sync: *sync,
+                }),
+            )),
+        }
+    }
+
+    fn inertialization(&self) -> Option<InertializationSettings> {
+        match self {
+            TransitionKind::Inertialize { duration, falloff } => Some(InertializationSettings {
+                duration: *duration,
+                falloff: *falloff,
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Stateful data associated with an FSM node
@@ -241,26 +311,31 @@ impl StateMachine {
         llfsm.node_spec = self.node_spec.clone();
 
         for state in self.states.values() {
-            llfsm
+            let states_with_label = llfsm
                 .hl_states_by_label
                 .entry(state.label.clone())
-                .or_default()
-                .push(state.id);
+                .or_default();
+            states_with_label.push(state.id);
+            // Independent of the iteration order of `self.states`
+            states_with_label.sort();
 
             llfsm.add_state(low_level::LowLevelState {
                 id: LowLevelStateId::HlState(state.id),
-                graph: state.graph.clone(),
+                graph: Some(state.graph.clone()),
                 hl_transition: None,
             });
             if let Some(state_transition) = &state.state_transition {
                 let transition_id = TransitionId::State(state.id);
                 for source_state in self.states.values() {
                     if source_state.id != state.id {
-                        match &state_transition.kind {
-                            TransitionKind::Immediate => {
+                        match state_transition.kind.transition_state() {
+                            None => {
                                 llfsm.add_transition(LowLevelTransition {
                                     id: LowLevelTransitionId::Immediate(transition_id),
                                     ignore_external: state_transition.ignore_external_events,
+                                    inertialization: state_transition.kind.inertialization(),
+                                    conditions: state_transition.conditions.clone(),
+                                    priority: state_transition.priority,
                                     reset_target_state: state_transition.reset_target_state,
                                     source: LowLevelStateId::HlState(source_state.id),
                                     target: LowLevelStateId::HlState(state.id),
@@ -269,20 +344,24 @@ impl StateMachine {
                                     hl_target: state.id,
                                 });
                             }
-                            TransitionKind::Graph { graph, timed } => {
+                            Some((graph, timed, crossfade)) => {
                                 llfsm.add_state(LowLevelState {
                                     id: LowLevelStateId::HlTransition(transition_id),
-                                    graph: graph.clone(),
+                                    graph,
                                     hl_transition: Some(low_level::LlTransitionData {
                                         source: source_state.id,
                                         target: state.id,
                                         hl_transition_id: transition_id,
-                                        timed: *timed,
+                                        timed,
+                                        crossfade,
                                     }),
                                 });
                                 llfsm.add_transition(LowLevelTransition {
                                     id: LowLevelTransitionId::Start(transition_id),
                                     ignore_external: state_transition.ignore_external_events,
+                                    inertialization: None,
+                                    conditions: state_transition.conditions.clone(),
+                                    priority: state_transition.priority,
                                     reset_target_state: state_transition.reset_target_state,
                                     source: LowLevelStateId::HlState(source_state.id),
                                     target: LowLevelStateId::HlTransition(transition_id),
@@ -293,6 +372,9 @@ impl StateMachine {
                                 llfsm.add_transition(LowLevelTransition {
                                     id: LowLevelTransitionId::End(transition_id),
                                     ignore_external: state_transition.ignore_external_events,
+                                    inertialization: None,
+                                    conditions: Vec::new(),
+                                    priority: 0,
                                     reset_target_state: false,
                                     source: LowLevelStateId::HlTransition(transition_id),
                                     target: LowLevelStateId::HlState(state.id),
@@ -309,11 +391,14 @@ impl StateMachine {
 
         for transition in self.transitions.values() {
             let transition_id = TransitionId::Direct(transition.id);
-            match &transition.data.kind {
-                TransitionKind::Immediate => {
+            match transition.data.kind.transition_state() {
+                None => {
                     llfsm.add_transition(LowLevelTransition {
                         id: LowLevelTransitionId::Immediate(transition_id),
                         ignore_external: transition.data.ignore_external_events,
+                        inertialization: transition.data.kind.inertialization(),
+                        conditions: transition.data.conditions.clone(),
+                        priority: transition.data.priority,
                         reset_target_state: transition.data.reset_target_state,
                         source: LowLevelStateId::HlState(transition.source),
                         target: LowLevelStateId::HlState(transition.target),
@@ -322,21 +407,25 @@ impl StateMachine {
                         hl_target: transition.target,
                     });
                 }
-                TransitionKind::Graph { graph, timed } => {
+                Some((graph, timed, crossfade)) => {
                     llfsm.add_state(LowLevelState {
                         id: LowLevelStateId::HlTransition(transition_id),
-                        graph: graph.clone(),
+                        graph,
                         hl_transition: Some(low_level::LlTransitionData {
                             source: transition.source,
                             target: transition.target,
                             hl_transition_id: transition_id,
-                            timed: *timed,
+                            timed,
+                            crossfade,
                         }),
                     });
 
                     llfsm.add_transition(LowLevelTransition {
                         id: LowLevelTransitionId::Start(transition_id),
                         ignore_external: transition.data.ignore_external_events,
+                        inertialization: None,
+                        conditions: transition.data.conditions.clone(),
+                        priority: transition.data.priority,
                         reset_target_state: transition.data.reset_target_state,
                         source: LowLevelStateId::HlState(transition.source),
                         target: LowLevelStateId::HlTransition(transition_id),
@@ -348,6 +437,9 @@ impl StateMachine {
                     llfsm.add_transition(LowLevelTransition {
                         id: LowLevelTransitionId::End(transition_id),
                         ignore_external: transition.data.ignore_external_events,
+                        inertialization: None,
+                        conditions: Vec::new(),
+                        priority: 0,
                         reset_target_state: false,
                         source: LowLevelStateId::HlTransition(transition_id),
                         target: LowLevelStateId::HlState(transition.target),
@@ -5,7 +5,12 @@ use super::{DirectTransitionId, FsmEditorMetadata, State, StateId, StateMachine}
 use crate::{
     context::spec_context::NodeSpec,
     errors::{AssetLoaderError, SavingError},
-    state_machine::high_level::{DirectTransition, TransitionData, TransitionKind},
+    interpolation::inertialization::InertializationFalloff,
+    state_machine::high_level::{
+        DirectTransition, TransitionData, TransitionKind,
+        condition::TransitionCondition,
+        crossfade::{CrossfadeCurve, CrossfadeSync},
+    },
     utils::{loading::TryLoad, normalize_asset_path},
 };
 
@@ -67,6 +72,10 @@ pub struct TransitionDataSerial {
     pub ignore_external_events: bool,
     #[serde(default)]
     pub reset_target_state: bool,
+    #[serde(default)]
+    pub conditions: Vec<TransitionCondition>,
+    #[serde(default)]
+    pub priority: i32,
 }
 
 impl TryFrom<&TransitionData> for TransitionDataSerial {
@@ -77,6 +86,8 @@ impl TryFrom<&TransitionData> for TransitionDataSerial {
             kind: TransitionKindSerial::try_from(&value.kind)?,
             ignore_external_events: value.ignore_external_events,
             reset_target_state: value.reset_target_state,
+            conditions: value.conditions.clone(),
+            priority: value.priority,
         })
     }
 }
@@ -92,6 +103,8 @@ impl TryLoad<TransitionData> for TransitionDataSerial {
             kind: self.kind.try_load(load_context)?,
             ignore_external_events: self.ignore_external_events,
             reset_target_state: self.reset_target_state,
+            conditions: self.conditions.clone(),
+            priority: self.priority,
         })
     }
 }
@@ -104,6 +117,18 @@ pub enum TransitionKindSerial {
         /// If set, will automatically end the transition after the given time has elapsed
         timed: Option<f32>,
     },
+    Crossfade {
+        duration: f32,
+        #[serde(default)]
+        curve: CrossfadeCurve,
+        #[serde(default)]
+        sync: CrossfadeSync,
+    },
+    Inertialize {
+        duration: f32,
+        #[serde(default)]
+        falloff: InertializationFalloff,
+    },
 }
 
 impl TryFrom<&TransitionKind> for TransitionKindSerial {
@@ -121,6 +146,19 @@ impl TryFrom<&TransitionKind> for TransitionKindSerial {
                 ),
                 timed: *timed,
             },
+            TransitionKind::Crossfade {
+                duration,
+                curve,
+                sync,
+            } => Self::Crossfade {
+                duration: *duration,
+                curve: *curve,
+                sync: *sync,
+            },
+            TransitionKind::Inertialize { duration, falloff } => Self::Inertialize {
+                duration: *duration,
+                falloff: *falloff,
+            },
         })
     }
 }
@@ -138,6 +176,21 @@ impl TryLoad<TransitionKind> for TransitionKindSerial {
                 graph: load_context.load(graph),
                 timed: *timed,
             },
+            TransitionKindSerial::Crossfade {
+                duration,
+                curve,
+                sync,
+            } => TransitionKind::Crossfade {
+                duration: *duration,
+                curve: *curve,
+                sync: *sync,
+            },
+            TransitionKindSerial::Inertialize { duration, falloff } => {
+                TransitionKind::Inertialize {
+                    duration: *duration,
+                    falloff: *falloff,
+                }
+            }
         })
     }
 }
@@ -1,19 +1,26 @@
-use std::{borrow::Cow, cmp::Ordering, collections::VecDeque};
+use std::{
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::VecDeque,
+};
 
 use bevy::{
     asset::{Asset, AssetId, Handle},
     log::warn,
-    platform::collections::HashMap,
+    platform::collections::{HashMap, HashSet},
     reflect::Reflect,
 };
 use serde::{Deserialize, Serialize};
 
 use super::high_level;
 use crate::{
-    animation_graph::{AnimationGraph, GraphInputPin, PinId, SourcePin, TargetPin, TimeUpdate},
+    animation_graph::{
+        AnimationGraph, DEFAULT_OUTPUT_POSE, GraphInputPin, PinId, SourcePin, TargetPin, TimeUpdate,
+    },
     context::{
         io_env::{GraphIoEnv, IoOverrides, LayeredIoEnv},
         new_context::{GraphContext, NodeContext},
+        node_states::ReflectGraphStateType,
         spec_context::NodeSpec,
     },
     duration_data::DurationData,
@@ -22,7 +29,12 @@ use crate::{
         events::{AnimationEvent, EventQueue, SampledEvent},
     },
     errors::GraphError,
-    state_machine::high_level::StateId,
+    interpolation::inertialization::{InertializationSettings, PoseHistory, PoseInertialization},
+    state_machine::high_level::{
+        StateId,
+        condition::TransitionCondition,
+        crossfade::{CrossfadeCurve, CrossfadeSync, blend_outputs},
+    },
 };
 
 #[derive(Reflect, Debug, Clone, PartialEq, Eq, Hash)]
@@ -32,7 +44,17 @@ pub enum LowLevelTransitionId {
     Immediate(high_level::TransitionId),
 }
 
-#[derive(Reflect, Debug, Clone, PartialEq, Eq, Hash)]
+impl LowLevelTransitionId {
+    pub fn hl_transition_id(&self) -> high_level::TransitionId {
+        match self {
+            LowLevelTransitionId::Start(id)
+            | LowLevelTransitionId::End(id)
+            | LowLevelTransitionId::Immediate(id) => *id,
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LowLevelStateId {
     HlState(high_level::StateId),
     HlTransition(high_level::TransitionId),
@@ -79,9 +101,21 @@ pub enum FsmBuiltinPin {
 
 /// Stateful data associated with an FSM node
 #[derive(Reflect, Debug, Clone)]
+#[reflect(GraphStateType)]
 pub struct FsmState {
     pub state: LowLevelStateId,
     pub state_entered_time: f32,
+    /// Time at which the source state of the transition in progress was entered. Crossfades
+    /// keep evaluating the source state as if it were still active.
+    pub source_entered_time: f32,
+    /// Set when an inertialized transition has been triggered but the target state has not been
+    /// evaluated yet.
+    pub pending_inertialization: Option<InertializationSettings>,
+    /// Inertialized transitions in progress, by output pin
+    pub inertializations: HashMap<PinId, PoseInertialization>,
+    /// Recent pose outputs by output pin. Only recorded while an inertialized transition can
+    /// start from the current state.
+    pub pose_history: HashMap<PinId, PoseHistory>,
 }
 
 #[derive(Reflect, Debug, Clone)]
@@ -90,21 +124,42 @@ pub struct LlTransitionData {
     pub target: high_level::StateId,
     pub hl_transition_id: high_level::TransitionId,
     pub timed: Option<f32>,
+    /// Set for crossfade transitions, which are evaluated by the state machine itself instead of
+    /// by a transition graph.
+    pub crossfade: Option<LlCrossfadeData>,
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct LlCrossfadeData {
+    pub curve: CrossfadeCurve,
+    pub sync: CrossfadeSync,
 }
 
 /// Specification of a state node in the low-level FSM
 #[derive(Reflect, Debug, Clone)]
 pub struct LowLevelState {
     pub id: LowLevelStateId,
-    pub graph: Handle<AnimationGraph>,
+    /// Graph evaluated while in this state. Crossfade transitions have none, as they are
+    /// evaluated by the state machine itself.
+    pub graph: Option<Handle<AnimationGraph>>,
     pub hl_transition: Option<LlTransitionData>,
 }
 
+impl LowLevelState {
+    fn graph(&self) -> Result<&Handle<AnimationGraph>, GraphError> {
+        self.graph.as_ref().ok_or(GraphError::FSMGraphAssetMissing)
+    }
+}
+
 /// Specification of a transition in the low-level FSM
 #[derive(Reflect, Debug, Clone)]
 pub struct LowLevelTransition {
     pub id: LowLevelTransitionId,
     pub ignore_external: bool,
+    /// Set for immediate transitions that should be inertialized
+    pub inertialization: Option<InertializationSettings>,
+    pub conditions: Vec<TransitionCondition>,
+    pub priority: i32,
     pub reset_target_state: bool,
     pub source: LowLevelStateId,
     pub target: LowLevelStateId,
@@ -122,6 +177,13 @@ pub struct LowLevelStateMachine {
     pub hl_states_by_label: HashMap<String, Vec<high_level::StateId>>,
     pub transitions_by_hl_state_pair:
         HashMap<(high_level::StateId, high_level::StateId), Vec<LowLevelTransitionId>>,
+    /// Transitions with conditions, grouped by high-level source state and sorted by priority.
+    pub conditional_transitions_by_hl_source:
+        HashMap<high_level::StateId, Vec<LowLevelTransitionId>>,
+
+    /// States that an inertialized transition can start from. The pose outputs are recorded
+    /// every frame while in one of them.
+    pub inertialized_sources: HashSet<LowLevelStateId>,
 
     pub start_state: Option<LowLevelStateId>,
     pub node_spec: NodeSpec,
@@ -136,6 +198,8 @@ impl LowLevelStateMachine {
             states: HashMap::new(),
             transitions: HashMap::new(),
             transitions_by_hl_state_pair: HashMap::new(),
+            conditional_transitions_by_hl_source: HashMap::new(),
+            inertialized_sources: HashSet::new(),
             start_state: None,
             node_spec: NodeSpec::default(),
             hl_states_by_label: HashMap::new(),
@@ -147,6 +211,9 @@ impl LowLevelStateMachine {
     }
 
     pub fn add_transition(&mut self, transition: LowLevelTransition) {
+        if transition.inertialization.is_some() {
+            self.inertialized_sources.insert(transition.source.clone());
+        }
         self.transitions
             .insert(transition.id.clone(), transition.clone());
         match transition.id {
@@ -156,13 +223,59 @@ impl LowLevelStateMachine {
                     .entry((transition.hl_source, transition.hl_target))
                     .or_default();
                 vec.push(transition.id.clone());
-                // Direct transitions should come first
-                vec.sort_by_key(|id| self.transitions.get(id).unwrap().transition_type);
+                // Direct transitions should come first, ties are broken by id so that the order
+                // does not depend on the order in which transitions were added
+                vec.sort_by_key(|id| {
+                    (
+                        self.transitions.get(id).unwrap().transition_type,
+                        id.hl_transition_id(),
+                    )
+                });
+
+                if !transition.conditions.is_empty() {
+                    let vec = self
+                        .conditional_transitions_by_hl_source
+                        .entry(transition.hl_source)
+                        .or_default();
+                    vec.push(transition.id.clone());
+                    // Direct transitions first, then by descending priority. The transition id is
+                    // used as a last resort so that the order is deterministic.
+                    vec.sort_by_key(|id| {
+                        let transition = self.transitions.get(id).unwrap();
+                        (
+                            transition.transition_type,
+                            Reverse(transition.priority),
+                            id.hl_transition_id(),
+                        )
+                    });
+                }
             }
             _ => {}
         }
     }
 
+    /// Returns the highest priority transition out of the given state whose conditions hold.
+    fn find_conditional_transition(
+        &self,
+        from_hl_state: StateId,
+        ctx: &NodeContext,
+    ) -> Result<Option<&LowLevelTransition>, GraphError> {
+        let Some(ids) = self
+            .conditional_transitions_by_hl_source
+            .get(&from_hl_state)
+        else {
+            return Ok(None);
+        };
+
+        for transition in ids.iter().filter_map(|id| self.transitions.get(id)) {
+            if TransitionCondition::all_hold(&transition.conditions, |pin| ctx.data_back(pin))? {
+                return Ok(Some(transition));
+            }
+        }
+
+        Ok(None)
+    }
+
     fn find_hl_transition_start(
         &self,
         from_hl_state: StateId,
@@ -176,6 +289,32 @@ impl LowLevelStateMachine {
             .find(|transition| !(transition.ignore_external && allow_external))
     }
 
+    fn initial_state(&self, time: f32) -> FsmState {
+        FsmState {
+            state: self.start_state.clone().unwrap(),
+            state_entered_time: time,
+            source_entered_time: time,
+            pending_inertialization: None,
+            inertializations: HashMap::new(),
+            pose_history: HashMap::new(),
+        }
+    }
+
+    /// Moves the FSM to its start state if the current state no longer exists, e.g. because it
+    /// was removed and the state machine reloaded. Otherwise the FSM stays where it was.
+    fn reset_removed_state(&self, fsm_state: &mut FsmState, time: f32) {
+        if self.states.contains_key(&fsm_state.state) {
+            return;
+        }
+        let Some(start_state) = self.start_state.clone() else {
+            return;
+        };
+        fsm_state.state = start_state;
+        fsm_state.state_entered_time = time;
+        fsm_state.source_entered_time = time;
+        fsm_state.pending_inertialization = None;
+    }
+
     fn trigger_transition(
         &self,
         transition: &LowLevelTransition,
@@ -185,14 +324,17 @@ impl LowLevelStateMachine {
     ) {
         if transition.reset_target_state
             && let ll_target_state_id = LowLevelStateId::HlState(transition.hl_target)
-            && let Some(target_state) = self.states.get(&ll_target_state_id)
+            && let Some(target_graph) = self
+                .states
+                .get(&ll_target_state_id)
+                .and_then(|state| state.graph.as_ref())
         {
-            *should_clear = Some((target_state.graph.id(), ll_target_state_id));
+            *should_clear = Some((target_graph.id(), ll_target_state_id));
         }
-        *fsm_state = FsmState {
-            state: transition.target.clone(),
-            state_entered_time: time,
-        };
+        fsm_state.state = transition.target.clone();
+        fsm_state.source_entered_time = fsm_state.state_entered_time;
+        fsm_state.state_entered_time = time;
+        fsm_state.pending_inertialization = transition.inertialization;
     }
 
     fn handle_event_queue(
@@ -203,10 +345,7 @@ impl LowLevelStateMachine {
         mut ctx: NodeContext,
     ) -> Result<(), GraphError> {
         let time = ctx.time();
-        let fsm_state = ctx.state_mut_or_else(|| FsmState {
-            state: self.start_state.clone().unwrap(),
-            state_entered_time: time,
-        })?;
+        let fsm_state = ctx.state_mut_or_else(|| self.initial_state(time))?;
 
         let mut should_clear: Option<(AssetId<AnimationGraph>, LowLevelStateId)> = None;
 
@@ -272,6 +411,36 @@ impl LowLevelStateMachine {
         Ok(())
     }
 
+    fn handle_conditions(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
+        let time = ctx.time();
+        let current_state = ctx
+            .state_mut_or_else(|| self.initial_state(time))?
+            .state
+            .clone();
+
+        // Conditions are only checked while resting in a state, an ongoing transition must end
+        // before another one can start.
+        let LowLevelStateId::HlState(hl_curr_state_id) = current_state else {
+            return Ok(());
+        };
+
+        let Some(transition) = self.find_conditional_transition(hl_curr_state_id, &ctx)? else {
+            return Ok(());
+        };
+
+        let mut should_clear: Option<(AssetId<AnimationGraph>, LowLevelStateId)> = None;
+        let fsm_state = ctx.state_mut_or_else(|| self.initial_state(time))?;
+        self.trigger_transition(transition, time, fsm_state, &mut should_clear);
+
+        if let Some((graph, state)) = should_clear {
+            ctx.create_child_context(graph, Some(state))
+                .node_states_mut()
+                .clear();
+        }
+
+        Ok(())
+    }
+
     /// Performs a node update
     pub fn update(&self, mut ctx: NodeContext) -> Result<(), GraphError> {
         let input = ctx.time_update_fwd()?;
@@ -286,6 +455,9 @@ impl LowLevelStateMachine {
         });
         ctx.set_time(pred_time);
 
+        let fsm_state = ctx.state_mut_or_else(|| self.initial_state(pred_time))?;
+        self.reset_removed_state(fsm_state, pred_time);
+
         ctx.set_time_update_back(Self::DRIVER_TIME, input);
         let event_queue = ctx
             .data_back(Self::DRIVER_EVENT_QUEUE)?
@@ -293,6 +465,8 @@ impl LowLevelStateMachine {
 
         // First we handle external events (e.g. user requests to change state)
         self.handle_event_queue(event_queue, true, ctx.clone())?;
+        // Then we check for transitions that should trigger based on the FSM inputs
+        self.handle_conditions(ctx.clone())?;
         // Then we trigger a graph update on active graphs
         let inner_eq = self.update_graph(ctx.clone())?;
         // Graph update may generate internal FSM events, we handle those too.
@@ -306,97 +480,147 @@ impl LowLevelStateMachine {
     pub fn update_graph(&self, mut ctx: NodeContext) -> Result<EventQueue, GraphError> {
         let time = ctx.time();
         let fsm_state = ctx.state::<FsmState>()?;
-        let state = self.states.get(&fsm_state.state).unwrap();
-        let graph = ctx
-            .graph_context
-            .resources
-            .animation_graph_assets
-            .get(&state.graph)
-            .ok_or(GraphError::GraphAssetMissing)?;
-
-        let mut io_overrides = IoOverrides::default();
+        let state = self
+            .states
+            .get(&fsm_state.state)
+            .ok_or(GraphError::FSMCurrentStateMissing)?;
 
         let elapsed_time = time - fsm_state.state_entered_time;
-
-        io_overrides
-            .data
-            .insert(FsmBuiltinPin::TimeElapsed.into(), elapsed_time.into());
+        let source_elapsed_time = time - fsm_state.source_entered_time;
+        let io_overrides = Self::state_io_overrides(state, elapsed_time);
 
         let mut driver_event_queue = EventQueue::default();
 
         if let Some(duration) = state.hl_transition.as_ref().and_then(|t| t.timed)
             && duration > 0.
+            && elapsed_time / duration >= 1.
         {
-            let percent = elapsed_time / duration;
-            io_overrides
-                .data
-                .insert(FsmBuiltinPin::PercentThroughDuration.into(), percent.into());
+            driver_event_queue
+                .events
+                .push(SampledEvent::instant(AnimationEvent::EndTransition));
+        }
 
-            if percent >= 1. {
+        let mut outputs = if let Some(transition) = &state.hl_transition
+            && let Some(crossfade) = &transition.crossfade
+        {
+            if transition.timed.is_none_or(|duration| duration <= 0.) {
                 driver_event_queue
                     .events
                     .push(SampledEvent::instant(AnimationEvent::EndTransition));
             }
+            self.evaluate_crossfade(
+                ctx.clone(),
+                transition,
+                crossfade,
+                elapsed_time,
+                source_elapsed_time,
+                io_overrides,
+            )?
+        } else {
+            self.evaluate_state(ctx.clone(), state, io_overrides)?
+        };
+
+        self.inertialize_outputs(&mut ctx, &mut outputs.data)?;
+
+        for (id, value) in outputs.data {
+            ctx.set_data_fwd(id, value);
         }
 
-        let sub_io_env = LayeredIoEnv(
-            Cow::<IoOverrides>::Owned(io_overrides),
-            Cow::<FsmIoEnv>::Owned(FsmIoEnv {
-                node_context: ctx.clone(),
-                state_machine: self,
-                current_state: state.id.clone(),
-                current_state_role: StateRole::Root,
-                state_stack: [].into(),
-            }),
-        );
+        for (pin, value) in outputs.time_updates {
+            ctx.set_time_update_back(pin, value);
+        }
 
-        let sub_ctx = ctx
-            .create_child_context(state.graph.id(), Some(state.id.clone()))
-            .with_io(&sub_io_env);
+        driver_event_queue.extend(outputs.driver_events);
+
+        Ok(driver_event_queue)
+    }
+
+    /// Applies any inertialized transitions in progress to the pose outputs, and records the
+    /// outputs so that later transitions can be inertialized.
+    fn inertialize_outputs(
+        &self,
+        ctx: &mut NodeContext,
+        outputs: &mut HashMap<PinId, DataValue>,
+    ) -> Result<(), GraphError> {
+        if self.inertialized_sources.is_empty() {
+            return Ok(());
+        }
+
+        let time = ctx.time();
+        let delta = time - ctx.prev_time();
+        let culled = ctx.graph_context.culled;
+        let fsm_state = ctx.state_mut_or_else(|| self.initial_state(time))?;
+        let pending = fsm_state.pending_inertialization.take();
+        // The poses of a culled player are incomplete, so they are neither recorded nor
+        // inertialized. Transitions right after the player is unculled snap.
+        let recording = !culled && self.inertialized_sources.contains(&fsm_state.state);
+        let mut pose_history = std::mem::take(&mut fsm_state.pose_history);
+
+        if culled {
+            fsm_state.inertializations.clear();
+        } else {
+            for (pin_id, value) in outputs.iter_mut() {
+                let DataValue::Pose(pose) = value else {
+                    continue;
+                };
+
+                // The transition was triggered since the last frame, so the recorded pose is the
+                // last one output by the source state
+                if let Some(settings) = pending
+                    && let Some(inertialization) = pose_history
+                        .get(pin_id)
+                        .and_then(|history| history.inertialize(pose, settings))
+                {
+                    fsm_state
+                        .inertializations
+                        .insert(pin_id.clone(), inertialization);
+                }
 
-        for (id, _) in graph.io_spec.iter_output_data() {
-            let target_pin = TargetPin::OutputData(id.clone());
-            let value = graph.get_data(target_pin, sub_ctx.clone())?;
-            if id == Self::DRIVER_EVENT_QUEUE {
-                driver_event_queue.extend(value.into_event_queue()?);
-            } else {
-                ctx.set_data_fwd(id, value);
+                if let Some(inertialization) = fsm_state.inertializations.get_mut(pin_id) {
+                    inertialization.apply(pose);
+                    inertialization.advance(delta);
+                }
             }
+
+            fsm_state
+                .inertializations
+                .retain(|_, inertialization| !inertialization.is_finished());
         }
 
-        for input_pin in graph.io_spec.iter_input_times() {
-            if let GraphInputPin::Passthrough(pin) = input_pin {
-                let source_pin = SourcePin::InputTime(input_pin.clone());
-                let value = graph.get_time_update(source_pin, sub_ctx.clone())?;
-                ctx.set_time_update_back(pin, value);
+        if recording {
+            for (pin_id, value) in outputs.iter() {
+                let DataValue::Pose(pose) = value else {
+                    continue;
+                };
+                let recorded = ctx.clone_pose(pose);
+                let history = pose_history.entry(pin_id.clone()).or_default();
+                if let Some(dropped) = history.push(recorded, delta) {
+                    ctx.recycle_pose(dropped);
+                }
+            }
+        } else {
+            for pose in pose_history
+                .drain()
+                .flat_map(|(_, history)| history.into_poses())
+            {
+                ctx.recycle_pose(pose);
             }
         }
 
-        Ok(driver_event_queue)
-    }
+        ctx.state_mut_or_else(|| self.initial_state(time))?
+            .pose_history = pose_history;
 
-    /// Last-resort type time update fetching.
-    pub fn time_update(&self, mut ctx: NodeContext, pin: PinId) -> Result<TimeUpdate, GraphError> {
-        let time = ctx.time();
-        let fsm_state = ctx.state::<FsmState>()?;
-        let state = self.states.get(&fsm_state.state).unwrap();
-        let graph = ctx
-            .graph_context
-            .resources
-            .animation_graph_assets
-            .get(&state.graph)
-            .ok_or(GraphError::GraphAssetMissing)?;
+        Ok(())
+    }
 
+    /// Overrides of the builtin FSM pins for the graph of a state entered `elapsed_time` ago
+    fn state_io_overrides(state: &LowLevelState, elapsed_time: f32) -> IoOverrides {
         let mut io_overrides = IoOverrides::default();
 
-        let elapsed_time = time - fsm_state.state_entered_time;
-
         io_overrides
             .data
             .insert(FsmBuiltinPin::TimeElapsed.into(), elapsed_time.into());
 
-        let mut driver_event_queue = EventQueue::default();
-
         if let Some(duration) = state.hl_transition.as_ref().and_then(|t| t.timed)
             && duration > 0.
         {
@@ -404,14 +628,27 @@ impl LowLevelStateMachine {
             io_overrides
                 .data
                 .insert(FsmBuiltinPin::PercentThroughDuration.into(), percent.into());
-
-            if percent >= 1. {
-                driver_event_queue
-                    .events
-                    .push(SampledEvent::instant(AnimationEvent::EndTransition));
-            }
         }
 
+        io_overrides
+    }
+
+    /// Runs the given closure with the graph of a state and a context to evaluate it in.
+    fn with_state_graph<T>(
+        &self,
+        ctx: &NodeContext,
+        state: &LowLevelState,
+        io_overrides: IoOverrides,
+        f: impl FnOnce(&AnimationGraph, GraphContext) -> Result<T, GraphError>,
+    ) -> Result<T, GraphError> {
+        let graph_handle = state.graph()?;
+        let graph = ctx
+            .graph_context
+            .resources
+            .animation_graph_assets
+            .get(graph_handle)
+            .ok_or(GraphError::GraphAssetMissing)?;
+
         let sub_io_env = LayeredIoEnv(
             Cow::<IoOverrides>::Owned(io_overrides),
             Cow::<FsmIoEnv>::Owned(FsmIoEnv {
@@ -424,13 +661,160 @@ impl LowLevelStateMachine {
         );
 
         let sub_ctx = ctx
-            .create_child_context(state.graph.id(), Some(state.id.clone()))
+            .create_child_context(graph_handle.id(), Some(state.id.clone()))
             .with_io(&sub_io_env);
 
-        let source_pin = SourcePin::InputTime(GraphInputPin::Passthrough(pin));
-        let time_update = graph.get_time_update(source_pin, sub_ctx.clone())?;
+        f(graph, sub_ctx)
+    }
+
+    fn evaluate_state(
+        &self,
+        ctx: NodeContext,
+        state: &LowLevelState,
+        io_overrides: IoOverrides,
+    ) -> Result<StateGraphOutputs, GraphError> {
+        self.with_state_graph(&ctx, state, io_overrides, |graph, sub_ctx| {
+            let mut outputs = StateGraphOutputs::default();
+
+            for (id, _) in graph.io_spec.iter_output_data() {
+                let target_pin = TargetPin::OutputData(id.clone());
+                let value = graph.get_data(target_pin, sub_ctx.clone())?;
+                if id == Self::DRIVER_EVENT_QUEUE {
+                    outputs.driver_events.extend(value.into_event_queue()?);
+                } else {
+                    outputs.data.insert(id.clone(), value);
+                }
+            }
+
+            for input_pin in graph.io_spec.iter_input_times() {
+                if let GraphInputPin::Passthrough(pin) = input_pin {
+                    let source_pin = SourcePin::InputTime(input_pin.clone());
+                    let value = graph.get_time_update(source_pin, sub_ctx.clone())?;
+                    outputs.time_updates.push((pin.clone(), value));
+                }
+            }
+
+            Ok(outputs)
+        })
+    }
+
+    fn state_duration(
+        &self,
+        ctx: &NodeContext,
+        state: &LowLevelState,
+    ) -> Result<DurationData, GraphError> {
+        self.with_state_graph(ctx, state, IoOverrides::default(), |graph, sub_ctx| {
+            // Graphs without a time output simply have no duration
+            Ok(graph
+                .get_duration(TargetPin::OutputTime, sub_ctx)
+                .ok()
+                .flatten())
+        })
+    }
+
+    /// Evaluates both the source and target states of a crossfade transition and blends their
+    /// outputs.
+    fn evaluate_crossfade(
+        &self,
+        ctx: NodeContext,
+        transition: &LlTransitionData,
+        crossfade: &LlCrossfadeData,
+        elapsed_time: f32,
+        source_elapsed_time: f32,
+        io_overrides: IoOverrides,
+    ) -> Result<StateGraphOutputs, GraphError> {
+        let source = self
+            .states
+            .get(&LowLevelStateId::HlState(transition.source))
+            .ok_or(GraphError::FSMCurrentStateMissing)?;
+        let target = self
+            .states
+            .get(&LowLevelStateId::HlState(transition.target))
+            .ok_or(GraphError::FSMCurrentStateMissing)?;
+
+        let weight = match transition.timed {
+            Some(duration) if duration > 0. => crossfade.curve.sample(elapsed_time / duration),
+            _ => 1.,
+        };
+
+        // The source state keeps running as if it were still active
+        let source_outputs = self.evaluate_state(
+            ctx.clone(),
+            source,
+            Self::state_io_overrides(source, source_elapsed_time),
+        )?;
+
+        let mut target_overrides = io_overrides;
+        if crossfade.sync == CrossfadeSync::NormalizedTime {
+            target_overrides.time =
+                self.synced_time_update(&ctx, source, &source_outputs, target)?;
+        }
+        let target_outputs = self.evaluate_state(ctx, target, target_overrides)?;
+
+        Ok(StateGraphOutputs {
+            data: blend_outputs(source_outputs.data, target_outputs.data, weight),
+            // Only the target state can drive the state machine, as we are already leaving the
+            // source state
+            driver_events: target_outputs.driver_events,
+            time_updates: target_outputs.time_updates,
+        })
+    }
+
+    /// Computes a time update that puts the target state at the same normalized time as the
+    /// source state.
+    fn synced_time_update(
+        &self,
+        ctx: &NodeContext,
+        source: &LowLevelState,
+        source_outputs: &StateGraphOutputs,
+        target: &LowLevelState,
+    ) -> Result<Option<TimeUpdate>, GraphError> {
+        let Some(source_time) = source_outputs
+            .data
+            .get(DEFAULT_OUTPUT_POSE)
+            .and_then(|value| value.as_pose().ok())
+            .map(|pose| pose.timestamp)
+        else {
+            return Ok(None);
+        };
+
+        let source_duration = self.state_duration(ctx, source)?;
+        let target_duration = self.state_duration(ctx, target)?;
 
-        Ok(time_update)
+        Ok(match (source_duration, target_duration) {
+            (Some(source_duration), Some(target_duration)) if source_duration > 0. => Some(
+                TimeUpdate::Absolute(source_time / source_duration * target_duration),
+            ),
+            _ => None,
+        })
+    }
+
+    /// Last-resort type time update fetching.
+    pub fn time_update(&self, mut ctx: NodeContext, pin: PinId) -> Result<TimeUpdate, GraphError> {
+        let time = ctx.time();
+        let fsm_state = ctx.state::<FsmState>()?;
+        let mut state = self
+            .states
+            .get(&fsm_state.state)
+            .ok_or(GraphError::FSMCurrentStateMissing)?;
+
+        // Crossfades have no graph of their own, the target state drives time
+        if let Some(transition) = &state.hl_transition
+            && transition.crossfade.is_some()
+        {
+            state = self
+                .states
+                .get(&LowLevelStateId::HlState(transition.target))
+                .ok_or(GraphError::FSMCurrentStateMissing)?;
+        }
+
+        let elapsed_time = time - fsm_state.state_entered_time;
+        let io_overrides = Self::state_io_overrides(state, elapsed_time);
+
+        self.with_state_graph(&ctx, state, io_overrides, |graph, sub_ctx| {
+            let source_pin = SourcePin::InputTime(GraphInputPin::Passthrough(pin));
+            graph.get_time_update(source_pin, sub_ctx)
+        })
     }
 
     fn get_source(&self, state: &LowLevelStateId) -> Result<LowLevelStateId, GraphError> {
@@ -450,6 +834,14 @@ impl LowLevelStateMachine {
     }
 }
 
+/// Outputs of a state graph that the FSM node needs to forward.
+#[derive(Default)]
+struct StateGraphOutputs {
+    data: HashMap<PinId, DataValue>,
+    driver_events: EventQueue,
+    time_updates: Vec<(PinId, TimeUpdate)>,
+}
+
 #[derive(Clone)]
 pub struct FsmIoEnv<'a> {
     /// The context at the FSM node
@@ -482,12 +874,12 @@ impl<'a> FsmIoEnv<'a> {
         next_state: LowLevelStateId,
         next_state_role: StateRole,
     ) -> Result<DataValue, GraphError> {
-        let graph_handle = &self
+        let graph_handle = self
             .state_machine
             .states
             .get(&next_state)
             .expect(Self::MISSING_STATE_ERROR_MESSAGE)
-            .graph;
+            .graph()?;
 
         let graph = self
             .node_context
@@ -534,12 +926,12 @@ impl<'a> FsmIoEnv<'a> {
         state: LowLevelStateId,
         next_state_role: StateRole,
     ) -> Result<DurationData, GraphError> {
-        let graph_handle = &self
+        let graph_handle = self
             .state_machine
             .states
             .get(&state)
             .expect(Self::MISSING_STATE_ERROR_MESSAGE)
-            .graph;
+            .graph()?;
 
         let graph = self
             .node_context
@@ -579,12 +971,12 @@ impl<'a> FsmIoEnv<'a> {
     fn state_time_update_fwd(&self, ctx: &GraphContext) -> Result<TimeUpdate, GraphError> {
         let mut next_state_stack = self.state_stack.clone();
         if let Some((next_state, next_state_role)) = next_state_stack.pop_back() {
-            let graph_handle = &self
+            let graph_handle = self
                 .state_machine
                 .states
                 .get(&next_state)
                 .expect(Self::MISSING_STATE_ERROR_MESSAGE)
-                .graph;
+                .graph()?;
 
             let graph = self
                 .node_context
@@ -675,3 +1067,109 @@ pub enum StateRole {
     Target,
     Root,
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::state_machine::high_level::{DirectTransitionId, TransitionId};
+
+    fn transition(id: u128, transition_type: LowLevelTransitionType) -> LowLevelTransition {
+        let source = StateId::from(Uuid::from_u128(100));
+        let target = StateId::from(Uuid::from_u128(101));
+        LowLevelTransition {
+            id: LowLevelTransitionId::Start(TransitionId::Direct(DirectTransitionId::from(
+                Uuid::from_u128(id),
+            ))),
+            ignore_external: false,
+            inertialization: None,
+            conditions: Vec::new(),
+            priority: 0,
+            reset_target_state: false,
+            source: LowLevelStateId::HlState(source),
+            target: LowLevelStateId::HlState(target),
+            transition_type,
+            hl_source: source,
+            hl_target: target,
+        }
+    }
+
+    #[test]
+    fn test_transition_order_does_not_depend_on_insertion_order() {
+        let transitions = [
+            transition(3, LowLevelTransitionType::State),
+            transition(2, LowLevelTransitionType::Direct),
+            transition(1, LowLevelTransitionType::State),
+        ];
+
+        let mut forward = LowLevelStateMachine::new();
+        let mut backward = LowLevelStateMachine::new();
+        for transition in transitions.iter().cloned() {
+            forward.add_transition(transition);
+        }
+        for transition in transitions.iter().rev().cloned() {
+            backward.add_transition(transition);
+        }
+
+        let order = |fsm: &LowLevelStateMachine| {
+            fsm.transitions_by_hl_state_pair
+                .values()
+                .next()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(order(&forward), order(&backward));
+        assert_eq!(
+            order(&forward),
+            [
+                transitions[1].id.clone(),
+                transitions[2].id.clone(),
+                transitions[0].id.clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removed_current_state_resets_to_start_state() {
+        let state = |id| LowLevelStateId::HlState(StateId::from(Uuid::from_u128(id)));
+        let mut fsm = LowLevelStateMachine::new();
+        for id in [1, 2] {
+            fsm.add_state(LowLevelState {
+                id: state(id),
+                graph: None,
+                hl_transition: None,
+            });
+        }
+        fsm.start_state = Some(state(1));
+
+        let mut fsm_state = fsm.initial_state(0.);
+        fsm_state.state = state(2);
+        fsm_state.state_entered_time = 1.;
+        fsm.reset_removed_state(&mut fsm_state, 3.);
+        assert_eq!(fsm_state.state, state(2));
+        assert_eq!(fsm_state.state_entered_time, 1.);
+
+        // State 2 was removed from the reloaded state machine
+        fsm.states.remove(&state(2));
+        fsm.reset_removed_state(&mut fsm_state, 3.);
+        assert_eq!(fsm_state.state, state(1));
+        assert_eq!(fsm_state.state_entered_time, 3.);
+    }
+
+    #[test]
+    fn test_only_sources_of_inertialized_transitions_record_poses() {
+        let mut fsm = LowLevelStateMachine::new();
+        fsm.add_transition(transition(1, LowLevelTransitionType::State));
+        assert!(fsm.inertialized_sources.is_empty());
+
+        let inertialized = LowLevelTransition {
+            inertialization: Some(InertializationSettings::default()),
+            ..transition(2, LowLevelTransitionType::Direct)
+        };
+        let (source, target) = (inertialized.source.clone(), inertialized.target.clone());
+        fsm.add_transition(inertialized);
+        assert!(fsm.inertialized_sources.contains(&source));
+        assert!(!fsm.inertialized_sources.contains(&target));
+    }
+}
@@ -4,32 +4,22 @@ pub mod serial;
 use self::config::SymmetryConfig;
 use crate::{
     errors::GraphError,
-    pose::{BonePose, Pose, RootMotionDelta},
+    pose::{Pose, RootMotionDelta},
     skeleton::Skeleton,
 };
 
-fn flip_bone_pose(val: &BonePose, config: &SymmetryConfig) -> BonePose {
-    BonePose {
-        rotation: val.rotation.map(|v| config.mode.apply_quat(v)),
-        translation: val.translation.map(|v| config.mode.apply_position(v)),
-        scale: val.scale,
-        weights: val.weights.clone(),
-    }
-}
-
 pub fn flip_pose(
     val: &Pose,
     config: &SymmetryConfig,
     skeleton: &Skeleton,
 ) -> Result<Pose, GraphError> {
-    let mut out = Pose::default();
-    for (bone_id, bone_index) in val.paths.iter() {
-        let channel = flip_bone_pose(&val.bones[*bone_index], config);
+    let mut out = Pose::with_layout(val.layout().clone());
+    for (bone_index, bone_id) in val.iter_bones() {
         // TODO: Make flipped return a Result type, so we can gracefully fail if no match for
         // id
         let path = skeleton
-            .id_to_path(*bone_id)
-            .ok_or(GraphError::BoneIdHasNoPath(*bone_id))?;
+            .id_to_path(bone_id)
+            .ok_or(GraphError::BoneIdHasNoPath(bone_id))?;
         let new_path = config.name_mapper.flip(&path);
         let new_id = new_path.id();
 
@@ -39,7 +29,19 @@ pub fn flip_pose(
             return Err(GraphError::SymmetryNoMatchForBone(new_path.clone()));
         }
 
-        out.add_bone(channel, new_id);
+        let new_index = out.insert_bone(new_id);
+        if let Some(rotation) = val.rotation(bone_index) {
+            out.set_rotation(new_index, config.mode.apply_quat(rotation));
+        }
+        if let Some(translation) = val.translation(bone_index) {
+            out.set_translation(new_index, config.mode.apply_position(translation));
+        }
+        if let Some(scale) = val.scale(bone_index) {
+            out.set_scale(new_index, scale);
+        }
+        if let Some(weights) = val.weights(bone_index) {
+            out.set_weights(new_index, weights);
+        }
     }
     out.skeleton = val.skeleton.clone();
     out.timestamp = val.timestamp;
@@ -9,10 +9,14 @@ use bevy::{
 use crate::{
     animated_scene::AnimatedSceneInstance,
     animation_clip::EntityPath,
-    animation_graph::TimeUpdate,
+    animation_graph::{PinId, TimeUpdate},
     animation_graph_player::{AnimationGraphPlayer, PlaybackState},
     context::system_resources::SystemResources,
+    deterministic::{DeterministicEvaluation, frame_delta},
+    edge_data::{DataValue, events::SampledEvent},
+    lod::{AnimationLod, AnimationLodLevel, AnimationLodSettings},
     pose::BoneId,
+    shared_evaluation::SharedEvaluations,
 };
 
 /// Component that receives root motion deltas each frame from the animation system.
@@ -104,7 +108,186 @@ fn extract_delta(
     }
 }
 
-fn build_entity_map(root_entity: Entity, resources: &SystemResources) -> HashMap<BoneId, Entity> {
+/// Component that forwards the events output by an [`AnimationGraphPlayer`] to the ECS.
+///
+/// Add this component to an entity with an [`AnimationGraphPlayer`]. Every frame, the built-in
+/// [`forward_animation_events`] system goes through all event queue outputs of the player and,
+/// for each [`SampledEvent`] that passes the filters below, triggers an [`AnimationGraphEvent`]
+/// targeting the player entity and writes it as a message.
+///
+/// # Example
+/// ```rust,ignore
+/// commands
+///     .entity(player_entity)
+///     .insert(ForwardAnimationEvents {
+///         tracks: Some(vec!["footsteps".into()]),
+///         ..Default::default()
+///     })
+///     .observe(|event: On<AnimationGraphEvent>| {
+///         info!("{:?}", event.event.event);
+///     });
+/// ```
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Default)]
+pub struct ForwardAnimationEvents {
+    /// If set, only events sampled from these markup tracks are forwarded. Events that do not
+    /// come from a markup track are dropped.
+    pub tracks: Option<Vec<String>>,
+    /// Events with a lower weight than this are dropped. Useful to ignore events from animations
+    /// that are mostly blended out.
+    pub min_weight: f32,
+    /// If enabled, a duration event from a markup track, which is sampled over several
+    /// consecutive frames, is only forwarded twice: with [`AnimationEventPhase::Enter`] on the
+    /// first frame and with [`AnimationEventPhase::Exit`] on the first frame it is no longer
+    /// sampled. Instant events, which do not come from a markup track, and every event when this
+    /// is disabled are forwarded every time they are sampled with [`AnimationEventPhase::Sampled`].
+    pub dedupe: bool,
+    /// Events forwarded in the last frame, used for deduplication
+    active: Vec<(PinId, SampledEvent)>,
+}
+
+impl Default for ForwardAnimationEvents {
+    fn default() -> Self {
+        Self {
+            tracks: None,
+            min_weight: 0.,
+            dedupe: true,
+            active: Vec::new(),
+        }
+    }
+}
+
+impl ForwardAnimationEvents {
+    fn accepts(&self, event: &SampledEvent) -> bool {
+        event.weight >= self.min_weight
+            && self.tracks.as_ref().is_none_or(|tracks| {
+                event
+                    .track
+                    .as_ref()
+                    .is_some_and(|track| tracks.contains(track))
+            })
+    }
+
+    /// Filters the event outputs of a player, returning the events to forward this frame.
+    fn process(
+        &mut self,
+        outputs: &HashMap<PinId, DataValue>,
+    ) -> Vec<(PinId, SampledEvent, AnimationEventPhase)> {
+        let mut pins: Vec<_> = outputs.keys().collect();
+        // Keep the order in which events are forwarded stable across frames
+        pins.sort();
+
+        let sampled: Vec<(PinId, SampledEvent)> = pins
+            .into_iter()
+            .filter_map(|pin| Some((pin, outputs.get(pin)?.as_event_queue().ok()?)))
+            .flat_map(|(pin, queue)| {
+                queue
+                    .events
+                    .iter()
+                    .filter(|event| self.accepts(event))
+                    .map(|event| (pin.clone(), event.clone()))
+            })
+            .collect();
+
+        if !self.dedupe {
+            return sampled
+                .into_iter()
+                .map(|(pin, event)| (pin, event, AnimationEventPhase::Sampled))
+                .collect();
+        }
+
+        let is_same = |(pin_a, a): &(PinId, SampledEvent), (pin_b, b): &(PinId, SampledEvent)| {
+            pin_a == pin_b && a.event == b.event && a.track == b.track
+        };
+
+        let mut forwarded = Vec::new();
+
+        for active in self.active.iter() {
+            if !sampled.iter().any(|sampled| is_same(active, sampled)) {
+                forwarded.push((
+                    active.0.clone(),
+                    active.1.clone(),
+                    AnimationEventPhase::Exit,
+                ));
+            }
+        }
+
+        let mut active = Vec::new();
+        for sampled in sampled {
+            // Instant events have no duration to pair an exit with
+            if sampled.1.track.is_none() {
+                forwarded.push((sampled.0, sampled.1, AnimationEventPhase::Sampled));
+                continue;
+            }
+            // The same event may be sampled more than once in a single frame, e.g. when blending
+            // two animations with the same markup
+            if active.iter().any(|other| is_same(other, &sampled)) {
+                continue;
+            }
+            if !self.active.iter().any(|other| is_same(other, &sampled)) {
+                forwarded.push((
+                    sampled.0.clone(),
+                    sampled.1.clone(),
+                    AnimationEventPhase::Enter,
+                ));
+            }
+            active.push(sampled);
+        }
+        self.active = active;
+
+        forwarded
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnimationEventPhase {
+    /// The event was sampled this frame. Used for instant events, and for every event when
+    /// deduplication is disabled.
+    Sampled,
+    /// The event was sampled this frame, but not in the previous one
+    Enter,
+    /// The event was sampled in the previous frame, but not in this one
+    Exit,
+}
+
+/// An event output by an animation graph, forwarded to the ECS by [`forward_animation_events`].
+///
+/// It is both triggered for observers of the player entity and written as a message.
+#[derive(EntityEvent, Message, Reflect, Clone, Debug)]
+pub struct AnimationGraphEvent {
+    /// Entity with the [`AnimationGraphPlayer`] that output the event
+    pub entity: Entity,
+    /// Graph output pin the event was read from
+    pub pin: PinId,
+    pub event: SampledEvent,
+    pub phase: AnimationEventPhase,
+}
+
+/// System that forwards the events output by animation players with a
+/// [`ForwardAnimationEvents`] component, as configured in that component.
+pub fn forward_animation_events(
+    mut commands: Commands,
+    mut players: Query<(Entity, &AnimationGraphPlayer, &mut ForwardAnimationEvents)>,
+    mut messages: MessageWriter<AnimationGraphEvent>,
+) {
+    for (entity, player, mut forwarding) in &mut players {
+        for (pin, event, phase) in forwarding.process(player.get_outputs()) {
+            let event = AnimationGraphEvent {
+                entity,
+                pin,
+                event,
+                phase,
+            };
+            messages.write(event.clone());
+            commands.trigger(event);
+        }
+    }
+}
+
+pub(crate) fn build_entity_map(
+    root_entity: Entity,
+    resources: &SystemResources,
+) -> HashMap<BoneId, Entity> {
     let mut entity_map = HashMap::default();
 
     let root_name = resources.names_query.get(root_entity).unwrap();
@@ -145,15 +328,31 @@ fn build_entity_map(root_entity: Entity, resources: &SystemResources) -> HashMap
 #[allow(clippy::too_many_arguments)]
 pub fn animation_player(
     time: Res<Time>,
-    mut animation_players: Query<(Entity, &mut AnimationGraphPlayer)>,
+    deterministic: Option<Res<DeterministicEvaluation>>,
+    mut animation_players: Query<(Entity, &mut AnimationGraphPlayer, Option<&mut AnimationLod>)>,
+    lod_settings: Res<AnimationLodSettings>,
+    shared_evaluations: Res<SharedEvaluations>,
     sysres: SystemResources,
 ) {
-    animation_players.par_iter_mut().for_each(|(root, player)| {
-        run_animation_player(root, player, &time, &sysres);
-    });
-    animation_players.par_iter_mut().for_each(|(root, player)| {
-        debug_draw_animation_players(player, root, &sysres);
-    });
+    let delta = frame_delta(&time, deterministic.as_deref());
+    animation_players
+        .par_iter_mut()
+        .for_each(|(root, player, lod)| {
+            if shared_evaluations.is_shared(root) {
+                return;
+            }
+            // Levels of detail depend on the camera, which may differ between peers
+            let lod = lod.filter(|_| deterministic.is_none()).and_then(|lod| {
+                let level = lod_settings.level(lod.level)?;
+                Some((lod, level))
+            });
+            run_animation_player(root, player, lod, delta, &sysres);
+        });
+    animation_players
+        .par_iter_mut()
+        .for_each(|(root, player, _)| {
+            debug_draw_animation_players(player, root, &sysres);
+        });
 }
 
 /// System that will draw deferred gizmo commands called during graph evaluation
@@ -171,7 +370,8 @@ pub fn animation_player_deferred_gizmos(
 pub fn run_animation_player(
     root: Entity,
     mut player: Mut<AnimationGraphPlayer>,
-    time: &Time,
+    lod: Option<(Mut<AnimationLod>, &AnimationLodLevel)>,
+    delta: f32,
     system_resources: &SystemResources,
 ) {
     let _run_animation_player_span = info_span!("run_animation_player").entered();
@@ -188,10 +388,30 @@ pub fn run_animation_player(
     }
 
     if !player.is_paused() {
-        player.queue_time_update(TimeUpdate::Delta(time.delta_secs()));
+        player.queue_time_update(TimeUpdate::Delta(delta));
     }
 
-    {
+    if let Some((mut lod, level)) = lod {
+        if !lod.should_update(level) {
+            // The queued time update keeps accumulating until the next evaluation
+            let pose = lod.interpolated_pose(level);
+            player.hold_outputs(pose);
+            return;
+        }
+
+        {
+            let _update_span = info_span!("player_update").entered();
+            player.update(system_resources, root);
+        }
+
+        let skeleton = system_resources.skeleton_assets.get(player.skeleton());
+        if player.is_culled() {
+            // The held pose must not be interpolated from once the player is unculled
+            lod.reset_interpolation();
+        } else if let Some(pose) = player.default_output_pose_mut() {
+            lod.process_evaluated(pose, level, skeleton);
+        }
+    } else {
         let _update_span = info_span!("player_update").entered();
         player.update(system_resources, root);
     }
@@ -215,25 +435,27 @@ pub fn apply_animation_to_targets(
         let Ok(player) = graph_players.get(animated_by.0) else {
             continue;
         };
+        if player.is_culled() {
+            continue;
+        }
         let Some(pose) = player.get_default_output_pose() else {
             continue;
         };
 
-        let Some(bone_index) = pose.paths.get(&target_bone_id) else {
+        let Some(bone_index) = pose.bone_index(&target_bone_id) else {
             continue;
         };
 
-        let bone_pose = &pose.bones[*bone_index];
-        if let Some(rotation) = bone_pose.rotation {
+        if let Some(rotation) = pose.rotation(bone_index) {
             target_transform.rotation = rotation;
         }
-        if let Some(translation) = bone_pose.translation {
+        if let Some(translation) = pose.translation(bone_index) {
             target_transform.translation = translation;
         }
-        if let Some(scale) = bone_pose.scale {
+        if let Some(scale) = pose.scale(bone_index) {
             target_transform.scale = scale;
         }
-        if let Some(weights) = &bone_pose.weights
+        if let Some(weights) = pose.weights(bone_index)
             && let Some(mut morphs) = target_morphs
         {
             apply_morph_weights(morphs.weights_mut(), weights);
@@ -257,3 +479,85 @@ fn apply_morph_weights(weights: &mut [f32], new_weights: &[f32]) {
         *morph_weight = *keyframe;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge_data::events::{AnimationEvent, EventQueue};
+
+    fn event(name: &str, track: Option<&str>, weight: f32) -> SampledEvent {
+        SampledEvent {
+            event: AnimationEvent::StringId(name.into()),
+            weight,
+            percentage: 0.5,
+            track: track.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn outputs(events: impl Into<Vec<SampledEvent>>) -> HashMap<PinId, DataValue> {
+        HashMap::from_iter([(
+            "events".to_string(),
+            DataValue::EventQueue(EventQueue::with_events(events)),
+        )])
+    }
+
+    fn phases(
+        forwarded: &[(PinId, SampledEvent, AnimationEventPhase)],
+    ) -> Vec<AnimationEventPhase> {
+        forwarded.iter().map(|(_, _, phase)| *phase).collect()
+    }
+
+    #[test]
+    fn test_forward_events_filters_by_track_and_weight() {
+        let mut forwarding = ForwardAnimationEvents {
+            tracks: Some(vec!["footsteps".into()]),
+            min_weight: 0.5,
+            dedupe: false,
+            ..Default::default()
+        };
+
+        let forwarded = forwarding.process(&outputs([
+            event("left", Some("footsteps"), 1.),
+            event("right", Some("footsteps"), 0.2),
+            event("hit", Some("combat"), 1.),
+            event("untracked", None, 1.),
+        ]));
+
+        assert_eq!(forwarded.len(), 1);
+        assert_eq!(forwarded[0].1, event("left", Some("footsteps"), 1.));
+        assert_eq!(forwarded[0].2, AnimationEventPhase::Sampled);
+    }
+
+    #[test]
+    fn test_forward_events_dedupes_to_enter_and_exit() {
+        let mut forwarding = ForwardAnimationEvents::default();
+        let step = event("step", Some("footsteps"), 1.);
+
+        let forwarded = forwarding.process(&outputs([step.clone(), step.clone()]));
+        assert_eq!(phases(&forwarded), [AnimationEventPhase::Enter]);
+
+        let forwarded = forwarding.process(&outputs([step.clone()]));
+        assert!(forwarded.is_empty());
+
+        let forwarded = forwarding.process(&outputs([]));
+        assert_eq!(phases(&forwarded), [AnimationEventPhase::Exit]);
+        assert_eq!(forwarded[0].1, step);
+    }
+
+    #[test]
+    fn test_forward_events_does_not_dedupe_instant_events() {
+        let mut forwarding = ForwardAnimationEvents::default();
+        let hit = event("hit", None, 1.);
+
+        let forwarded = forwarding.process(&outputs([hit.clone()]));
+        assert_eq!(phases(&forwarded), [AnimationEventPhase::Sampled]);
+
+        let forwarded = forwarding.process(&outputs([hit.clone()]));
+        assert_eq!(phases(&forwarded), [AnimationEventPhase::Sampled]);
+        assert_eq!(forwarded[0].1, hit);
+
+        let forwarded = forwarding.process(&outputs([]));
+        assert!(forwarded.is_empty());
+    }
+}
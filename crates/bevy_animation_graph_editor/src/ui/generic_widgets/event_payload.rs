@@ -0,0 +1,122 @@
+use bevy::math::{Quat, Vec2, Vec3};
+use bevy_animation_graph::core::{animation_clip::EntityPath, edge_data::events::EventPayload};
+
+use crate::ui::generic_widgets::{
+    entity_path::EntityPathWidget, picker::PickerWidget, quat::QuatWidget, vec2::Vec2Widget,
+    vec3::Vec3Widget,
+};
+
+pub struct EventPayloadWidget<'a> {
+    pub payload: &'a mut EventPayload,
+    pub id_hash: egui::Id,
+}
+
+impl<'a> EventPayloadWidget<'a> {
+    pub fn new_salted(payload: &'a mut EventPayload, salt: impl std::hash::Hash) -> Self {
+        Self {
+            payload,
+            id_hash: egui::Id::new(salt),
+        }
+    }
+}
+
+const VARIANTS: [&str; 9] = [
+    "None",
+    "F32",
+    "Bool",
+    "Vec2",
+    "Vec3",
+    "Quat",
+    "String",
+    "EntityPath",
+    "Reflected",
+];
+
+fn variant_name(payload: &EventPayload) -> &'static str {
+    match payload {
+        EventPayload::None => "None",
+        EventPayload::F32(_) => "F32",
+        EventPayload::Bool(_) => "Bool",
+        EventPayload::Vec2(_) => "Vec2",
+        EventPayload::Vec3(_) => "Vec3",
+        EventPayload::Quat(_) => "Quat",
+        EventPayload::String(_) => "String",
+        EventPayload::EntityPath(_) => "EntityPath",
+        EventPayload::Reflected { .. } => "Reflected",
+    }
+}
+
+fn default_variant(name: &str) -> EventPayload {
+    match name {
+        "F32" => EventPayload::F32(0.),
+        "Bool" => EventPayload::Bool(false),
+        "Vec2" => EventPayload::Vec2(Vec2::ZERO),
+        "Vec3" => EventPayload::Vec3(Vec3::ZERO),
+        "Quat" => EventPayload::Quat(Quat::IDENTITY),
+        "String" => EventPayload::String(String::new()),
+        "EntityPath" => EventPayload::EntityPath(EntityPath::default()),
+        "Reflected" => EventPayload::Reflected {
+            type_path: String::new(),
+            ron: String::new(),
+        },
+        _ => EventPayload::None,
+    }
+}
+
+impl<'a> egui::Widget for EventPayloadWidget<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.push_id(self.id_hash, |ui| {
+            let mut selected = variant_name(self.payload);
+            let mut response = PickerWidget::new_salted("payload variant picker")
+                .ui(ui, selected, |ui| {
+                    for name in VARIANTS {
+                        ui.selectable_value(&mut selected, name, name);
+                    }
+                })
+                .response;
+
+            if selected != variant_name(self.payload) {
+                response.mark_changed();
+                *self.payload = default_variant(selected);
+            }
+
+            match self.payload {
+                EventPayload::None => {}
+                EventPayload::F32(val) => {
+                    response |= ui.add(egui::DragValue::new(val));
+                }
+                EventPayload::Bool(val) => {
+                    response |= ui.add(egui::Checkbox::without_text(val));
+                }
+                EventPayload::Vec2(vec2) => {
+                    response |= ui.add(Vec2Widget::new_salted(vec2, "vec2"));
+                }
+                EventPayload::Vec3(vec3) => {
+                    response |= ui.add(Vec3Widget::new_salted(vec3, "vec3"));
+                }
+                EventPayload::Quat(quat) => {
+                    response |= ui.add(QuatWidget::new_salted(quat, "quat"));
+                }
+                EventPayload::String(string) => {
+                    response |= ui.text_edit_singleline(string);
+                }
+                EventPayload::EntityPath(entity_path) => {
+                    response |= ui.add(EntityPathWidget::new_salted(entity_path, "entity path"));
+                }
+                EventPayload::Reflected { type_path, ron } => {
+                    egui::Grid::new("reflected payload").show(ui, |ui| {
+                        response |= ui.label("type_path:");
+                        response |= ui.text_edit_singleline(type_path);
+                        ui.end_row();
+                        response |= ui.label("ron:");
+                        response |= ui.text_edit_multiline(ron);
+                        ui.end_row();
+                    });
+                }
+            }
+
+            response
+        })
+        .inner
+    }
+}
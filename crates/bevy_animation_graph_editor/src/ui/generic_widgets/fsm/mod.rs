@@ -1,4 +1,5 @@
 pub mod direct_transition;
 pub mod state;
 pub mod state_id_mut;
+pub mod transition_condition;
 pub mod transition_data;
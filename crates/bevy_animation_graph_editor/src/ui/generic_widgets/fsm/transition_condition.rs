@@ -0,0 +1,100 @@
+use bevy_animation_graph::core::state_machine::high_level::condition::{
+    CompareOp, TransitionCondition,
+};
+
+use crate::ui::generic_widgets::picker::PickerWidget;
+
+pub struct TransitionConditionWidget<'a> {
+    pub condition: &'a mut TransitionCondition,
+    pub id_hash: egui::Id,
+}
+
+impl<'a> TransitionConditionWidget<'a> {
+    pub fn new_salted(condition: &'a mut TransitionCondition, salt: impl std::hash::Hash) -> Self {
+        Self {
+            condition,
+            id_hash: egui::Id::new(salt),
+        }
+    }
+}
+
+impl<'a> egui::Widget for TransitionConditionWidget<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.push_id(self.id_hash, |ui| {
+            ui.horizontal(|ui| {
+                let mut tag = match &self.condition {
+                    TransitionCondition::F32 { .. } => TransitionConditionTag::F32,
+                    TransitionCondition::Bool { .. } => TransitionConditionTag::Bool,
+                };
+                let original = tag;
+                let mut response = PickerWidget::new_salted("condition kind")
+                    .ui(ui, format!("{:?}", tag), |ui| {
+                        let mut val = |t| ui.selectable_value(&mut tag, t, format!("{:?}", t));
+                        val(TransitionConditionTag::F32);
+                        val(TransitionConditionTag::Bool);
+                    })
+                    .response;
+
+                if tag != original {
+                    response.mark_changed();
+                    *self.condition = match tag {
+                        TransitionConditionTag::F32 => TransitionCondition::F32 {
+                            input: std::mem::take(input_mut(self.condition)),
+                            op: CompareOp::More,
+                            value: 0.,
+                        },
+                        TransitionConditionTag::Bool => TransitionCondition::Bool {
+                            input: std::mem::take(input_mut(self.condition)),
+                            value: true,
+                        },
+                    };
+                }
+
+                match self.condition {
+                    TransitionCondition::F32 { input, op, value } => {
+                        response |= ui.add(egui::TextEdit::singleline(input).desired_width(80.));
+                        let original_op = *op;
+                        response |= PickerWidget::new_salted("compare op")
+                            .ui(ui, format!("{:?}", op), |ui| {
+                                for val in [
+                                    CompareOp::Less,
+                                    CompareOp::LessEqual,
+                                    CompareOp::More,
+                                    CompareOp::MoreEqual,
+                                    CompareOp::Equal,
+                                ] {
+                                    ui.selectable_value(op, val, format!("{:?}", val));
+                                }
+                            })
+                            .response;
+                        if *op != original_op {
+                            response.mark_changed();
+                        }
+                        response |= ui.add(egui::DragValue::new(value).speed(0.1));
+                    }
+                    TransitionCondition::Bool { input, value } => {
+                        response |= ui.add(egui::TextEdit::singleline(input).desired_width(80.));
+                        response |= ui.label("is");
+                        response |= ui.add(egui::Checkbox::without_text(value));
+                    }
+                }
+
+                response
+            })
+            .inner
+        })
+        .inner
+    }
+}
+
+fn input_mut(condition: &mut TransitionCondition) -> &mut String {
+    match condition {
+        TransitionCondition::F32 { input, .. } | TransitionCondition::Bool { input, .. } => input,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransitionConditionTag {
+    F32,
+    Bool,
+}
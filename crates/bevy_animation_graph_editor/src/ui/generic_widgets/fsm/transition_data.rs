@@ -1,7 +1,14 @@
 use bevy::{ecs::world::World, utils::default};
-use bevy_animation_graph::core::state_machine::high_level::{TransitionData, TransitionKind};
+use bevy_animation_graph::core::{
+    interpolation::inertialization::InertializationFalloff,
+    state_machine::high_level::{
+        TransitionData, TransitionKind,
+        crossfade::{CrossfadeCurve, CrossfadeSync},
+    },
+};
 
 use crate::ui::generic_widgets::{
+    fsm::transition_condition::TransitionConditionWidget, list::ListWidget,
     option::CheapOptionWidget, picker::PickerWidget, popup_asset_picker::PopupAssetPicker,
 };
 
@@ -41,10 +48,29 @@ impl<'a> egui::Widget for TransitionDataWidget<'a> {
                 ));
                 ui.end_row();
 
+                response |= ui.label("conditions:");
+                response |= ListWidget::new_salted(
+                    &mut self.transition_data.conditions,
+                    "transition conditions",
+                )
+                .ui(ui, |ui, condition| {
+                    ui.add(TransitionConditionWidget::new_salted(
+                        condition,
+                        "transition condition",
+                    ))
+                });
+                ui.end_row();
+
+                response |= ui.label("priority:");
+                response |= ui.add(egui::DragValue::new(&mut self.transition_data.priority));
+                ui.end_row();
+
                 response |= ui.label("transition kind:");
                 let mut tag = match &self.transition_data.kind {
                     TransitionKind::Immediate => TransitionKindTag::Immediate,
                     TransitionKind::Graph { .. } => TransitionKindTag::Graph,
+                    TransitionKind::Crossfade { .. } => TransitionKindTag::Crossfade,
+                    TransitionKind::Inertialize { .. } => TransitionKindTag::Inertialize,
                 };
                 let original = tag;
                 response |= PickerWidget::new_salted("transition kind")
@@ -52,6 +78,8 @@ impl<'a> egui::Widget for TransitionDataWidget<'a> {
                         let mut val = |t| ui.selectable_value(&mut tag, t, format!("{:?}", t));
                         val(TransitionKindTag::Immediate);
                         val(TransitionKindTag::Graph);
+                        val(TransitionKindTag::Crossfade);
+                        val(TransitionKindTag::Inertialize);
                     })
                     .response;
 
@@ -67,6 +95,19 @@ impl<'a> egui::Widget for TransitionDataWidget<'a> {
                                 timed: default(),
                             };
                         }
+                        TransitionKindTag::Crossfade => {
+                            self.transition_data.kind = TransitionKind::Crossfade {
+                                duration: 0.2,
+                                curve: default(),
+                                sync: default(),
+                            };
+                        }
+                        TransitionKindTag::Inertialize => {
+                            self.transition_data.kind = TransitionKind::Inertialize {
+                                duration: 0.2,
+                                falloff: default(),
+                            };
+                        }
                     }
                 }
                 ui.end_row();
@@ -90,6 +131,63 @@ impl<'a> egui::Widget for TransitionDataWidget<'a> {
                             })
                             .inner;
                     }
+                    TransitionKind::Crossfade {
+                        duration,
+                        curve,
+                        sync,
+                    } => {
+                        response |= ui.label("duration:");
+                        response |= ui.add(egui::DragValue::new(duration).speed(0.01));
+                        ui.end_row();
+
+                        response |= ui.label("curve:");
+                        let original_curve = *curve;
+                        response |= PickerWidget::new_salted("crossfade curve")
+                            .ui(ui, format!("{:?}", curve), |ui| {
+                                for val in CrossfadeCurve::ALL {
+                                    ui.selectable_value(curve, val, format!("{:?}", val));
+                                }
+                            })
+                            .response;
+                        if *curve != original_curve {
+                            response.mark_changed();
+                        }
+                        ui.end_row();
+
+                        response |= ui.label("sync:");
+                        let original_sync = *sync;
+                        response |= PickerWidget::new_salted("crossfade sync")
+                            .ui(ui, format!("{:?}", sync), |ui| {
+                                for val in [CrossfadeSync::NoSync, CrossfadeSync::NormalizedTime] {
+                                    ui.selectable_value(sync, val, format!("{:?}", val));
+                                }
+                            })
+                            .response;
+                        if *sync != original_sync {
+                            response.mark_changed();
+                        }
+                    }
+                    TransitionKind::Inertialize { duration, falloff } => {
+                        response |= ui.label("duration:");
+                        response |= ui.add(egui::DragValue::new(duration).speed(0.01));
+                        ui.end_row();
+
+                        response |= ui.label("falloff:");
+                        let original_falloff = *falloff;
+                        response |= PickerWidget::new_salted("inertialization falloff")
+                            .ui(ui, format!("{:?}", falloff), |ui| {
+                                for val in [
+                                    InertializationFalloff::Polynomial,
+                                    InertializationFalloff::Spring,
+                                ] {
+                                    ui.selectable_value(falloff, val, format!("{:?}", val));
+                                }
+                            })
+                            .response;
+                        if *falloff != original_falloff {
+                            response.mark_changed();
+                        }
+                    }
                 }
 
                 response
@@ -102,4 +200,6 @@ impl<'a> egui::Widget for TransitionDataWidget<'a> {
 enum TransitionKindTag {
     Immediate,
     Graph,
+    Crossfade,
+    Inertialize,
 }
@@ -9,6 +9,7 @@ pub mod bone_mask;
 pub mod data_spec_widget;
 pub mod data_value;
 pub mod entity_path;
+pub mod event_payload;
 pub mod fsm;
 pub mod graph_input_pin;
 pub mod hash_like;
@@ -76,6 +76,8 @@ impl NativeEditorWindowExtension for CreateAnimWindow {
                                 },
                                 skeleton: asset_path(buffer.skeleton.id().untyped(), asset_server),
                                 event_tracks: Default::default(),
+                                range: None,
+                                loop_range: None,
                             },
                         });
                         queue.trigger(CloseWindow(queue.window_entity));
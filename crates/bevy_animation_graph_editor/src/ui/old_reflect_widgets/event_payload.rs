@@ -0,0 +1,45 @@
+use std::any::Any;
+
+use bevy_animation_graph::core::edge_data::events::EventPayload;
+use bevy_inspector_egui::reflect_inspector::InspectorUi;
+use egui_dock::egui;
+
+use super::{EguiInspectorExtension, MakeBuffer};
+use crate::ui::generic_widgets::event_payload::EventPayloadWidget;
+
+#[derive(Default)]
+pub struct EventPayloadInspector;
+
+impl EguiInspectorExtension for EventPayloadInspector {
+    type Base = EventPayload;
+    type Buffer = ();
+
+    fn mutable(
+        value: &mut Self::Base,
+        _buffer: &mut Self::Buffer,
+        ui: &mut egui::Ui,
+        _options: &dyn Any,
+        id: egui::Id,
+        _env: InspectorUi<'_, '_>,
+    ) -> bool {
+        ui.add(EventPayloadWidget::new_salted(value, id)).changed()
+    }
+
+    fn readonly(
+        value: &Self::Base,
+        _buffer: &Self::Buffer,
+        ui: &mut egui::Ui,
+        _options: &dyn Any,
+        id: egui::Id,
+        _env: InspectorUi<'_, '_>,
+    ) {
+        let mut val = value.clone();
+        ui.add_enabled_ui(false, |ui| {
+            ui.add(EventPayloadWidget::new_salted(&mut val, id))
+        });
+    }
+}
+
+impl MakeBuffer<()> for EventPayload {
+    fn make_buffer(&self) {}
+}
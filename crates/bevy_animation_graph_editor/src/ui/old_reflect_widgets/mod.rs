@@ -16,6 +16,7 @@ use bevy_animation_graph::core::{
     animated_scene::AnimatedScene,
     animation_clip::{EntityPath, GraphClip},
     animation_graph::AnimationGraph,
+    edge_data::events::EventPayload,
     event_track::TrackItemValue,
     ragdoll::{
         bone_mapping::RagdollBoneMap,
@@ -35,6 +36,7 @@ use egui_dock::egui;
 pub mod asset_picker;
 pub mod checkbox;
 pub mod entity_path;
+pub mod event_payload;
 pub mod pattern_mapper;
 pub mod plugin;
 pub mod submittable;
@@ -301,6 +303,7 @@ impl_widget_hash_from_hash! {
     EntityPath,
     bool,
     TrackItemValue,
+    EventPayload,
     Handle<AnimationGraph>,
     Handle<GraphClip>,
     Handle<StateMachine>,
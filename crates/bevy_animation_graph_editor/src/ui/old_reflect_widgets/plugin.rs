@@ -11,13 +11,15 @@ use bevy_animation_graph::core::{
 use super::{
     EguiInspectorExtensionRegistration, asset_picker::AssetPickerInspector,
     checkbox::CheckboxInspector, entity_path::EntityPathInspector,
-    pattern_mapper::PatternMapperInspector, submittable::SubmittableInspector,
-    target_tracks::TargetTracksInspector, vec2_plane::Vec2PlaneInspector,
+    event_payload::EventPayloadInspector, pattern_mapper::PatternMapperInspector,
+    submittable::SubmittableInspector, target_tracks::TargetTracksInspector,
+    vec2_plane::Vec2PlaneInspector,
 };
 pub struct BetterInspectorPlugin;
 impl Plugin for BetterInspectorPlugin {
     fn build(&self, app: &mut App) {
         EntityPathInspector.register(app);
+        EventPayloadInspector.register(app);
         PatternMapperInspector.register(app);
         CheckboxInspector.register(app);
         AssetPickerInspector::<AnimationGraph>::default().register(app);
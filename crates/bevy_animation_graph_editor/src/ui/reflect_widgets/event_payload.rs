@@ -0,0 +1,25 @@
+use bevy_animation_graph::core::edge_data::events::EventPayload;
+
+use crate::ui::{
+    generic_widgets::event_payload::EventPayloadWidget,
+    reflect_lib::{ReflectWidget, ReflectWidgetContext},
+};
+
+#[derive(Default)]
+pub struct EventPayloadReflectWidget;
+
+impl ReflectWidget for EventPayloadReflectWidget {
+    type Target = EventPayload;
+
+    fn draw(
+        &self,
+        ui: &mut egui::Ui,
+        value: &mut Self::Target,
+        _: &ReflectWidgetContext,
+    ) -> egui::Response {
+        ui.add(EventPayloadWidget::new_salted(
+            value,
+            "event payload widget",
+        ))
+    }
+}
@@ -6,13 +6,14 @@ use bevy_animation_graph::core::{
 use crate::ui::{
     reflect_lib::WidgetRegistry,
     reflect_widgets::{
-        data_value::DataValueReflectWidget, hashmap::HashMapReflectWidget,
-        ragdoll_config::RagdollConfigReflectWidget, sorted_map::SortedMapReflectWidget,
-        vec3::Vec3ReflectWidget,
+        data_value::DataValueReflectWidget, event_payload::EventPayloadReflectWidget,
+        hashmap::HashMapReflectWidget, ragdoll_config::RagdollConfigReflectWidget,
+        sorted_map::SortedMapReflectWidget, vec3::Vec3ReflectWidget,
     },
 };
 
 pub mod data_value;
+pub mod event_payload;
 pub mod hashmap;
 pub mod ragdoll_config;
 pub mod sorted_map;
@@ -24,6 +25,7 @@ pub fn register_reflect_widgets(registry: &mut WidgetRegistry) {
         .add(SortedMapReflectWidget::<PinId, DataSpecWithOptionalDefault>::default())
         .add(SortedMapReflectWidget::<PinId, DataValue>::default())
         .add(DataValueReflectWidget)
+        .add(EventPayloadReflectWidget)
         .add(Vec3ReflectWidget)
         .add(RagdollConfigReflectWidget);
 }
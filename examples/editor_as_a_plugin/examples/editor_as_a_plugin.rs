@@ -52,15 +52,16 @@ impl NodeLike for MyCustomNode {
 
         // This node doesn't do anything "useful", but for demonstration purposes let's add some
         // random noise to the translation of each bone that has an animated translation.
-        for bone in &mut in_pose.bones {
-            if let Some(pos) = &mut bone.translation {
+        let bones: Vec<usize> = in_pose.iter_bones().map(|(index, _)| index).collect();
+        for index in bones {
+            if let Some(pos) = in_pose.translation(index) {
                 let offset = Vec3::new(
                     rand::random::<f32>() - 0.5,
                     rand::random::<f32>() - 0.5,
                     rand::random::<f32>() - 0.5,
                 ) * 0.035;
 
-                *pos += offset;
+                in_pose.set_translation(index, pos + offset);
             }
         }
 
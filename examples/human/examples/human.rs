@@ -140,7 +140,7 @@ fn keyboard_animation_control(
     }
     if keyboard_input.pressed(KeyCode::ArrowLeft) {
         params.direction =
-            (Quat::from_rotation_y(-1. * time.delta_secs()) * params.direction).normalize();
+            (Quat::from_rotation_y(-time.delta_secs()) * params.direction).normalize();
     }
 
     player.set_input_data("target_speed", params.speed.into());
@@ -27,7 +27,7 @@ fn main() {
     }))
     .add_plugins(PhysicsPlugins::new(FixedPostUpdate))
     .add_plugins(AnimationGraphPlugin::from_physics_schedule(FixedPostUpdate))
-    .add_plugins(PhysicsDebugPlugin::default())
+    .add_plugins(PhysicsDebugPlugin)
     .add_plugins(bevy_egui::EguiPlugin::default())
     .add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::default())
     .insert_resource(GlobalAmbientLight {
@@ -65,6 +65,7 @@ struct Params {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 enum RagdollMode {
     NoRagdoll,
     PartialRagdoll,
@@ -298,17 +299,16 @@ fn find_target(
         return;
     };
 
-    if let Ok(window) = q_window.single() {
-        if let Some(world_position) = window
+    if let Ok(window) = q_window.single()
+        && let Some(world_position) = window
             .cursor_position()
             .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
             .and_then(|ray| {
                 Some(ray.get_point(ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?))
             })
-        {
-            params.target_position = world_position;
-            gizmos.sphere(Isometry3d::from_translation(world_position), 0.25, GREEN);
-        }
+    {
+        params.target_position = world_position;
+        gizmos.sphere(Isometry3d::from_translation(world_position), 0.25, GREEN);
     }
 }
 
@@ -322,8 +322,8 @@ fn update_params(mut params: ResMut<Params>, time: Res<Time>) {
     let velocity_delta_length = velocity_delta.length();
     let velocity_delta_dir = velocity_delta.normalize_or_zero();
 
-    params.velocity = params.velocity
-        + (velocity_delta_dir * 6. * time.delta_secs()).clamp_length_max(velocity_delta_length);
+    params.velocity +=
+        (velocity_delta_dir * 6. * time.delta_secs()).clamp_length_max(velocity_delta_length);
 
     let direction = params.velocity.normalize_or_zero();
     params.target_angle = direction.z.atan2(direction.x);
@@ -335,7 +335,7 @@ fn update_params(mut params: ResMut<Params>, time: Res<Time>) {
         .sin()
         .atan2((params.target_angle - params.angle).cos());
     let angle_sign = if delta_angle < 0. { -1. } else { 1. };
-    params.angle = params.angle
-        + (angle_sign * 4. * time.delta_secs()).clamp(-delta_angle.abs(), delta_angle.abs());
+    params.angle +=
+        (angle_sign * 4. * time.delta_secs()).clamp(-delta_angle.abs(), delta_angle.abs());
     params.angle = (params.angle + PI).rem_euclid(2. * PI) - PI;
 }
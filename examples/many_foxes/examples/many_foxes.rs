@@ -13,7 +13,10 @@ use bevy::{
     window::{PresentMode, WindowResolution},
     winit::{UpdateMode, WinitSettings},
 };
-use bevy_animation_graph::{AnimationGraphPlugin, core::animated_scene::AnimatedSceneHandle};
+use bevy_animation_graph::{
+    AnimationGraphPlugin,
+    core::{animated_scene::AnimatedSceneHandle, shared_evaluation::SharedEvaluation},
+};
 
 #[derive(FromArgs, Resource)]
 /// `many_foxes` stress test
@@ -21,11 +24,15 @@ struct Args {
     /// total number of foxes.
     #[argh(option, default = "1000")]
     count: usize,
+    /// share graph evaluations between foxes
+    #[argh(switch)]
+    shared: bool,
 }
 
 #[derive(Resource)]
 struct Foxes {
     count: usize,
+    shared: bool,
     speed: f32,
     moving: bool,
 }
@@ -64,6 +71,7 @@ fn main() {
         })
         .insert_resource(Foxes {
             count: args.count,
+            shared: args.shared,
             speed: 2.0,
             moving: true,
         })
@@ -152,12 +160,16 @@ fn setup(
             let (x, z) = (radius * c, radius * s);
 
             commands.entity(ring_parent).with_children(|builder| {
-                builder.spawn((
+                let mut fox = builder.spawn((
                     AnimatedSceneHandle::new(fox_handle.clone()),
                     Transform::from_xyz(x, 0.0, z)
                         .with_scale(Vec3::splat(0.01))
                         .with_rotation(base_rotation * Quat::from_rotation_y(-fox_angle)),
                 ));
+                if foxes.shared {
+                    // Spread the foxes over a few evaluations so they don't all run in lockstep
+                    fox.insert(SharedEvaluation::new((fox_i % 8) as f32 * 0.25));
+                }
             });
         }
 
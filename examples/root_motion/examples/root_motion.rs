@@ -192,10 +192,10 @@ fn keyboard_control(
     mut transforms: Query<&mut Transform, With<Character>>,
 ) {
     if keyboard_input.just_pressed(KeyCode::KeyR) {
-        if let Ok(si) = character.single() {
-            if let Ok(mut player) = animation_players.get_mut(si.player_entity()) {
-                player.reset();
-            }
+        if let Ok(si) = character.single()
+            && let Ok(mut player) = animation_players.get_mut(si.player_entity())
+        {
+            player.reset();
         }
         if let Ok(mut transform) = transforms.single_mut() {
             transform.translation = Vec3::ZERO;